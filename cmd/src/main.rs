@@ -1,13 +1,41 @@
+use symmath::art::ArtOpts;
 use symmath::expr::*;
 use symmath::simplify::*;
+use symmath::parse::parse;
 
 fn main() {
+    let mut input = None;
+    let mut art = ArtOpts::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ascii" => art.ascii = true,
+            "--width" => art.width = args.next().and_then(|w| w.parse().ok()),
+            _ => input = Some(arg)
+        }
+    }
+
     // let mut expr = Expr::pi() + Expr::frac(1, 2) + Expr::frac(4, 5) + Expr::frac(3, 2) * (Expr::frac(4, 2) * Expr::int(10)) + Expr::var("x")*Expr::int(0);
     // let mut expr = (Expr::var("x") + Expr::frac(1, 2)) / Expr::frac(2, 1);
-    let mut expr = Expr::pi() * (Expr::int(1) / Expr::int(6));
+    let mut expr = match input {
+        Some(input) => match parse(&input) {
+            Ok(expr) => expr,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        None => Expr::pi() * (Expr::int(1) / Expr::int(6))
+    };
     // let mut expr = Expr::func(FuncIdentifier::Sine, vec![Expr::int(1) - Expr::int(1)]) + Expr::int(1);
-    println!("Before simplify: {}", expr.to_ascii());
-    expr.simplify(&SimplifcationOpts::expand());
-    expr.simplify(&SimplifcationOpts::integers());
-    println!("After simplify: {}", expr.to_ascii());
-}
\ No newline at end of file
+    println!("Before simplify: {}", expr);
+    let simplified = expr.simplify(&SimplifcationOpts::expand()).and_then(|_| expr.simplify(&SimplifcationOpts::integers()));
+    if let Err(err) = simplified {
+        eprintln!("Cannot simplify: {}", err);
+        std::process::exit(1);
+    }
+    println!("After simplify: {}", expr);
+    println!();
+    println!("{}", expr.to_art(&art));
+}
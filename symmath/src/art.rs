@@ -0,0 +1,376 @@
+use crate::expr::*;
+use crate::num;
+use crate::pretty::unsigned;
+
+#[derive(Default)]
+pub struct ArtOpts {
+    // Only use ASCII characters, for terminals without Unicode box drawing
+    pub ascii: bool,
+    // Maximum line width; a top-level sum is broken between terms to fit
+    pub width: Option<usize>
+}
+
+impl ArtOpts {
+    pub fn ascii() -> ArtOpts {
+        ArtOpts {
+            ascii: true,
+            width: None
+        }
+    }
+
+    pub fn width(width: usize) -> ArtOpts {
+        ArtOpts {
+            ascii: false,
+            width: Some(width)
+        }
+    }
+}
+
+struct Glyphs {
+    bar: char,
+    times: &'static str,
+    // Single-row, top, middle and bottom pieces of each bracket
+    lparen: [char; 4],
+    rparen: [char; 4],
+    abs: char,
+    pi: &'static str,
+    infinity: &'static str
+}
+
+const UNICODE: Glyphs = Glyphs {
+    bar: '─',
+    times: "·",
+    lparen: ['(', '⎛', '⎜', '⎝'],
+    rparen: [')', '⎞', '⎟', '⎠'],
+    abs: '│',
+    pi: "π",
+    infinity: "∞"
+};
+
+const ASCII: Glyphs = Glyphs {
+    bar: '-',
+    times: "*",
+    lparen: ['(', '/', '|', '\\'],
+    rparen: [')', '\\', '|', '/'],
+    abs: '|',
+    pi: "pi",
+    infinity: "inf"
+};
+
+// A rectangle of characters with the row that lines up with surrounding text
+struct Block {
+    rows: Vec<Vec<char>>,
+    baseline: usize
+}
+
+impl Block {
+    fn text(s: &str) -> Block {
+        Block {
+            rows: vec![s.chars().collect()],
+            baseline: 0
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    // Places blocks side by side, lining up their baselines
+    fn hcat(blocks: Vec<Block>) -> Block {
+        let above = blocks.iter().map(|b| b.baseline).max().unwrap_or(0);
+        let below = blocks.iter().map(|b| b.height() - b.baseline - 1).max().unwrap_or(0);
+
+        let mut rows = vec![Vec::new(); above + below + 1];
+        for block in blocks.iter() {
+            let top = above - block.baseline;
+            for (idx, row) in rows.iter_mut().enumerate() {
+                if idx >= top && idx - top < block.height() {
+                    row.extend_from_slice(&block.rows[idx - top]);
+                } else {
+                    row.extend(std::iter::repeat_n(' ', block.width()));
+                }
+            }
+        }
+
+        Block {
+            rows,
+            baseline: above
+        }
+    }
+
+    // Stacks blocks vertically, each centred in the widest
+    fn vcat(blocks: Vec<Block>, baseline: usize) -> Block {
+        let width = blocks.iter().map(|b| b.width()).max().unwrap_or(0);
+        let mut rows = Vec::new();
+        for block in blocks.into_iter() {
+            let left = (width - block.width()) / 2;
+            for row in block.rows.into_iter() {
+                let mut padded = vec![' '; left];
+                padded.extend(row);
+                padded.resize(width, ' ');
+                rows.push(padded);
+            }
+        }
+
+        Block {
+            rows, baseline
+        }
+    }
+
+    fn fraction(num: Block, denom: Block, glyphs: &Glyphs) -> Block {
+        let width = num.width().max(denom.width()) + 2;
+        let bar = Block::text(&std::iter::repeat_n(glyphs.bar, width).collect::<String>());
+        let baseline = num.height();
+        return Block::vcat(vec![num, bar, denom], baseline);
+    }
+
+    fn power(base: Block, exponent: Block) -> Block {
+        let width = base.width() + exponent.width();
+        let mut rows = Vec::new();
+        for row in exponent.rows.iter() {
+            let mut padded = vec![' '; base.width()];
+            padded.extend_from_slice(row);
+            rows.push(padded);
+        }
+        for row in base.rows.iter() {
+            let mut padded = row.clone();
+            padded.resize(width, ' ');
+            rows.push(padded);
+        }
+
+        Block {
+            rows,
+            baseline: exponent.height() + base.baseline
+        }
+    }
+
+    // Surrounds the block with brackets as tall as it is
+    fn bracket(self, left: &[char; 4], right: &[char; 4]) -> Block {
+        let height = self.height();
+        let piece = |pieces: &[char; 4], idx: usize| {
+            if height == 1 { pieces[0] } else if idx == 0 { pieces[1] } else if idx == height - 1 { pieces[3] } else { pieces[2] }
+        };
+
+        let rows = self.rows.into_iter().enumerate().map(|(idx, row)| {
+            let mut out = vec![piece(left, idx)];
+            out.extend(row);
+            out.push(piece(right, idx));
+            out
+        }).collect();
+
+        Block {
+            rows,
+            baseline: self.baseline
+        }
+    }
+
+    fn parens(self, glyphs: &Glyphs) -> Block {
+        self.bracket(&glyphs.lparen, &glyphs.rparen)
+    }
+
+    fn into_string(self) -> String {
+        self.rows.into_iter().map(|row| row.into_iter().collect::<String>().trim_end().to_string()).collect::<Vec<String>>().join("\n")
+    }
+}
+
+// Binding strength when laid out. Stacked fractions delimit themselves, so they bind
+// like a plain symbol except as the base of a power.
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.operator {
+        Operator::Add | Operator::Sub => 1,
+        Operator::Mul => 2,
+        Operator::Pow => 3,
+        _ => 4
+    }
+}
+
+fn is_stacked(expr: &Expr) -> bool {
+    match &expr.operator {
+        Operator::Frac(f) => !f.is_int(),
+        Operator::Div => true,
+        _ => false
+    }
+}
+
+fn frac_block(frac: &num::Frac, glyphs: &Glyphs) -> Block {
+    let block = if frac.is_int() {
+        Block::text(&frac.num().abs().to_string())
+    } else {
+        Block::fraction(Block::text(&frac.num().abs().to_string()), Block::text(&frac.denom().abs().to_string()), glyphs)
+    };
+
+    if frac.is_negative() {
+        Block::hcat(vec![Block::text("-"), block])
+    } else {
+        block
+    }
+}
+
+impl Expr {
+    fn art_operand(&self, min_precedence: u8, glyphs: &Glyphs) -> Block {
+        let negative = unsigned(self).is_some();
+        let block = self.art(glyphs);
+        if precedence(self) < min_precedence || negative {
+            block.parens(glyphs)
+        } else {
+            block
+        }
+    }
+
+    // The terms of a sum or difference, each with the operator that joins it to the previous
+    // one. A leading sum or difference, as in a + b - c, contributes its own terms.
+    fn art_terms(&self, glyphs: &Glyphs) -> Vec<(&'static str, Block)> {
+        let els = self.children();
+        let mut terms = match els[0].operator {
+            Operator::Add | Operator::Sub => els[0].art_terms(glyphs),
+            _ => match unsigned(&els[0]) {
+                Some(term) => vec![("-", term.art_operand(2, glyphs))],
+                None => vec![("", els[0].art_operand(1, glyphs))]
+            }
+        };
+
+        for child in els[1..].iter() {
+            terms.push(match (&self.operator, unsigned(child)) {
+                (Operator::Add, Some(term)) => (" - ", term.art_operand(2, glyphs)),
+                (Operator::Add, None) => (" + ", child.art_operand(2, glyphs)),
+                _ => (" - ", child.art_operand(2, glyphs))
+            });
+        }
+        return terms;
+    }
+
+    fn art(&self, glyphs: &Glyphs) -> Block {
+        if let Some(term) = unsigned(self) {
+            return Block::hcat(vec![Block::text("-"), term.art_operand(2, glyphs)]);
+        }
+
+        match &self.operator {
+            Operator::Frac(frac) => frac_block(frac, glyphs),
+            Operator::Add | Operator::Sub => {
+                let mut blocks = Vec::new();
+                for (op, term) in self.art_terms(glyphs).into_iter() {
+                    blocks.push(Block::text(op));
+                    blocks.push(term);
+                }
+                Block::hcat(blocks)
+            },
+            Operator::Mul => {
+                let els = self.children();
+                // A trailing reciprocal, as in x * (1/6), is drawn as x over 6
+                if let Some(Operator::Frac(f)) = els.last().map(|el| &el.operator) {
+                    if f.num() == 1 && f.denom() > 1 && els.len() > 1 {
+                        let rest = &els[..els.len() - 1];
+                        let num = if rest.len() == 1 { rest[0].art(glyphs) } else { Expr::new(Operator::Mul, Some(rest.to_vec())).art(glyphs) };
+                        return Block::fraction(num, Block::text(&f.denom().to_string()), glyphs);
+                    }
+                }
+
+                let mut blocks = Vec::new();
+                for (idx, child) in els.iter().enumerate() {
+                    if idx != 0 { blocks.push(Block::text(glyphs.times)); }
+                    blocks.push(child.art_operand(3, glyphs));
+                }
+                Block::hcat(blocks)
+            },
+            Operator::Div => {
+                let els = self.children();
+                Block::fraction(els[0].art(glyphs), els[1].art(glyphs), glyphs)
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let base = if is_stacked(&els[0]) { els[0].art(glyphs).parens(glyphs) } else { els[0].art_operand(4, glyphs) };
+                Block::power(base, els[1].art(glyphs))
+            },
+            Operator::Name(name) => Block::text(name),
+            Operator::Func(FuncIdentifier::Abs) => {
+                let args = self.children().iter().map(|el| el.art(glyphs)).collect::<Vec<Block>>();
+                Block::hcat(args).bracket(&[glyphs.abs; 4], &[glyphs.abs; 4])
+            },
+            Operator::Func(ident) => {
+                let mut args = Vec::new();
+                for (idx, child) in self.children().iter().enumerate() {
+                    if idx != 0 { args.push(Block::text(", ")); }
+                    args.push(child.art(glyphs));
+                }
+                Block::hcat(vec![Block::text(&ident.get_name()), Block::hcat(args).parens(glyphs)])
+            },
+            Operator::Const(Constant::Pi) => Block::text(glyphs.pi),
+            Operator::Const(Constant::E) => Block::text("e"),
+            Operator::Const(Constant::I) => Block::text("i"),
+            Operator::Const(Constant::Infinity) => Block::text(glyphs.infinity)
+        }
+    }
+
+    // Lays the expression out in two dimensions for a terminal, with stacked fractions
+    // and raised exponents
+    pub fn to_art(&self, opts: &ArtOpts) -> String {
+        let glyphs = if opts.ascii { &ASCII } else { &UNICODE };
+
+        let width = match (opts.width, &self.operator) {
+            (Some(width), Operator::Add | Operator::Sub) => width,
+            _ => return self.art(glyphs).into_string()
+        };
+
+        // Pack as many terms as fit on each line, starting continuation lines with their operator
+        let mut lines: Vec<Vec<Block>> = vec![Vec::new()];
+        let mut used = 0;
+        for (op, term) in self.art_terms(glyphs).into_iter() {
+            if used != 0 && used + op.chars().count() + term.width() > width {
+                lines.push(Vec::new());
+                used = 0;
+            }
+
+            let op = if used == 0 { op.trim_start() } else { op };
+            used += op.chars().count() + term.width();
+            let line = lines.last_mut().unwrap();
+            line.push(Block::text(op));
+            line.push(term);
+        }
+
+        return lines.into_iter().map(|line| Block::hcat(line).into_string()).collect::<Vec<String>>().join("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArtOpts;
+    use crate::parse::parse;
+
+    fn art(input: &str, opts: &ArtOpts) -> String {
+        parse(input).unwrap().to_art(opts)
+    }
+
+    #[test]
+    fn fractions_and_powers() {
+        assert_eq!(art("1/2 + x^2", &ArtOpts::default()), concat!(
+            " 1     2\n",
+            "─── + x\n",
+            " 2"
+        ));
+        assert_eq!(art("(a + 1)/(b*c) * pi", &ArtOpts::default()), concat!(
+            " a + 1\n",
+            "───────·π\n",
+            "  b·c"
+        ));
+        assert_eq!(art("(x/y)^-2 - abs(z/4)", &ArtOpts::ascii()), concat!(
+            "     -2\n",
+            "/ x \\     | z |\n",
+            "|---|   - |---|\n",
+            "\\ y /     | 4 |"
+        ));
+    }
+
+    #[test]
+    fn line_breaking() {
+        assert_eq!(art("a^2 + 2*a*b + b^2 - sin(c)", &ArtOpts::width(14)), concat!(
+            " 2\n",
+            "a  + 2·a·b\n",
+            "   2\n",
+            "+ b  - sin(c)"
+        ));
+    }
+}
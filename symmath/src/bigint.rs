@@ -0,0 +1,395 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// Sign and magnitude integer of any size. The magnitude is stored as base 2^32 limbs,
+// least significant first, with no trailing zero limbs, so zero is an empty magnitude
+// and is never negative.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
+pub(crate) struct BigInt {
+    negative: bool,
+    mag: Vec<u32>
+}
+
+fn trim(mut mag: Vec<u32>) -> Vec<u32> {
+    while mag.last() == Some(&0) {
+        mag.pop();
+    }
+    return mag;
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    if a.len() != b.len() {
+        return a.len().cmp(&b.len());
+    }
+    for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+        if x != y {
+            return x.cmp(y);
+        }
+    }
+    return Ordering::Equal;
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut out = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (idx, x) in long.iter().enumerate() {
+        let sum = *x as u64 + *short.get(idx).unwrap_or(&0) as u64 + carry;
+        out.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry != 0 {
+        out.push(carry as u32);
+    }
+    return out;
+}
+
+// Requires a >= b
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (idx, x) in a.iter().enumerate() {
+        let diff = *x as i64 - *b.get(idx).unwrap_or(&0) as i64 - borrow;
+        out.push(diff as u32);
+        borrow = if diff < 0 { 1 } else { 0 };
+    }
+    return trim(out);
+}
+
+fn mul_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut out = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let prod = *x as u64 * *y as u64 + out[i + j] as u64 + carry;
+            out[i + j] = prod as u32;
+            carry = prod >> 32;
+        }
+        out[i + b.len()] = carry as u32;
+    }
+    return trim(out);
+}
+
+fn divrem_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+    let mut quot = vec![0u32; a.len()];
+    let mut rem = 0u64;
+    for idx in (0..a.len()).rev() {
+        let cur = (rem << 32) | a[idx] as u64;
+        quot[idx] = (cur / d as u64) as u32;
+        rem = cur % d as u64;
+    }
+    return (trim(quot), rem as u32);
+}
+
+// Leaves one extra limb on top for the bits shifted out
+fn shl_mag(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        let mut out = a.to_vec();
+        out.push(0);
+        return out;
+    }
+    let mut out = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u32;
+    for x in a.iter() {
+        out.push((x << shift) | carry);
+        carry = x >> (32 - shift);
+    }
+    out.push(carry);
+    return out;
+}
+
+fn shr_mag(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return trim(a.to_vec());
+    }
+    let mut out = vec![0u32; a.len()];
+    for idx in 0..a.len() {
+        let high = if idx + 1 < a.len() { a[idx + 1] << (32 - shift) } else { 0 };
+        out[idx] = (a[idx] >> shift) | high;
+    }
+    return trim(out);
+}
+
+// Schoolbook long division (Knuth's algorithm D). Requires b to be non-zero.
+fn divrem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_mag(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+    if b.len() == 1 {
+        let (quot, rem) = divrem_small(a, b[0]);
+        return (quot, trim(vec![rem]));
+    }
+
+    // Normalise so the divisor's top limb has its high bit set, which keeps each
+    // estimated quotient digit at most two too large
+    let shift = b.last().unwrap().leading_zeros();
+    let b = trim(shl_mag(b, shift));
+    let mut a = shl_mag(a, shift);
+
+    let n = b.len();
+    let m = a.len() - n;
+    let base = 1u64 << 32;
+    let mut quot = vec![0u32; m];
+
+    for j in (0..m).rev() {
+        let top = ((a[j + n] as u64) << 32) | a[j + n - 1] as u64;
+        let mut qhat = top / b[n - 1] as u64;
+        let mut rhat = top % b[n - 1] as u64;
+        while qhat >= base || qhat * b[n - 2] as u64 > ((rhat << 32) | a[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += b[n - 1] as u64;
+            if rhat >= base { break; }
+        }
+
+        // Subtract qhat * b from the current window of a
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for idx in 0..n {
+            let prod = qhat * b[idx] as u64 + carry;
+            carry = prod >> 32;
+            let diff = a[idx + j] as i64 - borrow - (prod & 0xffff_ffff) as i64;
+            a[idx + j] = diff as u32;
+            borrow = if diff < 0 { 1 } else { 0 };
+        }
+        let diff = a[j + n] as i64 - borrow - carry as i64;
+        a[j + n] = diff as u32;
+
+        // The estimate was one too large, so add the divisor back
+        if diff < 0 {
+            qhat -= 1;
+            let mut carry = 0u64;
+            for idx in 0..n {
+                let sum = a[idx + j] as u64 + b[idx] as u64 + carry;
+                a[idx + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            a[j + n] = a[j + n].wrapping_add(carry as u32);
+        }
+        quot[j] = qhat as u32;
+    }
+
+    return (trim(quot), shr_mag(&a[..n], shift));
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mag: Vec<u32>) -> BigInt {
+        let mag = trim(mag);
+        BigInt {
+            negative: negative && !mag.is_empty(),
+            mag
+        }
+    }
+
+    pub fn from_i64(val: i64) -> BigInt {
+        let abs = val.unsigned_abs();
+        BigInt::from_parts(val < 0, vec![abs as u32, (abs >> 32) as u32])
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.mag.len() > 2 {
+            return None;
+        }
+        let abs = self.mag.iter().rev().fold(0u64, |acc, limb| (acc << 32) | *limb as u64);
+        if self.negative {
+            if abs > i64::MIN.unsigned_abs() { None } else { Some((abs as i64).wrapping_neg()) }
+        } else {
+            if abs > i64::MAX as u64 { None } else { Some(abs as i64) }
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let abs = self.mag.iter().rev().fold(0.0, |acc, limb| acc * 4294967296.0 + *limb as f64);
+        if self.negative { -abs } else { abs }
+    }
+
+    // Reads an optionally signed string of decimal digits
+    pub fn parse(text: &str) -> Option<BigInt> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text)
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut mag = Vec::new();
+        for chunk in digits.as_bytes().chunks(9) {
+            let chunk = std::str::from_utf8(chunk).unwrap();
+            let scale = 10u32.pow(chunk.len() as u32);
+            mag = add_mag(&mul_mag(&mag, &[scale]), &trim(vec![chunk.parse::<u32>().unwrap()]));
+        }
+        return Some(BigInt::from_parts(negative, mag));
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn neg(&self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.mag.clone())
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_mag(&self.mag, &other.mag));
+        }
+        match cmp_mag(&self.mag, &other.mag) {
+            Ordering::Less => BigInt::from_parts(other.negative, sub_mag(&other.mag, &self.mag)),
+            _ => BigInt::from_parts(self.negative, sub_mag(&self.mag, &other.mag))
+        }
+    }
+
+    pub fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != other.negative, mul_mag(&self.mag, &other.mag))
+    }
+
+    // Number of significant bits in the magnitude
+    pub fn bits(&self) -> u64 {
+        match self.mag.last() {
+            Some(top) => self.mag.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0
+        }
+    }
+
+    pub fn shl(&self, bits: u64) -> BigInt {
+        let mut mag = vec![0u32; (bits / 32) as usize];
+        mag.extend(shl_mag(&self.mag, (bits % 32) as u32));
+        BigInt::from_parts(self.negative, mag)
+    }
+
+    // Shifts the magnitude, so like division this truncates towards zero
+    pub fn shr(&self, bits: u64) -> BigInt {
+        let limbs = (bits / 32) as usize;
+        if limbs >= self.mag.len() {
+            return BigInt::from_parts(false, Vec::new());
+        }
+        BigInt::from_parts(self.negative, shr_mag(&self.mag[limbs..], (bits % 32) as u32))
+    }
+
+    // Truncating division, so the remainder takes the sign of the dividend as with i64
+    pub fn divrem(&self, other: &BigInt) -> (BigInt, BigInt) {
+        if other.is_zero() {
+            panic!("attempt to divide by zero");
+        }
+        let (quot, rem) = divrem_mag(&self.mag, &other.mag);
+        (BigInt::from_parts(self.negative != other.negative, quot), BigInt::from_parts(self.negative, rem))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag)
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peel off nine decimal digits at a time, least significant first
+        let mut chunks = Vec::new();
+        let mut mag = self.mag.clone();
+        while !mag.is_empty() {
+            let (quot, rem) = divrem_small(&mag, 1_000_000_000);
+            chunks.push(rem);
+            mag = quot;
+        }
+
+        let mut out = String::new();
+        if self.negative { out.push('-'); }
+        out.push_str(&chunks.last().unwrap().to_string());
+        for chunk in chunks.iter().rev().skip(1) {
+            out.push_str(&format!("{:09}", chunk));
+        }
+        return write!(f, "{}", out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigInt;
+
+    fn big(val: i128) -> BigInt {
+        BigInt::parse(&val.to_string()).unwrap()
+    }
+
+    #[test]
+    fn matches_native_arithmetic() {
+        // A fixed linear congruential sequence covers a spread of limb counts and signs
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let bits = (state >> 58) as u32;
+            ((state as i64) >> bits) as i128
+        };
+
+        for _ in 0..2000 {
+            let (a, b) = (next(), next());
+            assert_eq!(big(a).add(&big(b)), big(a + b));
+            assert_eq!(big(a).sub(&big(b)), big(a - b));
+            assert_eq!(big(a).mul(&big(b)), big(a * b));
+            if b != 0 {
+                let wide = a * (1 << 40) + 12345;
+                let (quot, rem) = big(wide).divrem(&big(b));
+                assert_eq!((quot, rem), (big(wide / b), big(wide % b)), "{} / {}", wide, b);
+            }
+            assert_eq!(big(a).to_string(), a.to_string());
+            assert_eq!(big(a).cmp(&big(b)), a.cmp(&b));
+        }
+    }
+
+    #[test]
+    fn shifts() {
+        for val in [0i128, 1, -1, 12345678901234567, -98765432109876543210].iter() {
+            for bits in [0u64, 1, 31, 32, 33, 40].iter() {
+                assert_eq!(big(*val).shl(*bits).to_string(), (val << bits).to_string());
+                assert_eq!(big(*val).shr(*bits + 30).to_string(), ((val.abs() >> (bits + 30)) * val.signum()).to_string());
+            }
+        }
+        assert_eq!(big(0).bits(), 0);
+        assert_eq!(big(1).bits(), 1);
+        assert_eq!(big(-(1i128 << 64)).bits(), 65);
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(BigInt::from_i64(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(BigInt::from_i64(i64::MAX).add(&BigInt::from_i64(1)).to_i64(), None);
+        assert_eq!(BigInt::from_i64(-7).to_string(), "-7");
+        assert_eq!(BigInt::parse("-0").unwrap(), BigInt::from_i64(0));
+        assert_eq!(BigInt::parse("1e3"), None);
+
+        let factorial = (1..=30).fold(BigInt::from_i64(1), |acc, n| acc.mul(&BigInt::from_i64(n)));
+        assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+        let (quot, rem) = factorial.divrem(&BigInt::parse("265252859812191058636308480").unwrap());
+        assert_eq!((quot.to_i64(), rem.is_zero()), (Some(1000000), true));
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use crate::eval::{check_arity, pow_f64, EvalError};
+use crate::expr::*;
+
+#[derive(Clone)]
+#[derive(Debug)]
+enum Instr {
+    Push(f64),
+    Load(usize),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    PowInt(i32),
+    // A constant rational exponent, with whether its denominator and numerator are odd so
+    // that odd roots of negative bases stay real as in `eval_f64`
+    PowRational { exponent: f64, odd_root: bool, odd_power: bool },
+    Sin,
+    Cos,
+    Tan,
+    Abs
+}
+
+// A flat stack program for one expression. Variables are read from input slots in the
+// order given to `Expr::compile`, and subtrees without variables are folded to constants.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Compiled {
+    code: Vec<Instr>,
+    vars: Vec<String>,
+    stack_size: usize
+}
+
+fn has_variables(expr: &Expr) -> bool {
+    match &expr.operator {
+        Operator::Name(_) => true,
+        _ => expr.elements.as_ref().is_some_and(|els| els.iter().any(has_variables))
+    }
+}
+
+struct Compiler<'a> {
+    slots: HashMap<&'a str, usize>,
+    code: Vec<Instr>,
+    depth: usize,
+    max_depth: usize
+}
+
+impl<'a> Compiler<'a> {
+    fn emit(&mut self, instr: Instr) {
+        match instr {
+            Instr::Push(_) | Instr::Load(_) => {
+                self.depth += 1;
+                self.max_depth = self.max_depth.max(self.depth);
+            },
+            Instr::Add | Instr::Sub | Instr::Mul | Instr::Div | Instr::Pow => self.depth -= 1,
+            _ => {}
+        }
+        self.code.push(instr);
+    }
+
+    // Compiles each operand and combines them left to right with `op`
+    fn fold(&mut self, operands: &[Expr], op: Instr) -> Result<(), EvalError> {
+        for (idx, operand) in operands.iter().enumerate() {
+            self.expr(operand)?;
+            if idx != 0 { self.emit(op.clone()); }
+        }
+        return Ok(());
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<(), EvalError> {
+        if !has_variables(expr) {
+            let val = expr.eval_f64(&HashMap::new())?;
+            self.emit(Instr::Push(val));
+            return Ok(());
+        }
+
+        match &expr.operator {
+            Operator::Add => self.fold(expr.children(), Instr::Add)?,
+            Operator::Mul => self.fold(expr.children(), Instr::Mul)?,
+            Operator::Sub => self.fold(expr.children(), Instr::Sub)?,
+            Operator::Div => self.fold(&expr.children()[..2], Instr::Div)?,
+            Operator::Pow => {
+                let els = expr.children();
+                self.expr(&els[0])?;
+                match &els[1].operator {
+                    Operator::Frac(f) if f.is_int() && f.num().to_i64().is_some_and(|n| n.abs() <= i32::MAX as i64) => {
+                        self.emit(Instr::PowInt(f.num().to_i64().unwrap() as i32));
+                    },
+                    Operator::Frac(f) => {
+                        let two = crate::num::Int::from(2);
+                        self.emit(Instr::PowRational {
+                            exponent: f.to_f64(),
+                            odd_root: f.denom() % &two != 0,
+                            odd_power: f.num() % &two != 0
+                        });
+                    },
+                    _ => {
+                        self.expr(&els[1])?;
+                        self.emit(Instr::Pow);
+                    }
+                }
+            },
+            Operator::Name(name) => {
+                let slot = *self.slots.get(name.as_str()).ok_or_else(|| EvalError::UnboundVariable(name.clone()))?;
+                self.emit(Instr::Load(slot));
+            },
+            Operator::Func(FuncIdentifier::Name(name)) => return Err(EvalError::UnknownFunction(name.clone())),
+            Operator::Func(ident) => {
+                let args = expr.children();
+                check_arity(&ident.get_name(), args, 1)?;
+                self.expr(&args[0])?;
+                self.emit(match ident {
+                    FuncIdentifier::Sine => Instr::Sin,
+                    FuncIdentifier::Cosine => Instr::Cos,
+                    FuncIdentifier::Tangent => Instr::Tan,
+                    FuncIdentifier::Abs => Instr::Abs,
+                    FuncIdentifier::Name(_) => unreachable!()
+                });
+            },
+            // Leaves without variables were folded above
+            Operator::Frac(_) | Operator::Const(_) => unreachable!()
+        }
+        return Ok(());
+    }
+}
+
+impl Expr {
+    // Compiles the expression for repeated evaluation, reading each variable from the
+    // input slot at its position in `vars`
+    pub fn compile(&self, vars: &[&str]) -> Result<Compiled, EvalError> {
+        let mut compiler = Compiler {
+            slots: vars.iter().enumerate().map(|(slot, name)| (*name, slot)).collect(),
+            code: Vec::new(),
+            depth: 0,
+            max_depth: 0
+        };
+        compiler.expr(self)?;
+
+        return Ok(Compiled {
+            code: compiler.code,
+            vars: vars.iter().map(|name| String::from(*name)).collect(),
+            stack_size: compiler.max_depth
+        });
+    }
+}
+
+impl Compiled {
+    pub fn vars(&self) -> &[String] {
+        &self.vars
+    }
+
+    fn run(&self, inputs: &[f64], stack: &mut Vec<f64>) -> f64 {
+        stack.clear();
+        for instr in self.code.iter() {
+            match *instr {
+                Instr::Push(val) => stack.push(val),
+                Instr::Load(slot) => stack.push(inputs[slot]),
+                Instr::Add => {
+                    let rhs = stack.pop().unwrap();
+                    *stack.last_mut().unwrap() += rhs;
+                },
+                Instr::Sub => {
+                    let rhs = stack.pop().unwrap();
+                    *stack.last_mut().unwrap() -= rhs;
+                },
+                Instr::Mul => {
+                    let rhs = stack.pop().unwrap();
+                    *stack.last_mut().unwrap() *= rhs;
+                },
+                Instr::Div => {
+                    let rhs = stack.pop().unwrap();
+                    *stack.last_mut().unwrap() /= rhs;
+                },
+                Instr::Pow => {
+                    let rhs = stack.pop().unwrap();
+                    let top = stack.last_mut().unwrap();
+                    *top = pow_f64(*top, rhs, None);
+                },
+                Instr::PowInt(n) => {
+                    let top = stack.last_mut().unwrap();
+                    *top = top.powi(n);
+                },
+                Instr::PowRational { exponent, odd_root, odd_power } => {
+                    let top = stack.last_mut().unwrap();
+                    *top = if *top < 0.0 && odd_root {
+                        let magnitude = (-*top).powf(exponent);
+                        if odd_power { -magnitude } else { magnitude }
+                    } else {
+                        top.powf(exponent)
+                    };
+                },
+                Instr::Sin => {
+                    let top = stack.last_mut().unwrap();
+                    *top = top.sin();
+                },
+                Instr::Cos => {
+                    let top = stack.last_mut().unwrap();
+                    *top = top.cos();
+                },
+                Instr::Tan => {
+                    let top = stack.last_mut().unwrap();
+                    *top = top.tan();
+                },
+                Instr::Abs => {
+                    let top = stack.last_mut().unwrap();
+                    *top = top.abs();
+                }
+            }
+        }
+        return stack[0];
+    }
+
+    // Evaluates with `inputs[slot]` bound to each variable. Where eval_f64 reports a
+    // division by zero or a value that is not real, this gives an infinity or NaN instead.
+    pub fn eval(&self, inputs: &[f64]) -> f64 {
+        assert_eq!(inputs.len(), self.vars.len(), "expected one input per variable");
+        let mut stack = Vec::with_capacity(self.stack_size);
+        return self.run(inputs, &mut stack);
+    }
+
+    // Evaluates once per row, where `columns[slot]` holds every value of that slot's variable
+    // and `out` receives one result per row
+    pub fn eval_batch(&self, columns: &[&[f64]], out: &mut [f64]) {
+        assert_eq!(columns.len(), self.vars.len(), "expected one input column per variable");
+        assert!(columns.iter().all(|column| column.len() == out.len()), "input columns and output differ in length");
+
+        let mut stack = Vec::with_capacity(self.stack_size);
+        let mut row = vec![0.0; columns.len()];
+        for (idx, result) in out.iter_mut().enumerate() {
+            for (slot, column) in columns.iter().enumerate() {
+                row[slot] = column[idx];
+            }
+            *result = self.run(&row, &mut stack);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::eval::EvalError;
+    use crate::parse::parse;
+
+    #[test]
+    fn matches_tree_walking() {
+        let inputs = ["sin(x)^2 + cos(y)*x/3 - abs(x - y)^(1/3)", "(x - y)^(2/3) + 2*pi*x*y / (1 + x^2)", "tan(e*x)^y - 10 + x^-3", "x"];
+        for input in inputs.iter() {
+            let expr = parse(input).unwrap();
+            let compiled = expr.compile(&["x", "y"]).unwrap();
+            for (x, y) in [(0.5, 2.0), (-1.25, 3.5), (4.0, -0.75)].iter() {
+                let env = [(String::from("x"), *x), (String::from("y"), *y)].iter().cloned().collect::<HashMap<String, f64>>();
+                let actual = compiled.eval(&[*x, *y]);
+                match expr.eval_f64(&env) {
+                    Ok(expected) => assert!((expected - actual).abs() <= 1e-12 * expected.abs().max(1.0), "{}: {} != {}", input, expected, actual),
+                    // The compiled form has no errors and gives NaN instead
+                    Err(err) => assert!(err == EvalError::NotReal && actual.is_nan(), "{}: {} but got {}", input, err, actual)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn batch_and_slot_order() {
+        let compiled = parse("a - 2*b").unwrap().compile(&["b", "a"]).unwrap();
+        assert_eq!(compiled.vars(), ["b", "a"]);
+        let mut out = [0.0; 3];
+        compiled.eval_batch(&[&[1.0, 2.0, 3.0], &[10.0, 20.0, 30.0]], &mut out);
+        assert_eq!(out, [8.0, 16.0, 24.0]);
+
+        assert_eq!(parse("x + y").unwrap().compile(&["x"]).unwrap_err(), EvalError::UnboundVariable(String::from("y")));
+        assert_eq!(parse("2 * g(x)").unwrap().compile(&["x"]).unwrap_err(), EvalError::UnknownFunction(String::from("g")));
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use crate::eval::{check_arity, EvalError};
+use crate::expr::*;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn real(re: f64) -> Complex {
+        Complex::new(re, 0.0)
+    }
+
+    pub fn i() -> Complex {
+        Complex::new(0.0, 1.0)
+    }
+
+    pub fn conj(self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    // The modulus
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn exp(self) -> Complex {
+        let scale = self.re.exp();
+        Complex::new(scale * self.im.cos(), scale * self.im.sin())
+    }
+
+    // The principal logarithm, with its imaginary part in (-pi, pi]
+    pub fn ln(self) -> Complex {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    pub fn powi(self, n: i32) -> Complex {
+        let mut out = Complex::real(1.0);
+        let mut base = if n < 0 { Complex::real(1.0) / self } else { self };
+        let mut n = n.unsigned_abs();
+        while n > 0 {
+            if n % 2 == 1 {
+                out = out * base;
+            }
+            base = base * base;
+            n /= 2;
+        }
+        return out;
+    }
+
+    // The principal value exp(w ln z), taking 0^w as 0 when w has a positive real part
+    pub fn powc(self, exponent: Complex) -> Complex {
+        if self.re == 0.0 && self.im == 0.0 {
+            if exponent.re == 0.0 && exponent.im == 0.0 {
+                return Complex::real(1.0);
+            }
+            if exponent.re > 0.0 {
+                return Complex::real(0.0);
+            }
+        }
+        return (exponent * self.ln()).exp();
+    }
+
+    pub fn sin(self) -> Complex {
+        Complex::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    pub fn cos(self) -> Complex {
+        Complex::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+    }
+
+    pub fn tan(self) -> Complex {
+        self.sin() / self.cos()
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        let norm = other.re * other.re + other.im * other.im;
+        let num = self * other.conj();
+        Complex::new(num.re / norm, num.im / norm)
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.im < 0.0 {
+            write!(f, "{} - {}i", self.re, -self.im)
+        } else {
+            write!(f, "{} + {}i", self.re, self.im)
+        }
+    }
+}
+
+impl Expr {
+    // Unlike eval_f64, powers take their principal value, so (-8)^(1/3) is 1 + 1.732i
+    // rather than -2
+    pub fn eval_complex(&self, env: &HashMap<String, Complex>) -> Result<Complex, EvalError> {
+        match &self.operator {
+            Operator::Frac(f) => Ok(Complex::real(f.to_f64())),
+            Operator::Add => {
+                let mut sum = Complex::real(0.0);
+                for child in self.children().iter() {
+                    sum = sum + child.eval_complex(env)?;
+                }
+                Ok(sum)
+            },
+            Operator::Mul => {
+                let mut product = Complex::real(1.0);
+                for child in self.children().iter() {
+                    product = product * child.eval_complex(env)?;
+                }
+                Ok(product)
+            },
+            Operator::Div => {
+                let els = self.children();
+                Ok(els[0].eval_complex(env)? / els[1].eval_complex(env)?)
+            },
+            Operator::Sub => {
+                let els = self.children();
+                let mut diff = els[0].eval_complex(env)?;
+                for child in els[1..].iter() {
+                    diff = diff - child.eval_complex(env)?;
+                }
+                Ok(diff)
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let base = els[0].eval_complex(env)?;
+                match &els[1].operator {
+                    Operator::Frac(f) if f.is_int() && f.num().abs() <= i32::MAX as i64 => Ok(base.powi(f.num().to_i64().unwrap() as i32)),
+                    _ => Ok(base.powc(els[1].eval_complex(env)?))
+                }
+            },
+            Operator::Name(name) => env.get(name).copied().ok_or_else(|| EvalError::UnboundVariable(name.clone())),
+            Operator::Func(FuncIdentifier::Name(name)) => Err(EvalError::UnknownFunction(name.clone())),
+            Operator::Func(ident) => {
+                let args = self.children();
+                check_arity(&ident.get_name(), args, 1)?;
+                let z = args[0].eval_complex(env)?;
+                Ok(match ident {
+                    FuncIdentifier::Sine => z.sin(),
+                    FuncIdentifier::Cosine => z.cos(),
+                    FuncIdentifier::Tangent => z.tan(),
+                    FuncIdentifier::Abs => Complex::real(z.abs()),
+                    FuncIdentifier::Name(_) => unreachable!()
+                })
+            },
+            Operator::Const(Constant::Pi) => Ok(Complex::real(std::f64::consts::PI)),
+            Operator::Const(Constant::E) => Ok(Complex::real(std::f64::consts::E)),
+            Operator::Const(Constant::I) => Ok(Complex::i()),
+            Operator::Const(Constant::Infinity) => Ok(Complex::real(f64::INFINITY))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::parse::parse;
+    use super::Complex;
+
+    fn eval(input: &str, env: &[(&str, Complex)]) -> Complex {
+        let env = env.iter().map(|(name, val)| (String::from(*name), *val)).collect::<HashMap<String, Complex>>();
+        parse(input).unwrap().eval_complex(&env).unwrap()
+    }
+
+    fn close(a: Complex, b: Complex) -> bool {
+        (a - b).abs() < 1e-12 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn evaluates_with_i() {
+        assert_eq!(eval("(1 + 2*i) * (3 - i)", &[]), Complex::new(5.0, 5.0));
+        assert_eq!(eval("abs(3 + 4*i)", &[]), Complex::real(5.0));
+        assert_eq!(eval("i^-3", &[]), Complex::i());
+        assert!(close(eval("e^(i*pi) + 1", &[]), Complex::real(0.0)));
+        assert!(close(eval("(-8)^(1/3)", &[]), Complex::new(1.0, 3f64.sqrt())));
+        assert!(close(eval("cos(i)^2 + sin(i)^2", &[]), Complex::real(1.0)));
+
+        // The impedance of a resistor in series with a capacitor and inductor in parallel
+        let z = eval("r + 1/(1/(i*w*l) + i*w*c)", &[("r", Complex::real(50.0)), ("w", Complex::real(1000.0)), ("l", Complex::real(0.1)), ("c", Complex::real(1e-6))]);
+        assert!(close(z, Complex::new(50.0, 1000.0 / 9.0)));
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use crate::expr::*;
+use crate::num::NumError;
+
+type Memo = HashMap<(Expr, String), Expr>;
+
+fn func(name: &str, arg: Expr) -> Expr {
+    Expr::func(FuncIdentifier::Name(String::from(name)), vec![arg])
+}
+
+// The name given to the derivative of an unknown function in its `idx`th argument, f' for a
+// function of one argument and f_1, f_2, ... for partial derivatives otherwise
+fn derivative_name(name: &str, idx: usize, arity: usize) -> String {
+    if arity == 1 {
+        return format!("{}'", name);
+    }
+    return format!("{}_{}", name, idx + 1);
+}
+
+impl Expr {
+    pub(crate) fn depends_on(&self, var: &str) -> bool {
+        match &self.operator {
+            Operator::Name(name) => name == var,
+            _ => self.elements.as_ref().is_some_and(|els| els.iter().any(|el| el.depends_on(var)))
+        }
+    }
+
+    // The derivative before simplification, looked up in `memo` first. Subtrees that do
+    // not mention `var` are cut off as 0 so that the rules below only ever see the parts
+    // that matter.
+    fn derivative(&self, var: &str, memo: &mut Memo) -> Expr {
+        if !self.depends_on(var) {
+            return Expr::int(0);
+        }
+        let key = (self.clone(), String::from(var));
+        if let Some(known) = memo.get(&key) {
+            return known.clone();
+        }
+        let out = self.derivative_rule(var, memo);
+        memo.insert(key, out.clone());
+        return out;
+    }
+
+    fn derivative_rule(&self, var: &str, memo: &mut Memo) -> Expr {
+        match &self.operator {
+            Operator::Name(_) => Expr::int(1),
+            Operator::Add => Expr::new(Operator::Add, Some(self.children().iter().map(|el| el.derivative(var, memo)).collect())),
+            Operator::Sub => Expr::new(Operator::Sub, Some(self.children().iter().map(|el| el.derivative(var, memo)).collect())),
+            // (uvw)' = u'vw + uv'w + uvw'
+            Operator::Mul => {
+                let els = self.children();
+                let mut terms = Vec::new();
+                for idx in 0..els.len() {
+                    if !els[idx].depends_on(var) {
+                        continue;
+                    }
+                    let mut factors = els.clone();
+                    factors[idx] = els[idx].derivative(var, memo);
+                    terms.push(Expr::new(Operator::Mul, Some(factors)));
+                }
+                Expr::new(Operator::Add, Some(terms))
+            },
+            // (u/v)' = (u'v - uv') / v^2
+            Operator::Div => {
+                let els = self.children();
+                let (u, v) = (&els[0], &els[1]);
+                let num = u.derivative(var, memo) * v.clone() - u.clone() * v.derivative(var, memo);
+                num / Expr::pow(v.clone(), Expr::int(2))
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let (u, v) = (&els[0], &els[1]);
+                if !v.depends_on(var) {
+                    // (u^c)' = c u^(c - 1) u'
+                    let lowered = match &v.operator {
+                        Operator::Frac(f) => Expr::new_empty(Operator::Frac(f.clone() - crate::num::Frac::new(1, 1))),
+                        _ => v.clone() - Expr::int(1)
+                    };
+                    // u^1 is written u, so that (x^2)' is 2*x
+                    if lowered == Expr::int(1) {
+                        return v.clone() * u.clone() * u.derivative(var, memo);
+                    }
+                    return v.clone() * Expr::pow(u.clone(), lowered) * u.derivative(var, memo);
+                }
+                if let Operator::Const(Constant::E) = u.operator {
+                    // (e^v)' = e^v v'
+                    return self.clone() * v.derivative(var, memo);
+                }
+                if !u.depends_on(var) {
+                    // (c^v)' = c^v ln(c) v'
+                    return self.clone() * func("ln", u.clone()) * v.derivative(var, memo);
+                }
+                // (u^v)' = u^v (v' ln(u) + v u' / u)
+                let inner = v.derivative(var, memo) * func("ln", u.clone()) + v.clone() * u.derivative(var, memo) / u.clone();
+                self.clone() * inner
+            },
+            Operator::Func(ident) => {
+                let args = self.children();
+                match ident {
+                    FuncIdentifier::Name(name) => {
+                        let mut terms = Vec::new();
+                        for (idx, arg) in args.iter().enumerate() {
+                            if !arg.depends_on(var) {
+                                continue;
+                            }
+                            let partial = Expr::func(FuncIdentifier::Name(derivative_name(name, idx, args.len())), args.clone());
+                            terms.push(partial * arg.derivative(var, memo));
+                        }
+                        Expr::new(Operator::Add, Some(terms))
+                    },
+                    _ => {
+                        let u = &args[0];
+                        let outer = match ident {
+                            FuncIdentifier::Sine => Expr::func(FuncIdentifier::Cosine, vec![u.clone()]),
+                            FuncIdentifier::Cosine => Expr::func(FuncIdentifier::Sine, vec![u.clone()]) * Expr::int(-1),
+                            // tan' = 1 / cos^2
+                            FuncIdentifier::Tangent => Expr::pow(Expr::func(FuncIdentifier::Cosine, vec![u.clone()]), Expr::int(-2)),
+                            FuncIdentifier::Abs => func("sign", u.clone()),
+                            FuncIdentifier::Name(_) => unreachable!()
+                        };
+                        outer * u.derivative(var, memo)
+                    }
+                }
+            },
+            // Leaves other than names never depend on a variable
+            Operator::Frac(_) | Operator::Const(_) => unreachable!()
+        }
+    }
+
+    // Differentiates with respect to the variable `var`. Unknown functions are kept as
+    // opaque derivatives such as f'(x), and |x|' is written sign(x).
+    pub fn diff(&self, var: &str) -> Result<Expr, NumError> {
+        DiffCache::new().diff(self, var)
+    }
+
+    pub fn diff_n(&self, var: &str, n: usize) -> Result<Expr, NumError> {
+        DiffCache::new().diff_n(self, var, n)
+    }
+
+    pub fn gradient(&self, vars: &[&str]) -> Result<Vec<Expr>, NumError> {
+        DiffCache::new().gradient(self, vars)
+    }
+
+    pub fn jacobian(exprs: &[Expr], vars: &[&str]) -> Result<Vec<Vec<Expr>>, NumError> {
+        DiffCache::new().jacobian(exprs, vars)
+    }
+
+    pub fn hessian(&self, vars: &[&str]) -> Result<Vec<Vec<Expr>>, NumError> {
+        DiffCache::new().hessian(self, vars)
+    }
+}
+
+// Remembers derivatives between calls, both of whole expressions and of every subtree met
+// along the way, so that the parts shared between the entries of a gradient or Hessian
+// are only differentiated once
+#[derive(Default)]
+pub struct DiffCache {
+    subtrees: Memo,
+    simplified: Memo
+}
+
+impl DiffCache {
+    pub fn new() -> DiffCache {
+        DiffCache::default()
+    }
+
+    // The number of derivatives remembered
+    pub fn len(&self) -> usize {
+        self.subtrees.len() + self.simplified.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn diff(&mut self, expr: &Expr, var: &str) -> Result<Expr, NumError> {
+        let key = (expr.clone(), String::from(var));
+        if let Some(known) = self.simplified.get(&key) {
+            return Ok(known.clone());
+        }
+        let out = expr.derivative(var, &mut self.subtrees).simplified()?;
+        self.simplified.insert(key, out.clone());
+        return Ok(out);
+    }
+
+    // Each derivative is simplified before the next is taken, so f''(x) comes from f'(x)
+    // in its simplest form
+    pub fn diff_n(&mut self, expr: &Expr, var: &str, n: usize) -> Result<Expr, NumError> {
+        let mut out = expr.clone();
+        for _ in 0..n {
+            out = self.diff(&out, var)?;
+        }
+        return Ok(out);
+    }
+
+    pub fn gradient(&mut self, expr: &Expr, vars: &[&str]) -> Result<Vec<Expr>, NumError> {
+        vars.iter().map(|var| self.diff(expr, var)).collect()
+    }
+
+    // One row per expression and one column per variable
+    pub fn jacobian(&mut self, exprs: &[Expr], vars: &[&str]) -> Result<Vec<Vec<Expr>>, NumError> {
+        exprs.iter().map(|expr| self.gradient(expr, vars)).collect()
+    }
+
+    // Mixed partial derivatives are taken to commute, so only the upper triangle is
+    // differentiated and mirrored into the lower one
+    pub fn hessian(&mut self, expr: &Expr, vars: &[&str]) -> Result<Vec<Vec<Expr>>, NumError> {
+        let gradient = self.gradient(expr, vars)?;
+        let mut out = vec![vec![Expr::int(0); vars.len()]; vars.len()];
+        for row in 0..vars.len() {
+            for col in row..vars.len() {
+                let entry = self.diff(&gradient[row], vars[col])?;
+                out[col][row] = entry.clone();
+                out[row][col] = entry;
+            }
+        }
+        return Ok(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+    use crate::parse::parse;
+    use crate::simplify::SimplifcationOpts;
+    use super::DiffCache;
+
+    fn diff(input: &str) -> Expr {
+        parse(input).unwrap().diff("x").unwrap()
+    }
+
+    fn simplified(input: &str) -> Expr {
+        parse(input).unwrap().simplified().unwrap()
+    }
+
+    #[test]
+    fn rules() {
+        assert_eq!(diff("x^2"), simplified("2*x"));
+        assert_eq!(diff("3*x + y"), Expr::int(3));
+        assert_eq!(diff("y^2 + pi"), Expr::int(0));
+        assert_eq!(diff("sin(x)"), simplified("cos(x)"));
+        assert_eq!(diff("cos(2*x)"), simplified("sin(2*x) * -2"));
+        assert_eq!(diff("tan(x)"), simplified("cos(x)^-2"));
+        assert_eq!(diff("abs(x)"), simplified("sign(x)"));
+        assert_eq!(diff("e^x"), simplified("e^x"));
+        assert_eq!(diff("1/x"), Expr::int(-1) / Expr::pow(Expr::var("x"), Expr::int(2)));
+    }
+
+    #[test]
+    fn matches_finite_differences() {
+        let inputs = ["x^3 * sin(x) / (1 + x^2)", "tan(x^(1/2)) - cos(x)*x", "x^x + 2^x", "abs(x - 3)^3"];
+        for input in inputs.iter() {
+            let expr = parse(input).unwrap();
+            let derivative = expr.diff("x").unwrap();
+            for x in [0.7, 1.3, 2.1].iter() {
+                let at = |x: f64| [(String::from("x"), x)].iter().cloned().collect();
+                let h = 1e-6;
+                let estimate = (expr.eval_f64(&at(x + h)).unwrap() - expr.eval_f64(&at(x - h)).unwrap()) / (2.0 * h);
+                // ln and sign are left as opaque functions, so substitute them by hand
+                let exact = derivative.to_string().replace("ln(2)", &format!("{}", 2f64.ln())).replace("ln(x)", &format!("{}", x.ln())).replace("sign(x + -3)", "-1");
+                let exact = parse(&exact).unwrap().eval_f64(&at(*x)).unwrap();
+                assert!((estimate - exact).abs() < 1e-5 * exact.abs().max(1.0), "{}: {} vs {}", input, estimate, exact);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_functions() {
+        let mut expected = Expr::func(FuncIdentifier::Name(String::from("f'")), vec![parse("x^2").unwrap()]) * parse("2*x").unwrap();
+        expected.simplify(&SimplifcationOpts::default()).unwrap();
+        assert_eq!(diff("f(x^2)"), expected);
+        assert_eq!(diff("g(x, y, x)"), simplified("g_1(x, y, x) + g_3(x, y, x)"));
+
+        // Derivative names read back through the parser
+        for input in ["f(x^2)", "g(x, y, x)", "f(x)*f'(x)"].iter() {
+            let derivative = diff(input);
+            assert_eq!(parse(&derivative.to_string()).unwrap(), derivative, "{}", input);
+        }
+    }
+
+    #[test]
+    fn gradient_jacobian_hessian() {
+        let expr = parse("x^2*y + sin(x*y)").unwrap();
+        let gradient = expr.gradient(&["x", "y"]).unwrap();
+        assert_eq!(gradient, vec![expr.diff("x").unwrap(), expr.diff("y").unwrap()]);
+
+        let hessian = expr.hessian(&["x", "y"]).unwrap();
+        assert_eq!(hessian[0][0], gradient[0].diff("x").unwrap());
+        assert_eq!(hessian[1][1], gradient[1].diff("y").unwrap());
+        assert_eq!(hessian[0][1], hessian[1][0]);
+        assert_eq!(hessian[0][1], gradient[0].diff("y").unwrap());
+
+        let jacobian = Expr::jacobian(&[parse("x*y").unwrap(), parse("x + z").unwrap()], &["x", "y", "z"]).unwrap();
+        assert_eq!(jacobian, vec![
+            vec![Expr::var("y"), Expr::var("x"), Expr::int(0)],
+            vec![Expr::int(1), Expr::int(0), Expr::int(1)]
+        ]);
+    }
+
+    #[test]
+    fn higher_orders_are_memoized() {
+        assert_eq!(parse("x^5").unwrap().diff_n("x", 3).unwrap(), simplified("60*x^2"));
+        assert_eq!(parse("x^5").unwrap().diff_n("x", 0).unwrap(), parse("x^5").unwrap());
+        let second = Expr::func(FuncIdentifier::Name(String::from("f''")), vec![Expr::var("x")]);
+        assert_eq!(parse("f(x)").unwrap().diff_n("x", 2).unwrap(), second);
+
+        let mut cache = DiffCache::new();
+        let expr = parse("sin(x*y*z)^2 + (x*y*z)^3 * cos(x*y*z)").unwrap();
+        let hessian = cache.hessian(&expr, &["x", "y", "z"]).unwrap();
+        let remembered = cache.len();
+        assert_eq!(cache.hessian(&expr, &["x", "y", "z"]).unwrap(), hessian);
+        assert_eq!(cache.len(), remembered);
+        // x*y*z and its derivative appear throughout, but are only worked out once
+        assert!(cache.subtrees.contains_key(&(parse("x*y*z").unwrap(), String::from("x"))));
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::expr::*;
+use crate::num;
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum EvalError {
+    UnboundVariable(String),
+    UnknownFunction(String),
+    ArityMismatch { name: String, expected: usize, found: usize },
+    DivisionByZero,
+    // Such as an even root of a negative number
+    NotReal,
+    // The requested digits could not be settled, as happens for a value on a rounding boundary
+    PrecisionExhausted(u32),
+    // An interval whose bounds are NaN or out of order
+    InvalidInterval
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::UnboundVariable(name) => write!(f, "no value bound to variable '{}'", name),
+            EvalError::UnknownFunction(name) => write!(f, "unknown function '{}'", name),
+            EvalError::ArityMismatch { name, expected, found } => write!(f, "'{}' takes {} argument(s) but {} were given", name, expected, found),
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::NotReal => write!(f, "result is not a real number"),
+            EvalError::PrecisionExhausted(digits) => write!(f, "could not settle {} decimal places", digits),
+            EvalError::InvalidInterval => write!(f, "interval bounds are NaN or out of order")
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+pub(crate) fn check_arity(name: &str, args: &[Expr], expected: usize) -> Result<(), EvalError> {
+    if args.len() != expected {
+        return Err(EvalError::ArityMismatch {
+            name: String::from(name),
+            expected,
+            found: args.len()
+        });
+    }
+    return Ok(());
+}
+
+// Real powers. A negative base raised to a rational exponent with an odd denominator has a
+// real value, such as (-8)^(1/3) = -2, which powf alone would report as NaN.
+pub(crate) fn pow_f64(base: f64, exponent: f64, exact: Option<&num::Frac>) -> f64 {
+    if let Some(f) = exact {
+        if base < 0.0 && !f.is_int() && f.denom() % &num::Int::from(2) != 0 {
+            let magnitude = (-base).powf(exponent);
+            let odd = f.num() % &num::Int::from(2) != 0;
+            return if odd { -magnitude } else { magnitude };
+        }
+    }
+    return base.powf(exponent);
+}
+
+impl Expr {
+    pub fn eval_f64(&self, env: &HashMap<String, f64>) -> Result<f64, EvalError> {
+        match &self.operator {
+            Operator::Frac(f) => Ok(f.to_f64()),
+            Operator::Add => {
+                let mut sum = 0.0;
+                for child in self.children().iter() {
+                    sum += child.eval_f64(env)?;
+                }
+                Ok(sum)
+            },
+            Operator::Mul => {
+                let mut product = 1.0;
+                for child in self.children().iter() {
+                    product *= child.eval_f64(env)?;
+                }
+                Ok(product)
+            },
+            Operator::Div => {
+                let els = self.children();
+                let num = els[0].eval_f64(env)?;
+                let denom = els[1].eval_f64(env)?;
+                if denom == 0.0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+                Ok(num / denom)
+            },
+            Operator::Sub => {
+                let els = self.children();
+                let mut diff = els[0].eval_f64(env)?;
+                for child in els[1..].iter() {
+                    diff -= child.eval_f64(env)?;
+                }
+                Ok(diff)
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let exact = match &els[1].operator {
+                    Operator::Frac(f) => Some(f),
+                    _ => None
+                };
+                let base = els[0].eval_f64(env)?;
+                let exponent = els[1].eval_f64(env)?;
+                if base == 0.0 && exponent < 0.0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+                // NaN from finite inputs means a negative base under an even root or an
+                // irrational exponent
+                let val = pow_f64(base, exponent, exact);
+                if val.is_nan() && !base.is_nan() && !exponent.is_nan() {
+                    return Err(EvalError::NotReal);
+                }
+                Ok(val)
+            },
+            Operator::Name(name) => env.get(name).copied().ok_or_else(|| EvalError::UnboundVariable(name.clone())),
+            Operator::Func(FuncIdentifier::Name(name)) => Err(EvalError::UnknownFunction(name.clone())),
+            Operator::Func(ident) => {
+                let args = self.children();
+                check_arity(&ident.get_name(), args, 1)?;
+                let x = args[0].eval_f64(env)?;
+                Ok(match ident {
+                    FuncIdentifier::Sine => x.sin(),
+                    FuncIdentifier::Cosine => x.cos(),
+                    FuncIdentifier::Tangent => x.tan(),
+                    FuncIdentifier::Abs => x.abs(),
+                    FuncIdentifier::Name(_) => unreachable!()
+                })
+            },
+            Operator::Const(Constant::Pi) => Ok(std::f64::consts::PI),
+            Operator::Const(Constant::E) => Ok(std::f64::consts::E),
+            Operator::Const(Constant::I) => Err(EvalError::NotReal),
+            Operator::Const(Constant::Infinity) => Ok(f64::INFINITY)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::expr::*;
+    use crate::parse::parse;
+    use super::EvalError;
+
+    fn eval(input: &str, env: &[(&str, f64)]) -> Result<f64, EvalError> {
+        let env = env.iter().map(|(name, val)| (String::from(*name), *val)).collect::<HashMap<String, f64>>();
+        parse(input).unwrap().eval_f64(&env)
+    }
+
+    #[test]
+    fn evaluates_every_operator() {
+        assert_eq!(eval("3/4 + x*2 - y/4", &[("x", 1.5), ("y", 3.0)]), Ok(3.0));
+        assert_eq!(eval("2^10 / 4^-1", &[]), Ok(4096.0));
+        assert!((eval("sin(pi/6) + cos(0) + tan(pi/4) + abs(-e)", &[]).unwrap() - (2.5 + std::f64::consts::E)).abs() < 1e-12);
+        assert!((eval("(-8)^(1/3)", &[]).unwrap() + 2.0).abs() < 1e-12);
+        assert!((eval("(-8)^(2/3)", &[]).unwrap() - 4.0).abs() < 1e-12);
+        assert_eq!(eval("(-8)^0.5", &[]), Err(EvalError::NotReal));
+
+        let sub = Expr::int(10) - Expr::var("x");
+        assert_eq!(sub.eval_f64(&[(String::from("x"), 4.0)].iter().cloned().collect()), Ok(6.0));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(eval("x + 1", &[]), Err(EvalError::UnboundVariable(String::from("x"))));
+        assert_eq!(eval("f(2)", &[]), Err(EvalError::UnknownFunction(String::from("f"))));
+        let expr = Expr::func(FuncIdentifier::Sine, vec![Expr::int(1), Expr::int(2)]);
+        assert_eq!(expr.eval_f64(&HashMap::new()), Err(EvalError::ArityMismatch { name: String::from("sin"), expected: 1, found: 2 }));
+        assert_eq!(eval("1/(x - 2)", &[("x", 2.0)]), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("0^-1", &[]), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("(-1)^(1/2)", &[]), Err(EvalError::NotReal));
+        assert_eq!(eval("(-1)^x", &[("x", 0.5)]), Err(EvalError::NotReal));
+    }
+}
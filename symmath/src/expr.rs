@@ -1,8 +1,14 @@
+use std::hash::{Hash, Hasher};
 use std::ops::{Add, Sub, Mul, Div};
 use crate::num;
 
 #[derive(Clone)]
 #[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum FuncIdentifier {
     Name(String),
     Sine,
@@ -48,10 +54,18 @@ impl FuncIdentifier {
     }
 }
 
+#[derive(Clone)]
 #[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Constant {
     Pi,
-    E
+    E,
+    I,
+    Infinity
 }
 
 impl Constant {
@@ -59,11 +73,21 @@ impl Constant {
         match self {
             Constant::Pi => String::from("pi"),
             Constant::E => String::from("e"),
+            Constant::I => String::from("i"),
+            Constant::Infinity => String::from("inf"),
         }
     }
 }
 
+// Serialized trees refer to variants by name in JSON and by position in binary, so new
+// variants must be added at the end and existing ones never renamed or reordered
+#[derive(Clone)]
 #[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Operator {
     Frac(num::Frac),
     Add,
@@ -76,6 +100,9 @@ pub enum Operator {
     Const(Constant)
 }
 
+#[derive(Clone)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Expr {
     pub(crate) operator: Operator,
     pub(crate) elements: Option<Vec<Expr>>
@@ -95,7 +122,7 @@ impl Expr {
         }
     }
 
-    pub fn frac(num: i64, denom: i64) -> Expr {
+    pub fn frac(num: impl Into<num::Int>, denom: impl Into<num::Int>) -> Expr {
         Expr {
             operator: Operator::Frac(num::Frac::new(num, denom)),
             elements: None
@@ -104,12 +131,12 @@ impl Expr {
 
     pub fn frac_cpy(f: &num::Frac) -> Expr {
         Expr {
-            operator: Operator::Frac(num::Frac::new(f.num, f.denom)),
+            operator: Operator::Frac(f.clone()),
             elements: None
         }
     }
 
-    pub fn int(val: i64) -> Expr {
+    pub fn int(val: impl Into<num::Int>) -> Expr {
         Expr {
             operator: Operator::Frac(num::Frac::new(val, 1)),
             elements: None
@@ -129,6 +156,13 @@ impl Expr {
             elements: Some(params)
         }
     }
+
+    pub fn pow(base: Expr, exponent: Expr) -> Expr {
+        Expr {
+            operator: Operator::Pow,
+            elements: Some(vec![base, exponent])
+        }
+    }
     
     pub fn pi() -> Expr {
         Expr {
@@ -144,13 +178,31 @@ impl Expr {
         }
     }
 
+    pub fn i() -> Expr {
+        Expr {
+            operator: Operator::Const(Constant::I),
+            elements: None
+        }
+    }
+
+    pub fn infinity() -> Expr {
+        Expr {
+            operator: Operator::Const(Constant::Infinity),
+            elements: None
+        }
+    }
+
+    pub(crate) fn children(&self) -> &Vec<Expr> {
+        self.elements.as_ref().expect("Node has no children")
+    }
+
     fn join_operands_ascii(&self, delim: &str) -> String {
         return self.elements.as_ref().expect("Node has no children").iter().map(|el| el.to_ascii()).collect::<Vec<String>>().join(delim);
     }
 
     pub fn to_ascii(&self) -> String {
         match &self.operator {
-            Operator::Frac(frac) => if frac.is_int() { format!("{}", frac.num) } else { format!("({}/{})", frac.num, frac.denom) },
+            Operator::Frac(frac) => if frac.is_int() { format!("{}", frac.num()) } else { format!("({}/{})", frac.num(), frac.denom()) },
             Operator::Add => format!("({})", self.join_operands_ascii(" + ")),
             Operator::Mul => format!("({})", self.join_operands_ascii(" * ")),
             Operator::Div => format!("({})", self.join_operands_ascii(" / ")),
@@ -166,13 +218,13 @@ impl Expr {
         match &mut self.operator {
             Operator::Frac(ref mut frac) => {
                 if frac.is_negative() {
-                    frac.num *= -1;
+                    *frac = -&*frac;
                 }
             },
+            // |a + b| is not |a| + |b|, so sums are wrapped whole like differences
             Operator::Add => {
-                for ref mut child in self.elements.as_mut().expect("Add has no children").iter_mut() {
-                    child.abs();
-                }
+                self.operator = Operator::Func(FuncIdentifier::Abs);
+                self.elements = Some(vec![Expr::new(Operator::Add, self.elements.take())]);
             },
             Operator::Mul => {
                 for ref mut child in self.elements.as_mut().expect("Mul has no children").iter_mut() {
@@ -202,12 +254,39 @@ impl Expr {
                 self.operator = Operator::Func(FuncIdentifier::Abs);
                 self.elements = Some(vec![Expr::new(Operator::Func(newf), self.elements.take())]);
             },
-            Operator::Const(c) => {
-                match c {
-                    _ => {}
+            Operator::Const(Constant::I) => {
+                self.operator = Operator::Frac(num::Frac::new(1, 1));
+            },
+            Operator::Const(_) => {}
+        }
+    }
+}
+
+// Negates a term by folding the sign into its coefficient: the leading Frac of a product
+// if there is one, otherwise the trailing one, otherwise a new trailing -1. The LaTeX and
+// terminal renderers write exactly these shapes as subtractions.
+pub(crate) fn negate(mut expr: Expr) -> Expr {
+    match expr.operator {
+        Operator::Frac(ref mut f) => {
+            *f = -&*f;
+            return expr;
+        },
+        Operator::Mul => {
+            let els = expr.elements.as_mut().expect("Mul has no children");
+            let idx = match (&els.first().unwrap().operator, &els.last().unwrap().operator) {
+                (Operator::Frac(_), _) => 0,
+                (_, Operator::Frac(_)) => els.len() - 1,
+                _ => {
+                    els.push(Expr::int(-1));
+                    return expr;
                 }
+            };
+            if let Operator::Frac(ref mut f) = els[idx].operator {
+                *f = -&*f;
             }
-        }
+            return expr;
+        },
+        _ => expr * Expr::int(-1)
     }
 }
 
@@ -263,4 +342,14 @@ impl PartialEq for Expr {
 
         return true;
     }
+}
+
+impl Eq for Expr {}
+
+// Hashes the same structure that eq compares
+impl Hash for Expr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.operator.hash(state);
+        self.elements.hash(state);
+    }
 }
\ No newline at end of file
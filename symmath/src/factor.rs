@@ -0,0 +1,777 @@
+use std::collections::BTreeMap;
+use crate::expr::*;
+use crate::num::{Frac, Int, NumError};
+use crate::simplify::SimplifcationOpts;
+
+// Kronecker substitutions of a higher degree than this are left unfactored
+const MAX_DEGREE: u64 = 1 << 12;
+// How many usable primes are tried before keeping the one with the fewest modular factors
+const PRIME_TRIALS: usize = 5;
+
+// Dense polynomials over the integers, lowest coefficient first and with no trailing zeros
+type Poly = Vec<Int>;
+
+fn trim(mut a: Poly) -> Poly {
+    while a.last().is_some_and(|c| c.is_zero()) {
+        a.pop();
+    }
+    return a;
+}
+
+fn add(a: &Poly, b: &Poly) -> Poly {
+    let zero = Int::from(0);
+    return trim((0..a.len().max(b.len())).map(|i| a.get(i).unwrap_or(&zero) + b.get(i).unwrap_or(&zero)).collect());
+}
+
+fn sub(a: &Poly, b: &Poly) -> Poly {
+    let zero = Int::from(0);
+    return trim((0..a.len().max(b.len())).map(|i| a.get(i).unwrap_or(&zero) - b.get(i).unwrap_or(&zero)).collect());
+}
+
+fn mul(a: &Poly, b: &Poly) -> Poly {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut out = vec![Int::from(0); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            out[i + j] = &out[i + j] + &(x * y);
+        }
+    }
+    return trim(out);
+}
+
+fn scale(a: &Poly, c: &Int) -> Poly {
+    return trim(a.iter().map(|x| x * c).collect());
+}
+
+fn derivative(a: &Poly) -> Poly {
+    return trim(a.iter().enumerate().skip(1).map(|(i, c)| c * &Int::from(i as i64)).collect());
+}
+
+// Divides out the content and makes the leading coefficient positive
+fn primitive(a: &Poly) -> Poly {
+    let mut g = a.iter().fold(Int::from(0), |g, c| g.gcd(c)).abs();
+    if g.is_zero() {
+        return Vec::new();
+    }
+    if a.last().is_some_and(|c| c.is_negative()) {
+        g = -g;
+    }
+    return a.iter().map(|c| c / &g).collect();
+}
+
+// a / b when b divides a over the integers
+fn div_exact(a: &Poly, b: &Poly) -> Option<Poly> {
+    if a.len() < b.len() {
+        return if a.is_empty() { Some(Vec::new()) } else { None };
+    }
+    let lead = b.last().expect("Division by the zero polynomial");
+    let mut rem = a.clone();
+    let mut quot = vec![Int::from(0); a.len() - b.len() + 1];
+    while !rem.is_empty() && rem.len() >= b.len() {
+        let top = rem.last().unwrap();
+        if !(top % lead).is_zero() {
+            return None;
+        }
+        let c = top / lead;
+        let shift = rem.len() - b.len();
+        for (i, x) in b.iter().enumerate() {
+            rem[i + shift] = &rem[i + shift] - &(&c * x);
+        }
+        quot[shift] = c;
+        rem = trim(rem);
+    }
+    if !rem.is_empty() {
+        return None;
+    }
+    return Some(trim(quot));
+}
+
+// The remainder of lc(b)^k a divided by b, which stays in the integers
+fn pseudo_rem(a: &Poly, b: &Poly) -> Poly {
+    let lead = b.last().unwrap();
+    let mut rem = a.clone();
+    while !rem.is_empty() && rem.len() >= b.len() {
+        let top = rem.last().unwrap().clone();
+        let shift = rem.len() - b.len();
+        rem = scale(&rem, lead);
+        for (i, x) in b.iter().enumerate() {
+            rem[i + shift] = &rem[i + shift] - &(&top * x);
+        }
+        rem = trim(rem);
+    }
+    return rem;
+}
+
+// The primitive greatest common divisor, by the primitive remainder sequence
+fn gcd(a: &Poly, b: &Poly) -> Poly {
+    let (mut a, mut b) = (primitive(a), primitive(b));
+    if a.len() < b.len() {
+        std::mem::swap(&mut a, &mut b);
+    }
+    while !b.is_empty() {
+        let rem = primitive(&pseudo_rem(&a, &b));
+        a = b;
+        b = rem;
+    }
+    return a;
+}
+
+// Yun's algorithm: f = a1 a2^2 a3^3 ... with every ai square-free and coprime to the others
+fn square_free(f: &Poly) -> Vec<(Poly, u32)> {
+    let mut out = Vec::new();
+    let df = derivative(f);
+    let g = gcd(f, &df);
+    let mut c = div_exact(f, &g).expect("gcd divides f");
+    let mut d = sub(&div_exact(&df, &g).expect("gcd divides f'"), &derivative(&c));
+    let mut mult = 1;
+    while c.len() > 1 {
+        let a = gcd(&c, &d);
+        c = div_exact(&c, &a).expect("gcd divides c");
+        d = sub(&div_exact(&d, &a).expect("gcd divides d"), &derivative(&c));
+        if a.len() > 1 {
+            out.push((a, mult));
+        }
+        mult += 1;
+    }
+    return out;
+}
+
+// Dense polynomials over the integers modulo a small prime p
+type PolyMod = Vec<u64>;
+
+fn trim_mod(mut a: PolyMod) -> PolyMod {
+    while a.last() == Some(&0) {
+        a.pop();
+    }
+    return a;
+}
+
+fn to_mod(c: &Int, p: u64) -> u64 {
+    let m = Int::from(p as i64);
+    let r = c % &m;
+    let r = if r.is_negative() { r + m } else { r };
+    return r.to_i64().expect("Residue fits in an i64") as u64;
+}
+
+fn poly_to_mod(a: &Poly, p: u64) -> PolyMod {
+    return trim_mod(a.iter().map(|c| to_mod(c, p)).collect());
+}
+
+fn poly_from_mod(a: &PolyMod) -> Poly {
+    return a.iter().map(|c| Int::from(*c as i64)).collect();
+}
+
+fn inv_mod(x: u64, p: u64) -> u64 {
+    let (mut out, mut base, mut exp) = (1, x % p, p - 2);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            out = out * base % p;
+        }
+        base = base * base % p;
+        exp >>= 1;
+    }
+    return out;
+}
+
+fn sub_mod(a: &PolyMod, b: &PolyMod, p: u64) -> PolyMod {
+    return trim_mod((0..a.len().max(b.len())).map(|i| (a.get(i).unwrap_or(&0) + p - b.get(i).unwrap_or(&0)) % p).collect());
+}
+
+fn mul_mod(a: &PolyMod, b: &PolyMod, p: u64) -> PolyMod {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut out = vec![0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            out[i + j] = (out[i + j] + x * y) % p;
+        }
+    }
+    return trim_mod(out);
+}
+
+fn divrem_mod(a: &PolyMod, b: &PolyMod, p: u64) -> (PolyMod, PolyMod) {
+    if a.len() < b.len() {
+        return (Vec::new(), a.clone());
+    }
+    let inv = inv_mod(*b.last().expect("Division by the zero polynomial"), p);
+    let mut rem = a.clone();
+    let mut quot = vec![0; a.len() - b.len() + 1];
+    while !rem.is_empty() && rem.len() >= b.len() {
+        let c = rem.last().unwrap() * inv % p;
+        let shift = rem.len() - b.len();
+        for (i, x) in b.iter().enumerate() {
+            rem[i + shift] = (rem[i + shift] + p - c * x % p) % p;
+        }
+        quot[shift] = c;
+        rem = trim_mod(rem);
+    }
+    return (trim_mod(quot), rem);
+}
+
+fn monic_mod(a: &PolyMod, p: u64) -> PolyMod {
+    let inv = inv_mod(*a.last().unwrap(), p);
+    return a.iter().map(|c| c * inv % p).collect();
+}
+
+fn derivative_mod(a: &PolyMod, p: u64) -> PolyMod {
+    return trim_mod(a.iter().enumerate().skip(1).map(|(i, c)| c * (i as u64 % p) % p).collect());
+}
+
+fn gcd_mod(a: &PolyMod, b: &PolyMod, p: u64) -> PolyMod {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    while !b.is_empty() {
+        let rem = divrem_mod(&a, &b, p).1;
+        a = b;
+        b = rem;
+    }
+    return if a.is_empty() { a } else { monic_mod(&a, p) };
+}
+
+// (g, s, t) with s a + t b = g, the monic gcd of a and b
+fn ext_gcd_mod(a: &PolyMod, b: &PolyMod, p: u64) -> (PolyMod, PolyMod, PolyMod) {
+    let (mut r0, mut r1) = (a.clone(), b.clone());
+    let (mut s0, mut s1) = (vec![1], Vec::new());
+    let (mut t0, mut t1) = (Vec::new(), vec![1]);
+    while !r1.is_empty() {
+        let (quot, rem) = divrem_mod(&r0, &r1, p);
+        let s2 = sub_mod(&s0, &mul_mod(&quot, &s1, p), p);
+        let t2 = sub_mod(&t0, &mul_mod(&quot, &t1, p), p);
+        r0 = std::mem::replace(&mut r1, rem);
+        s0 = std::mem::replace(&mut s1, s2);
+        t0 = std::mem::replace(&mut t1, t2);
+    }
+    let inv = inv_mod(*r0.last().unwrap(), p);
+    let norm = |a: &PolyMod| a.iter().map(|c| c * inv % p).collect::<PolyMod>();
+    return (norm(&r0), norm(&s0), norm(&t0));
+}
+
+// base^exp reduced modulo `modulus`
+fn pow_mod(base: &PolyMod, exp: &Int, modulus: &PolyMod, p: u64) -> PolyMod {
+    let mut out = vec![1];
+    for bit in (0..exp.bits()).rev() {
+        out = divrem_mod(&mul_mod(&out, &out, p), modulus, p).1;
+        if !(&exp.shr_floor(bit) % &Int::from(2)).is_zero() {
+            out = divrem_mod(&mul_mod(&out, base, p), modulus, p).1;
+        }
+    }
+    return divrem_mod(&out, modulus, p).1;
+}
+
+// A xorshift generator, so that factoring is reproducible
+fn random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    return *state;
+}
+
+// Cantor-Zassenhaus splitting of a product of distinct monic irreducibles of degree d
+fn equal_degree(g: PolyMod, d: usize, p: u64, state: &mut u64, out: &mut Vec<PolyMod>) {
+    if g.len() - 1 == d {
+        out.push(g);
+        return;
+    }
+    let exp = (Int::from(p as i64).pow(d as u32) - Int::from(1)) / Int::from(2);
+    loop {
+        let a = trim_mod((0..g.len() - 1).map(|_| random(state) % p).collect());
+        if a.len() < 2 {
+            continue;
+        }
+        let b = sub_mod(&pow_mod(&a, &exp, &g, p), &vec![1], p);
+        let c = gcd_mod(&b, &g, p);
+        if c.len() > 1 && c.len() < g.len() {
+            let rest = divrem_mod(&g, &c, p).0;
+            equal_degree(c, d, p, state, out);
+            equal_degree(rest, d, p, state, out);
+            return;
+        }
+    }
+}
+
+// The monic irreducible factors of a monic square-free polynomial mod p, grouped by
+// degree through x^(p^d) - x before each group is split
+fn factor_mod(f: &PolyMod, p: u64) -> Vec<PolyMod> {
+    let mut out = Vec::new();
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    let x = vec![0, 1];
+    let mut f = f.clone();
+    let mut h = x.clone();
+    let mut d = 1;
+    while f.len() > 2 * d {
+        h = pow_mod(&h, &Int::from(p as i64), &f, p);
+        let g = gcd_mod(&sub_mod(&h, &x, p), &f, p);
+        if g.len() > 1 {
+            f = divrem_mod(&f, &g, p).0;
+            h = divrem_mod(&h, &f, p).1;
+            equal_degree(g, d, p, &mut state, &mut out);
+        }
+        d += 1;
+    }
+    if f.len() > 1 {
+        out.push(f);
+    }
+    return out;
+}
+
+// Non-negative residues modulo m
+fn reduce(a: &Poly, m: &Int) -> Poly {
+    return trim(a.iter().map(|c| {
+        let r = c % m;
+        if r.is_negative() { r + m.clone() } else { r }
+    }).collect());
+}
+
+fn inverse(a: &Int, m: &Int) -> Int {
+    let (mut r0, mut r1) = (a % m, m.clone());
+    let (mut s0, mut s1) = (Int::from(1), Int::from(0));
+    while !r1.is_zero() {
+        let quot = &r0 / &r1;
+        let r2 = &r0 - &(&quot * &r1);
+        let s2 = &s0 - &(&quot * &s1);
+        r0 = std::mem::replace(&mut r1, r2);
+        s0 = std::mem::replace(&mut s1, s2);
+    }
+    if r0.is_negative() {
+        s0 = -s0;
+    }
+    let s = &s0 % m;
+    return if s.is_negative() { s + m.clone() } else { s };
+}
+
+// Lifts target = g h mod p, with g and h monic and coprime, to a factorization modulo p^k
+fn hensel_pair(target: &Poly, g: &PolyMod, h: &PolyMod, p: u64, k: u32) -> (Poly, Poly) {
+    let (_, _, t) = ext_gcd_mod(g, h, p);
+    let prime = Int::from(p as i64);
+    let (mut lifted_g, mut lifted_h) = (poly_from_mod(g), poly_from_mod(h));
+    let mut m = prime.clone();
+    for _ in 1..k {
+        let next = &m * &prime;
+        let err = reduce(&sub(&reduce(target, &next), &mul(&lifted_g, &lifted_h)), &next);
+        let e = trim_mod(err.iter().map(|c| to_mod(&(c / &m), p)).collect());
+        // a h + b g = e (mod p), with a of lower degree than g so both stay monic
+        let a = divrem_mod(&mul_mod(&t, &e, p), g, p).1;
+        let b = divrem_mod(&sub_mod(&e, &mul_mod(&a, h, p), p), g, p).0;
+        lifted_g = reduce(&add(&lifted_g, &scale(&poly_from_mod(&a), &m)), &next);
+        lifted_h = reduce(&add(&lifted_h, &scale(&poly_from_mod(&b), &m)), &next);
+        m = next;
+    }
+    return (lifted_g, lifted_h);
+}
+
+fn hensel(target: &Poly, factors: &[PolyMod], p: u64, k: u32) -> Vec<Poly> {
+    if factors.len() == 1 {
+        return vec![target.clone()];
+    }
+    let (left, right) = factors.split_at(factors.len() / 2);
+    let product = |fs: &[PolyMod]| fs.iter().fold(vec![1], |acc, f| mul_mod(&acc, f, p));
+    let (g, h) = hensel_pair(target, &product(left), &product(right), p, k);
+    let mut out = hensel(&g, left, p, k);
+    out.extend(hensel(&h, right, p, k));
+    return out;
+}
+
+fn is_prime(n: u64) -> bool {
+    return n > 1 && (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d));
+}
+
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    let mut out = Vec::new();
+    for last in k - 1..n {
+        for mut combo in combinations(last, k - 1) {
+            combo.push(last);
+            out.push(combo);
+        }
+    }
+    return out;
+}
+
+// The irreducible factors of a primitive square-free polynomial with a positive leading
+// coefficient: factor modulo a prime, Hensel lift past the Mignotte bound, then recombine
+// the lifted factors into true divisors, smallest subsets first
+fn zassenhaus(f: &Poly) -> Vec<Poly> {
+    if f.len() <= 2 {
+        return vec![f.clone()];
+    }
+    let lead = f.last().unwrap().clone();
+    let mut best: Option<(u64, Vec<PolyMod>)> = None;
+    let mut trials = 0;
+    let mut p = 3;
+    while trials < PRIME_TRIALS {
+        if is_prime(p) && !(&lead % &Int::from(p as i64)).is_zero() {
+            let fp = poly_to_mod(f, p);
+            if gcd_mod(&fp, &derivative_mod(&fp, p), p).len() == 1 {
+                let factors = factor_mod(&monic_mod(&fp, p), p);
+                if best.as_ref().is_none_or(|(_, fs)| factors.len() < fs.len()) {
+                    best = Some((p, factors));
+                }
+                trials += 1;
+            }
+        }
+        p += 2;
+    }
+    let (p, factors) = best.unwrap();
+    if factors.len() == 1 {
+        return vec![f.clone()];
+    }
+
+    let deg = f.len() - 1;
+    let max = f.iter().map(|c| c.abs()).max().unwrap();
+    let bound = Int::from(2) * lead.abs() * Int::from(2).pow(deg as u32) * Int::from(deg as i64 + 1) * max;
+    let prime = Int::from(p as i64);
+    let (mut m, mut k) = (prime.clone(), 1);
+    while m <= bound {
+        m = &m * &prime;
+        k += 1;
+    }
+    let target = reduce(&scale(f, &inverse(&lead, &m)), &m);
+    let mut lifted = hensel(&target, &factors, p, k);
+
+    let half = &m / &Int::from(2);
+    let symmetric = |a: Poly| a.into_iter().map(|c| if c > half { c - m.clone() } else { c }).collect::<Poly>();
+    let mut f = f.clone();
+    let mut out = Vec::new();
+    let mut size = 1;
+    while 2 * size <= lifted.len() {
+        let lead = f.last().unwrap().clone();
+        let found = combinations(lifted.len(), size).into_iter().find_map(|subset| {
+            let product = subset.iter().fold(vec![lead.clone()], |acc, i| reduce(&mul(&acc, &lifted[*i]), &m));
+            let g = primitive(&trim(symmetric(product)));
+            div_exact(&f, &g).map(|quot| (subset, g, quot))
+        });
+        match found {
+            Some((subset, g, quot)) => {
+                out.push(g);
+                f = quot;
+                lifted = lifted.into_iter().enumerate().filter(|(i, _)| !subset.contains(i)).map(|(_, g)| g).collect();
+            },
+            None => size += 1
+        }
+    }
+    out.push(primitive(&f));
+    return out;
+}
+
+// Sparse polynomials in several generators, keyed by exponent vectors so that the last
+// entry is the leading term in lex order
+type Terms = BTreeMap<Vec<u32>, Int>;
+
+fn is_constant(poly: &Terms) -> bool {
+    return poly.keys().all(|exps| exps.iter().all(|e| *e == 0));
+}
+
+// Flips the sign so the leading coefficient is positive
+fn normalize(poly: Terms) -> Terms {
+    if poly.values().next_back().is_some_and(|c| c.is_negative()) {
+        return poly.into_iter().map(|(exps, c)| (exps, -c)).collect();
+    }
+    return poly;
+}
+
+// Multivariate division, which succeeds only when b divides a over the integers
+fn div_terms(a: &Terms, b: &Terms) -> Option<Terms> {
+    let (lead_exps, lead) = b.iter().next_back().expect("Division by the zero polynomial");
+    let mut rem = a.clone();
+    let mut quot = Terms::new();
+    while let Some((exps, top)) = rem.iter().next_back() {
+        if exps.iter().zip(lead_exps.iter()).any(|(e, l)| e < l) || !(top % lead).is_zero() {
+            return None;
+        }
+        let shift: Vec<u32> = exps.iter().zip(lead_exps.iter()).map(|(e, l)| e - l).collect();
+        let c = top / lead;
+        for (exps, x) in b.iter() {
+            let key: Vec<u32> = exps.iter().zip(shift.iter()).map(|(e, s)| e + s).collect();
+            let val = rem.remove(&key).unwrap_or_else(|| Int::from(0)) - &c * x;
+            if !val.is_zero() {
+                rem.insert(key, val);
+            }
+        }
+        quot.insert(shift, c);
+    }
+    return Some(quot);
+}
+
+// The image under x_i -> x^(w_i), where w_1 = 1 and w_(i+1) = w_i (deg_i f + 1)
+fn kronecker(poly: &Terms, radices: &[u64]) -> Poly {
+    let mut out = Vec::new();
+    for (exps, c) in poly.iter() {
+        let mut weight = 1;
+        let mut power = 0;
+        for (e, radix) in exps.iter().zip(radices.iter()) {
+            power += *e as u64 * weight;
+            weight *= radix;
+        }
+        if out.len() <= power as usize {
+            out.resize(power as usize + 1, Int::from(0));
+        }
+        out[power as usize] = c.clone();
+    }
+    return out;
+}
+
+fn unkronecker(poly: &Poly, radices: &[u64]) -> Terms {
+    let mut out = Terms::new();
+    for (power, c) in poly.iter().enumerate() {
+        if c.is_zero() {
+            continue;
+        }
+        let mut rest = power as u64;
+        let mut exps = Vec::new();
+        for (i, radix) in radices.iter().enumerate() {
+            let digit = if i + 1 == radices.len() { rest } else { rest % radix };
+            exps.push(digit as u32);
+            rest /= radix;
+        }
+        out.insert(exps, c.clone());
+    }
+    return out;
+}
+
+// The irreducible factors, with repetition, of a primitive polynomial without monomial
+// factors. A Kronecker substitution turns it into one variable, whose factors are
+// recombined until their preimages divide the original.
+fn factor_terms(poly: &Terms, vars: usize) -> Vec<Terms> {
+    let radices: Vec<u64> = (0..vars).map(|i| poly.keys().map(|exps| exps[i] as u64).max().unwrap() + 1).collect();
+    let degree = radices.iter().rev().fold(0, |acc, radix| acc * radix + radix - 1);
+    if degree > MAX_DEGREE {
+        return vec![poly.clone()];
+    }
+    let mut pool = Vec::new();
+    for (part, mult) in square_free(&primitive(&kronecker(poly, &radices))) {
+        for factor in zassenhaus(&part) {
+            for _ in 0..mult {
+                pool.push(factor.clone());
+            }
+        }
+    }
+
+    let mut rest = poly.clone();
+    let mut out = Vec::new();
+    let mut size = 1;
+    while size <= pool.len() && !is_constant(&rest) {
+        let found = combinations(pool.len(), size).into_iter().find_map(|subset| {
+            let product = subset.iter().fold(vec![Int::from(1)], |acc, i| mul(&acc, &pool[*i]));
+            let candidate = normalize(unkronecker(&product, &radices));
+            div_terms(&rest, &candidate).map(|quot| (subset, candidate, quot))
+        });
+        match found {
+            Some((subset, candidate, quot)) => {
+                out.push(candidate);
+                rest = quot;
+                pool = pool.into_iter().enumerate().filter(|(i, _)| !subset.contains(i)).map(|(_, f)| f).collect();
+            },
+            None => size += 1
+        }
+    }
+    if !is_constant(&rest) {
+        out.push(normalize(rest));
+    }
+    return out;
+}
+
+// Splits an expanded term into its rational coefficient and powers of generators
+fn monomial(term: &Expr) -> Result<(Frac, Vec<(Expr, u32)>), NumError> {
+    let factors = match term.operator {
+        Operator::Mul => term.children().clone(),
+        _ => vec![term.clone()]
+    };
+    let mut coeff = Frac::new(1, 1);
+    let mut powers = Vec::new();
+    for factor in factors {
+        match &factor.operator {
+            Operator::Frac(f) => coeff = coeff.checked_mul(f.clone())?,
+            Operator::Pow => {
+                let els = factor.children();
+                let exponent = match &els[1].operator {
+                    Operator::Frac(f) if f.is_int() && !f.is_negative() => f.num().to_i64().filter(|e| *e <= u32::MAX as i64),
+                    _ => None
+                };
+                match (&els[0].operator, exponent) {
+                    (Operator::Add | Operator::Mul, _) | (_, None) => powers.push((factor.clone(), 1)),
+                    (_, Some(e)) => powers.push((els[0].clone(), e as u32))
+                }
+            },
+            _ => powers.push((factor.clone(), 1))
+        }
+    }
+    return Ok((coeff, powers));
+}
+
+fn to_expr(poly: &Terms, gens: &[Expr]) -> Result<Expr, NumError> {
+    let mut terms = Vec::new();
+    for (exps, c) in poly.iter().rev() {
+        let mut factors = Vec::new();
+        for (gen, e) in gens.iter().zip(exps.iter()) {
+            match e {
+                0 => {},
+                1 => factors.push(gen.clone()),
+                _ => factors.push(Expr::pow(gen.clone(), Expr::int(*e as i64)))
+            }
+        }
+        if factors.is_empty() || *c != 1 {
+            factors.push(Expr::int(c.clone()));
+        }
+        terms.push(if factors.len() == 1 { factors.pop().unwrap() } else { Expr::new(Operator::Mul, Some(factors)) });
+    }
+    let mut out = if terms.len() == 1 { terms.pop().unwrap() } else { Expr::new(Operator::Add, Some(terms)) };
+    out.simplify(&SimplifcationOpts::default())?;
+    return Ok(out);
+}
+
+impl Expr {
+    // Factors a polynomial into irreducibles over the rationals, returned as a product of
+    // powers with the rational content last. Anything that is not a sum of products of
+    // names, like sin(x) or x^(1/2), is treated as one more variable, and a quotient has
+    // its numerator and denominator factored separately.
+    pub fn factor(&self) -> Result<Expr, NumError> {
+        let mut expr = self.clone();
+        expr.simplify(&SimplifcationOpts::default())?;
+        if let Operator::Div = expr.operator {
+            let els = expr.children();
+            return Ok(Expr::new(Operator::Div, Some(vec![els[0].factor()?, els[1].factor()?])));
+        }
+        expr.simplify(&SimplifcationOpts::expand())?;
+
+        let terms = match expr.operator {
+            Operator::Add => expr.children().clone(),
+            _ => vec![expr.clone()]
+        };
+        let mut monomials = Vec::new();
+        let mut gens: Vec<Expr> = Vec::new();
+        for term in terms.iter() {
+            let (coeff, powers) = monomial(term)?;
+            for (gen, _) in powers.iter() {
+                if !gens.contains(gen) {
+                    gens.push(gen.clone());
+                }
+            }
+            monomials.push((coeff, powers));
+        }
+        if gens.is_empty() {
+            return Ok(expr);
+        }
+        gens.sort_by_key(|gen| gen.to_ascii());
+
+        // Clear denominators, then pull the integer content out with the sign of the
+        // leading term
+        let denom = monomials.iter().fold(Int::from(1), |acc, (c, _)| acc.lcm(c.denom()));
+        let mut poly = Terms::new();
+        for (coeff, powers) in monomials {
+            let mut exps = vec![0; gens.len()];
+            for (gen, e) in powers {
+                let idx = gens.iter().position(|g| *g == gen).unwrap();
+                exps[idx] += e;
+            }
+            let val = poly.remove(&exps).unwrap_or_else(|| Int::from(0)) + coeff.num() * &(&denom / coeff.denom());
+            if !val.is_zero() {
+                poly.insert(exps, val);
+            }
+        }
+        if poly.is_empty() {
+            return Ok(Expr::int(0));
+        }
+        let mut content = poly.values().fold(Int::from(0), |g, c| g.gcd(c)).abs();
+        if poly.values().next_back().unwrap().is_negative() {
+            content = -content;
+        }
+        let numeric = Frac::checked_new(content.clone(), denom)?;
+        let mut poly: Terms = poly.into_iter().map(|(exps, c)| (exps, &c / &content)).collect();
+
+        // Each generator dividing every term is a factor of its own
+        let mut factors: Vec<(Terms, u32)> = Vec::new();
+        for i in 0..gens.len() {
+            let least = poly.keys().map(|exps| exps[i]).min().unwrap();
+            if least > 0 {
+                let mut exps = vec![0; gens.len()];
+                exps[i] = 1;
+                factors.push((std::iter::once((exps, Int::from(1))).collect(), least));
+                poly = poly.into_iter().map(|(mut exps, c)| {
+                    exps[i] -= least;
+                    (exps, c)
+                }).collect();
+            }
+        }
+        if !is_constant(&poly) {
+            for factor in factor_terms(&poly, gens.len()) {
+                match factors.iter_mut().find(|(f, _)| *f == factor) {
+                    Some((_, mult)) => *mult += 1,
+                    None => factors.push((factor, 1))
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        for (factor, mult) in factors.iter() {
+            let base = to_expr(factor, &gens)?;
+            let degree = factor.keys().map(|exps| exps.iter().sum::<u32>()).max().unwrap();
+            out.push((degree, base.to_ascii(), match mult {
+                1 => base,
+                _ => Expr::pow(base, Expr::int(*mult as i64))
+            }));
+        }
+        out.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+        let mut out: Vec<Expr> = out.into_iter().map(|(_, _, factor)| factor).collect();
+        if !numeric.is_one() {
+            out.push(Expr::frac_cpy(&numeric));
+        }
+        return Ok(match out.len() {
+            1 => out.pop().unwrap(),
+            _ => Expr::new(Operator::Mul, Some(out))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::Expr;
+    use crate::parse::parse;
+
+    fn factor(input: &str) -> Expr {
+        parse(input).unwrap().factor().unwrap()
+    }
+
+    // Factors are compared as trees, since a simplified factor such as x + -1 prints with its sign
+    fn simplified(input: &str) -> Expr {
+        parse(input).unwrap().simplified().unwrap()
+    }
+
+    #[test]
+    fn univariate() {
+        assert_eq!(factor("x^2 - 1"), simplified("(x - 1)*(x + 1)"));
+        assert_eq!(factor("x^2 + 2*x + 1"), simplified("(x + 1)^2"));
+        assert_eq!(factor("(x+1)^3*(x-2)^2"), simplified("(x - 2)^2*(x + 1)^3"));
+        assert_eq!(factor("x^12 - 1"), simplified("(x - 1)*(x + 1)*(x^2 - x + 1)*(x^2 + 1)*(x^2 + x + 1)*(x^4 - x^2 + 1)"));
+        assert_eq!(factor("x^4 + 4"), simplified("(x^2 - x*2 + 2)*(x^2 + x*2 + 2)"));
+        assert_eq!(factor("(2*x^3 + 3*x - 5)*(3*x^4 - x + 7)"), simplified("(x - 1)*(x^2*2 + x*2 + 5)*(x^4*3 - x + 7)"));
+        assert_eq!(factor("x^4 + x^3 + x^2 + x + 1"), simplified("x^4 + x^3 + x^2 + x + 1"));
+    }
+
+    #[test]
+    fn content() {
+        assert_eq!(factor("2*x^2 - 2"), simplified("(x - 1)*(x + 1)*2"));
+        assert_eq!(factor("1 - x^2"), simplified("-(x - 1)*(x + 1)"));
+        assert_eq!(factor("x^2/2 - 1/2"), simplified("(x - 1)*(x + 1)/2"));
+        assert_eq!(factor("x^3 - x"), simplified("(x - 1)*(x + 1)*x"));
+        assert_eq!(factor("6"), simplified("6"));
+    }
+
+    #[test]
+    fn large_powers_stay_unexpanded() {
+        assert_eq!(factor("(x + 1)^100000000"), simplified("(x + 1)^100000000"));
+        assert_eq!(factor("(x + 1)^3000*2"), simplified("(x + 1)^3000*2"));
+    }
+
+    #[test]
+    fn multivariate() {
+        assert_eq!(factor("x^2 - y^2"), simplified("(x - y)*(x + y)"));
+        assert_eq!(factor("6*x^2 + 5*x*y + y^2"), simplified("(x*2 + y)*(x*3 + y)"));
+        assert_eq!(factor("(x^2+y^2+1)*(x*y-3)*(x - y)^2"), simplified("(x - y)^2*(x*y - 3)*(x^2 + y^2 + 1)"));
+        assert_eq!(factor("sin(x)^2 - 1"), simplified("(sin(x) - 1)*(sin(x) + 1)"));
+        assert_eq!(factor("(x^2 - 1)/(x^2 + x)"), simplified("(x - 1)*(x + 1)/((x + 1)*x)"));
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use crate::expr::*;
+use crate::interval::Interval;
+use crate::num::{Frac, NumError};
+use crate::simplify::SimplifcationOpts;
+
+fn func(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::func(FuncIdentifier::Name(String::from(name)), args)
+}
+
+// ln |u|, the antiderivative of 1/u on either side of its pole
+fn ln_abs(u: Expr) -> Expr {
+    func("ln", vec![Expr::func(FuncIdentifier::Abs, vec![u])])
+}
+
+// 1/u as a power, so that 1/x^3 becomes x^-3 rather than (x^3)^-1
+fn reciprocal(u: &Expr) -> Expr {
+    if let Operator::Pow = u.operator {
+        let els = u.children();
+        if let Operator::Frac(f) = &els[1].operator {
+            return Expr::pow(els[0].clone(), Expr::frac_cpy(&-f));
+        }
+    }
+    return Expr::pow(u.clone(), Expr::int(-1));
+}
+
+// The slope a when `u` is a x + b in `var`, so that the integral of f(u) is F(u) / a
+fn slope(u: &Expr, var: &str) -> Result<Option<Expr>, NumError> {
+    let slope = u.diff(var)?;
+    if slope.depends_on(var) || slope == Expr::int(0) {
+        return Ok(None);
+    }
+    return Ok(Some(slope));
+}
+
+// The subexpressions whose zeros are poles of `expr`: arguments of ln, bases of negative
+// powers and divisors
+fn pole_arguments<'a>(expr: &'a Expr, var: &str, out: &mut Vec<&'a Expr>) {
+    match &expr.operator {
+        Operator::Func(FuncIdentifier::Name(name)) if name == "ln" => out.push(&expr.children()[0]),
+        Operator::Pow => match &expr.children()[1].operator {
+            Operator::Frac(f) if f.is_negative() => out.push(&expr.children()[0]),
+            _ => {}
+        },
+        Operator::Div => out.push(&expr.children()[1]),
+        _ => {}
+    }
+    for el in expr.elements.iter().flatten() {
+        if el.depends_on(var) {
+            pole_arguments(el, var, out);
+        }
+    }
+}
+
+// The bases of fractional powers, such as x in x^(3/2), which are only real where they are
+// not negative
+fn root_bases<'a>(expr: &'a Expr, var: &str, out: &mut Vec<&'a Expr>) {
+    if let Operator::Pow = expr.operator {
+        match &expr.children()[1].operator {
+            Operator::Frac(f) if !f.is_int() => out.push(&expr.children()[0]),
+            _ => {}
+        }
+    }
+    for el in expr.elements.iter().flatten() {
+        if el.depends_on(var) {
+            root_bases(el, var, out);
+        }
+    }
+}
+
+// Whether an antiderivative stays finite and real from `lower` to `upper`, checked by
+// bounding each pole argument and root base over the interval. Symbolic bounds cannot be
+// checked, so any pole or root counts.
+fn continuous_between(integral: &Expr, var: &str, lower: &Expr, upper: &Expr) -> bool {
+    let (mut args, mut bases) = (Vec::new(), Vec::new());
+    pole_arguments(integral, var, &mut args);
+    root_bases(integral, var, &mut bases);
+    args.retain(|arg| arg.depends_on(var));
+    bases.retain(|base| base.depends_on(var));
+    if args.is_empty() && bases.is_empty() {
+        return true;
+    }
+    let (a, b) = match (lower.eval_f64(&HashMap::new()), upper.eval_f64(&HashMap::new())) {
+        (Ok(a), Ok(b)) if a.is_finite() && b.is_finite() => (a.min(b), a.max(b)),
+        _ => return false
+    };
+    let env = [(String::from(var), Interval::new(a, b))].iter().cloned().collect::<HashMap<String, Interval>>();
+    return args.iter().all(|arg| matches!(arg.eval_interval(&env), Ok(bounds) if !bounds.contains(0.0)))
+        && bases.iter().all(|base| matches!(base.eval_interval(&env), Ok(bounds) if bounds.lo >= 0.0));
+}
+
+impl Expr {
+    // Replaces every occurrence of the variable `var` with `value`
+    pub fn substitute(&self, var: &str, value: &Expr) -> Expr {
+        match &self.operator {
+            Operator::Name(name) if name == var => value.clone(),
+            _ => Expr::new(self.operator.clone(), self.elements.as_ref().map(|els| els.iter().map(|el| el.substitute(var, value)).collect()))
+        }
+    }
+
+    // An antiderivative before simplification, or None when no rule applies
+    fn antiderivative(&self, var: &str) -> Result<Option<Expr>, NumError> {
+        if !self.depends_on(var) {
+            return Ok(Some(self.clone() * Expr::var(var)));
+        }
+        match &self.operator {
+            Operator::Name(_) => Ok(Some(Expr::frac(1, 2) * Expr::pow(self.clone(), Expr::int(2)))),
+            Operator::Add | Operator::Sub => {
+                let mut terms = Vec::new();
+                for child in self.children().iter() {
+                    match child.antiderivative(var)? {
+                        Some(term) => terms.push(term),
+                        None => return Ok(None)
+                    }
+                }
+                Ok(Some(Expr::new(self.operator.clone(), Some(terms))))
+            },
+            // Constant factors come out, leaving a single factor that depends on `var`
+            Operator::Mul => {
+                let (inner, factors): (Vec<Expr>, Vec<Expr>) = self.children().iter().cloned().partition(|el| el.depends_on(var));
+                if inner.len() != 1 {
+                    return Ok(None);
+                }
+                let integral = match inner[0].antiderivative(var)? {
+                    Some(integral) => integral,
+                    None => return Ok(None)
+                };
+                let mut factors = factors;
+                factors.push(integral);
+                Ok(Some(Expr::new(Operator::Mul, Some(factors))))
+            },
+            Operator::Div => {
+                let els = self.children();
+                let (num, denom) = (&els[0], &els[1]);
+                if !denom.depends_on(var) {
+                    return Ok(num.antiderivative(var)?.map(|integral| integral / denom.clone()));
+                }
+                if !num.depends_on(var) {
+                    return Ok(reciprocal(denom).antiderivative(var)?.map(|integral| num.clone() * integral));
+                }
+                Ok(None)
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let (u, v) = (&els[0], &els[1]);
+                if !v.depends_on(var) {
+                    let a = match slope(u, var)? {
+                        Some(a) => a,
+                        None => return Ok(None)
+                    };
+                    // The power rule, with u^-1 integrating to ln |u|
+                    return Ok(Some(match &v.operator {
+                        Operator::Frac(f) if *f == Frac::new(-1, 1) => ln_abs(u.clone()) / a,
+                        Operator::Frac(f) => {
+                            let raised = f.clone() + Frac::new(1, 1);
+                            Expr::pow(u.clone(), Expr::frac_cpy(&raised)) / (Expr::frac_cpy(&raised) * a)
+                        },
+                        _ => {
+                            let raised = v.clone() + Expr::int(1);
+                            Expr::pow(u.clone(), raised.clone()) / (raised * a)
+                        }
+                    }));
+                }
+                if u.depends_on(var) {
+                    return Ok(None);
+                }
+                let a = match slope(v, var)? {
+                    Some(a) => a,
+                    None => return Ok(None)
+                };
+                // e^v / a, or c^v / (ln(c) a) for any other constant base
+                Ok(Some(match u.operator {
+                    Operator::Const(Constant::E) => self.clone() / a,
+                    _ => self.clone() / (func("ln", vec![u.clone()]) * a)
+                }))
+            },
+            Operator::Func(ident) => {
+                let args = self.children();
+                if args.len() != 1 {
+                    return Ok(None);
+                }
+                let u = &args[0];
+                let a = match slope(u, var)? {
+                    Some(a) => a,
+                    None => return Ok(None)
+                };
+                Ok(match ident {
+                    FuncIdentifier::Sine => Some(Expr::func(FuncIdentifier::Cosine, vec![u.clone()]) * Expr::int(-1) / a),
+                    FuncIdentifier::Cosine => Some(Expr::func(FuncIdentifier::Sine, vec![u.clone()]) / a),
+                    // tan u = sin u / cos u integrates to -ln |cos u|
+                    FuncIdentifier::Tangent => Some(ln_abs(Expr::func(FuncIdentifier::Cosine, vec![u.clone()])) * Expr::int(-1) / a),
+                    _ => None
+                })
+            },
+            _ => Ok(None)
+        }
+    }
+
+    // An antiderivative with respect to `var`, without a constant of integration. Integrals
+    // that none of the rules cover come back unevaluated as integral(f, var).
+    pub fn integrate(&self, var: &str) -> Result<Expr, NumError> {
+        let mut integrand = self.clone();
+        integrand.simplify(&SimplifcationOpts::default())?;
+        let mut out = match integrand.antiderivative(var)? {
+            Some(integral) => integral,
+            None => return Ok(func("integral", vec![self.clone(), Expr::var(var)]))
+        };
+        out.simplify(&SimplifcationOpts::default())?;
+        return Ok(out);
+    }
+
+    // F(upper) - F(lower) for an antiderivative F, or integral(f, var, lower, upper) when
+    // there is none or F has a pole between the bounds
+    pub fn integrate_between(&self, var: &str, lower: &Expr, upper: &Expr) -> Result<Expr, NumError> {
+        let mut integrand = self.clone();
+        integrand.simplify(&SimplifcationOpts::default())?;
+        let integral = match integrand.antiderivative(var)? {
+            Some(integral) if continuous_between(&integral, var, lower, upper) => integral,
+            _ => return Ok(func("integral", vec![self.clone(), Expr::var(var), lower.clone(), upper.clone()]))
+        };
+        let mut out = integral.substitute(var, upper) - integral.substitute(var, lower);
+        out.simplify(&SimplifcationOpts::default())?;
+        return Ok(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::expr::*;
+    use crate::parse::parse;
+
+    fn integrate(input: &str) -> Expr {
+        parse(input).unwrap().integrate("x").unwrap()
+    }
+
+    fn simplified(input: &str) -> Expr {
+        parse(input).unwrap().simplified().unwrap()
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(integrate("3"), simplified("3*x"));
+        assert_eq!(integrate("1/x"), simplified("ln(abs(x))"));
+        assert_eq!(integrate("(2*x + 1)^-1"), simplified("ln(abs(2*x + 1)) / 2"));
+        assert_eq!(integrate("cos(x)"), simplified("sin(x)"));
+        assert_eq!(integrate("tan(x)"), simplified("-ln(abs(cos(x)))"));
+        assert_eq!(integrate("x^y"), simplified("x^(y + 1) / (y + 1)"));
+        assert_eq!(integrate("2^x"), simplified("2^x / ln(2)"));
+    }
+
+    #[test]
+    fn differentiates_back() {
+        let inputs = ["x^3 - 4*x + 7", "5*x^(1/2) + 2/x^3", "y*sin(3*x - 1)", "cos(x/2) * 4 + e^(2*x)", "(1 - x)^(5/2)"];
+        for input in inputs.iter() {
+            let expr = parse(input).unwrap();
+            let back = expr.integrate("x").unwrap().diff("x").unwrap();
+            for x in [0.2, 0.5, 0.9].iter() {
+                let env: HashMap<String, f64> = [(String::from("x"), *x), (String::from("y"), 1.5)].iter().cloned().collect();
+                let (a, b) = (expr.eval_f64(&env).unwrap(), back.eval_f64(&env).unwrap());
+                assert!((a - b).abs() < 1e-12 * a.abs().max(1.0), "{}: {} vs {}", input, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn definite_and_unsupported() {
+        let expr = parse("3*x^2 + 2*x").unwrap();
+        assert_eq!(expr.integrate_between("x", &Expr::int(1), &Expr::int(3)).unwrap(), Expr::int(34));
+        assert_eq!(parse("x^2").unwrap().integrate_between("x", &Expr::int(0), &Expr::var("t")).unwrap(), simplified("t^3 / 3"));
+
+        let integral = |args: Vec<Expr>| Expr::func(FuncIdentifier::Name(String::from("integral")), args);
+        let hard = parse("sin(x^2)").unwrap();
+        assert_eq!(integrate("sin(x^2)"), integral(vec![hard.clone(), Expr::var("x")]));
+        assert_eq!(hard.integrate_between("x", &Expr::int(0), &Expr::int(1)).unwrap(), integral(vec![hard, Expr::var("x"), Expr::int(0), Expr::int(1)]));
+        assert_eq!(integrate("x*sin(x)"), integral(vec![parse("x*sin(x)").unwrap(), Expr::var("x")]));
+
+        // The antiderivatives have poles that F(1) - F(-1) would step over
+        for input in ["x^-2", "1/x", "1/(2*x - 1)", "x^(1/2)", "(1 - 2*x)^(3/2)"].iter() {
+            let expr = parse(input).unwrap();
+            assert_eq!(expr.integrate_between("x", &Expr::int(-1), &Expr::int(1)).unwrap(), integral(vec![expr, Expr::var("x"), Expr::int(-1), Expr::int(1)]));
+        }
+        assert_eq!(parse("x^-2").unwrap().integrate_between("x", &Expr::int(1), &Expr::int(2)).unwrap(), Expr::frac(1, 2));
+        assert_eq!(parse("1/x").unwrap().integrate_between("x", &Expr::int(-2), &Expr::int(-1)).unwrap(), simplified("ln(1) - ln(2)"));
+        assert_eq!(parse("x^(1/2)").unwrap().integrate_between("x", &Expr::int(0), &Expr::int(4)).unwrap(), Expr::frac(16, 3));
+        assert_ne!(integrate("(1 - 2*x)^(3/2)"), integral(vec![parse("(1 - 2*x)^(3/2)").unwrap(), Expr::var("x")]));
+        let root = parse("x^(1/2)").unwrap();
+        assert_eq!(root.integrate_between("x", &Expr::int(0), &Expr::var("t")).unwrap(), integral(vec![root, Expr::var("x"), Expr::int(0), Expr::var("t")]));
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt;
+use crate::eval::{check_arity, EvalError};
+use crate::expr::*;
+use crate::num::{Frac, Int};
+
+// Library functions such as sin and powf are not correctly rounded, so their results are
+// widened by this many units in the last place instead of one
+const LIBM_ULPS: u32 = 2;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64
+}
+
+fn down(x: f64) -> f64 {
+    if x.is_finite() { x.next_down() } else { x }
+}
+
+fn up(x: f64) -> f64 {
+    if x.is_finite() { x.next_up() } else { x }
+}
+
+// The next representable values below and above the exact result of an operation, given
+// the rounded result and the sign of its rounding error. Sums, products and quotients only
+// move when they were inexact, which the error-free transformations below detect.
+fn directed(val: f64, err: f64) -> (f64, f64) {
+    if !val.is_finite() || err == 0.0 {
+        return (val, val);
+    }
+    if err > 0.0 { (val, up(val)) } else { (down(val), val) }
+}
+
+// A finite operation that rounds to infinity still has a finite exact result, which lies
+// beyond the largest finite value
+fn overflowed(val: f64) -> (f64, f64) {
+    if val > 0.0 { (f64::MAX, val) } else { (val, -f64::MAX) }
+}
+
+fn add_end(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    if sum.is_infinite() && a.is_finite() && b.is_finite() {
+        return overflowed(sum);
+    }
+    let b_part = sum - a;
+    let err = (a - (sum - b_part)) + (b - b_part);
+    directed(sum, err)
+}
+
+// 0 * inf counts as 0 at interval endpoints, since the infinite end is never reached
+fn mul_end(a: f64, b: f64) -> (f64, f64) {
+    if a == 0.0 || b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let product = a * b;
+    if product.is_infinite() && a.is_finite() && b.is_finite() {
+        return overflowed(product);
+    }
+    directed(product, a.mul_add(b, -product))
+}
+
+fn div_end(a: f64, b: f64) -> (f64, f64) {
+    let quot = a / b;
+    if b.is_infinite() {
+        return (quot, quot);
+    }
+    if quot.is_infinite() && a.is_finite() {
+        return overflowed(quot);
+    }
+    let rem = (-quot).mul_add(b, a);
+    directed(quot, if b > 0.0 { rem } else { -rem })
+}
+
+// The smallest lower and largest upper end over each candidate pair. Unbounded ends can
+// meet as inf - inf or inf / inf, and nothing is known about the result there.
+fn span(ends: &[(f64, f64)]) -> Interval {
+    if ends.iter().any(|end| end.0.is_nan() || end.1.is_nan()) {
+        return Interval::entire();
+    }
+    let lo = ends.iter().map(|end| end.0).fold(f64::INFINITY, f64::min);
+    let hi = ends.iter().map(|end| end.1).fold(f64::NEG_INFINITY, f64::max);
+    Interval::new(lo, hi)
+}
+
+fn is_odd(n: &Int) -> bool {
+    n % &Int::from(2) != 0
+}
+
+impl Interval {
+    // Panics unless lo <= hi, which also rules out NaN bounds. checked_new reports an error
+    // instead, for bounds that come from outside the crate.
+    pub fn new(lo: f64, hi: f64) -> Interval {
+        assert!(lo <= hi, "interval bounds are out of order");
+        Interval { lo, hi }
+    }
+
+    pub fn checked_new(lo: f64, hi: f64) -> Result<Interval, EvalError> {
+        if lo.is_nan() || hi.is_nan() || lo > hi {
+            return Err(EvalError::InvalidInterval);
+        }
+        Ok(Interval { lo, hi })
+    }
+
+    // Panics for NaN, like new
+    pub fn point(x: f64) -> Interval {
+        Interval::new(x, x)
+    }
+
+    pub fn entire() -> Interval {
+        Interval::new(f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn width(&self) -> f64 {
+        self.hi - self.lo
+    }
+
+    fn outward(lo: f64, hi: f64) -> Interval {
+        Interval::new(down(lo), up(hi))
+    }
+
+    fn widen(self, ulps: u32) -> Interval {
+        let mut out = self;
+        for _ in 0..ulps {
+            out = Interval::outward(out.lo, out.hi);
+        }
+        return out;
+    }
+
+    fn neg(self) -> Interval {
+        Interval::new(-self.hi, -self.lo)
+    }
+
+    fn add(self, other: Interval) -> Interval {
+        let lo = add_end(self.lo, other.lo).0;
+        let hi = add_end(self.hi, other.hi).1;
+        if lo.is_nan() || hi.is_nan() {
+            return Interval::entire();
+        }
+        return Interval::new(lo, hi);
+    }
+
+    fn mul(self, other: Interval) -> Interval {
+        span(&[mul_end(self.lo, other.lo), mul_end(self.lo, other.hi), mul_end(self.hi, other.lo), mul_end(self.hi, other.hi)])
+    }
+
+    // A divisor that straddles zero splits the quotient in two, which is returned as the
+    // interval spanning both pieces
+    fn div(self, other: Interval) -> Result<Interval, EvalError> {
+        if other.lo == 0.0 && other.hi == 0.0 {
+            return Err(EvalError::DivisionByZero);
+        }
+        if other.lo > 0.0 || other.hi < 0.0 {
+            return Ok(span(&[div_end(self.lo, other.lo), div_end(self.lo, other.hi), div_end(self.hi, other.lo), div_end(self.hi, other.hi)]));
+        }
+        if self.contains(0.0) || (other.lo < 0.0 && other.hi > 0.0) {
+            return Ok(Interval::entire());
+        }
+        // One end of the divisor is zero and the dividend has a fixed sign
+        let end = if other.lo == 0.0 { other.hi } else { other.lo };
+        let positive = (self.lo > 0.0) == (other.lo == 0.0);
+        let near = if self.lo > 0.0 { div_end(self.lo, end) } else { div_end(self.hi, end) };
+        return Ok(if positive {
+            Interval::new(near.0, f64::INFINITY)
+        } else {
+            Interval::new(f64::NEG_INFINITY, near.1)
+        });
+    }
+
+    fn abs(self) -> Interval {
+        if self.lo >= 0.0 {
+            return self;
+        }
+        if self.hi <= 0.0 {
+            return self.neg();
+        }
+        return Interval::new(0.0, self.hi.max(-self.lo));
+    }
+
+    // Repeated squaring on a point, kept as an interval so that each product rounds outward
+    fn pow_point(x: f64, n: u32) -> Interval {
+        let mut out = Interval::point(1.0);
+        let mut base = Interval::point(x);
+        let mut n = n;
+        while n > 0 {
+            if n % 2 == 1 {
+                out = out.mul(base);
+            }
+            base = base.mul(base);
+            n /= 2;
+        }
+        return out;
+    }
+
+    // Even powers fall to their minimum at the point nearest zero, and odd powers increase
+    fn pow_int(self, n: i64) -> Result<Interval, EvalError> {
+        if n < 0 {
+            return Interval::point(1.0).div(self.pow_int(-n)?);
+        }
+        let n = n as u32;
+        if n.is_multiple_of(2) {
+            let abs = self.abs();
+            return Ok(Interval::new(Interval::pow_point(abs.lo, n).lo, Interval::pow_point(abs.hi, n).hi));
+        }
+        return Ok(Interval::new(Interval::pow_point(self.lo, n).lo, Interval::pow_point(self.hi, n).hi));
+    }
+
+    // |x|^e for positive e, signed like x when `odd` so that odd roots of negative numbers
+    // stay real as in eval_f64. The exponent is itself rounded, which moves the result by up
+    // to |e ln x| / 2 units in the last place on top of the error in powf.
+    fn pow_root(self, exponent: f64, odd: bool) -> Interval {
+        let root = |x: f64| {
+            let magnitude = x.abs().powf(exponent);
+            if magnitude.is_infinite() && x.is_finite() {
+                let (lo, hi) = overflowed(if odd && x < 0.0 { -magnitude } else { magnitude });
+                return Interval::new(lo, hi);
+            }
+            let ulps = LIBM_ULPS + (exponent * x.abs().ln()).abs().min(2048.0).ceil() as u32;
+            let bounds = Interval::point(if odd && x < 0.0 { -magnitude } else { magnitude }).widen(ulps);
+            if x == 0.0 { Interval::point(0.0) } else { bounds }
+        };
+        if odd {
+            return Interval::new(root(self.lo).lo, root(self.hi).hi);
+        }
+        let abs = self.abs();
+        return Interval::new(root(abs.lo).lo, root(abs.hi).hi).clamp_below(0.0);
+    }
+
+    fn clamp_below(self, lo: f64) -> Interval {
+        Interval::new(self.lo.max(lo), self.hi.max(lo))
+    }
+
+    fn clamp(self, lo: f64, hi: f64) -> Interval {
+        Interval::new(self.lo.max(lo).min(hi), self.hi.min(hi).max(lo))
+    }
+
+    // Powers with a real exponent are only defined for a non-negative base, so the negative
+    // part of the base is dropped. On that domain x^y is monotone in each argument and its
+    // extremes lie at the corners.
+    fn pow_real(self, exponent: Interval) -> Result<Interval, EvalError> {
+        if self.hi < 0.0 {
+            return Err(EvalError::NotReal);
+        }
+        let base = self.clamp_below(0.0);
+        let corners = [base.lo.powf(exponent.lo), base.lo.powf(exponent.hi), base.hi.powf(exponent.lo), base.hi.powf(exponent.hi)];
+        // Only an overflow makes every corner infinite when the base and exponent are finite
+        let finite = base.hi.is_finite() && exponent.lo.is_finite() && exponent.hi.is_finite();
+        let mut lo = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+        if finite && lo == f64::INFINITY {
+            lo = f64::MAX;
+        }
+        let hi = corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        return Ok(Interval::new(lo, hi).widen(LIBM_ULPS).clamp_below(0.0));
+    }
+
+    fn pow(self, exponent: Interval, exact: Option<&Frac>) -> Result<Interval, EvalError> {
+        match exact {
+            Some(f) if f.is_int() && f.num().abs() <= i32::MAX as i64 => self.pow_int(f.num().to_i64().unwrap()),
+            Some(f) if is_odd(f.denom()) => {
+                let odd = is_odd(f.num());
+                if f.is_negative() {
+                    return Interval::point(1.0).div(self.pow_root(-f.to_f64(), odd));
+                }
+                return Ok(self.pow_root(f.to_f64(), odd));
+            },
+            _ => self.pow_real(exponent)
+        }
+    }
+
+    // Whether some offset + k * period lies in the interval, erring towards yes near the ends
+    fn hits(self, offset: f64, period: f64) -> bool {
+        let first = ((self.lo - offset) / period).ceil();
+        return first * period + offset <= self.hi * (1.0 + 4.0 * f64::EPSILON) + 4.0 * f64::EPSILON;
+    }
+
+    // The image of a monotone stretch is spanned by its ends, widened to the enclosing
+    // extremes wherever a peak or trough falls inside the interval
+    fn periodic(self, f: fn(f64) -> f64, peak: f64) -> Interval {
+        if self.width() >= 2.0 * PI || !self.width().is_finite() {
+            return Interval::new(-1.0, 1.0);
+        }
+        let (a, b) = (f(self.lo), f(self.hi));
+        let mut out = Interval::new(a.min(b), a.max(b)).widen(LIBM_ULPS);
+        if self.hits(peak, 2.0 * PI) {
+            out.hi = 1.0;
+        }
+        if self.hits(peak + PI, 2.0 * PI) {
+            out.lo = -1.0;
+        }
+        return out.clamp(-1.0, 1.0);
+    }
+
+    fn sin(self) -> Interval {
+        self.periodic(f64::sin, PI / 2.0)
+    }
+
+    fn cos(self) -> Interval {
+        self.periodic(f64::cos, 0.0)
+    }
+
+    // tan increases between its poles, so anything spanning a pole is unbounded
+    fn tan(self) -> Interval {
+        if self.width() >= PI || !self.width().is_finite() || self.hits(PI / 2.0, PI) {
+            return Interval::entire();
+        }
+        return Interval::new(self.lo.tan(), self.hi.tan()).widen(LIBM_ULPS);
+    }
+
+    fn constant(val: f64) -> Interval {
+        if val.is_infinite() {
+            let (lo, hi) = overflowed(val);
+            return Interval::new(lo, hi);
+        }
+        Interval::outward(val, val)
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+impl Expr {
+    // Bounds the expression over every combination of values in `env`. The result always
+    // contains the true range, though it may be wider when a variable appears more than once.
+    pub fn eval_interval(&self, env: &HashMap<String, Interval>) -> Result<Interval, EvalError> {
+        match &self.operator {
+            Operator::Frac(f) => {
+                if f.denom().is_zero() {
+                    return Err(EvalError::DivisionByZero);
+                }
+                let val = f.to_f64();
+                if f.is_int() && val.abs() <= 9007199254740992.0 {
+                    return Ok(Interval::point(val));
+                }
+                Ok(Interval::constant(val).widen(1))
+            },
+            Operator::Add => {
+                let mut sum = Interval::point(0.0);
+                for child in self.children().iter() {
+                    sum = sum.add(child.eval_interval(env)?);
+                }
+                Ok(sum)
+            },
+            Operator::Mul => {
+                let mut product = Interval::point(1.0);
+                for child in self.children().iter() {
+                    product = product.mul(child.eval_interval(env)?);
+                }
+                Ok(product)
+            },
+            Operator::Div => {
+                let els = self.children();
+                els[0].eval_interval(env)?.div(els[1].eval_interval(env)?)
+            },
+            Operator::Sub => {
+                let els = self.children();
+                let mut diff = els[0].eval_interval(env)?;
+                for child in els[1..].iter() {
+                    diff = diff.add(child.eval_interval(env)?.neg());
+                }
+                Ok(diff)
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let exact = match &els[1].operator {
+                    Operator::Frac(f) => Some(f),
+                    _ => None
+                };
+                els[0].eval_interval(env)?.pow(els[1].eval_interval(env)?, exact)
+            },
+            Operator::Name(name) => {
+                let bound = env.get(name).ok_or_else(|| EvalError::UnboundVariable(name.clone()))?;
+                // The fields are public, so a binding may never have gone through new
+                Interval::checked_new(bound.lo, bound.hi)
+            },
+            Operator::Func(FuncIdentifier::Name(name)) => Err(EvalError::UnknownFunction(name.clone())),
+            Operator::Func(ident) => {
+                let args = self.children();
+                check_arity(&ident.get_name(), args, 1)?;
+                let x = args[0].eval_interval(env)?;
+                Ok(match ident {
+                    FuncIdentifier::Sine => x.sin(),
+                    FuncIdentifier::Cosine => x.cos(),
+                    FuncIdentifier::Tangent => x.tan(),
+                    FuncIdentifier::Abs => x.abs(),
+                    FuncIdentifier::Name(_) => unreachable!()
+                })
+            },
+            Operator::Const(Constant::Pi) => Ok(Interval::constant(PI)),
+            Operator::Const(Constant::E) => Ok(Interval::constant(std::f64::consts::E)),
+            Operator::Const(Constant::I) => Err(EvalError::NotReal),
+            // Not a real number, and inf - inf or inf/inf has no bounds at all
+            Operator::Const(Constant::Infinity) => Err(EvalError::NotReal)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::eval::EvalError;
+    use crate::parse::parse;
+    use super::Interval;
+
+    fn eval(input: &str, lo: f64, hi: f64) -> Result<Interval, EvalError> {
+        let env = [(String::from("x"), Interval::new(lo, hi))].iter().cloned().collect::<HashMap<String, Interval>>();
+        parse(input).unwrap().eval_interval(&env)
+    }
+
+    fn close(interval: Interval, lo: f64, hi: f64) -> bool {
+        interval.lo <= lo && interval.hi >= hi && (interval.lo - lo).abs() < 1e-12 && (interval.hi - hi).abs() < 1e-12
+    }
+
+    #[test]
+    fn powers_abs_and_division() {
+        assert!(close(eval("x^2", -2.0, 1.0).unwrap(), 0.0, 4.0));
+        assert!(close(eval("x^3", -2.0, 1.0).unwrap(), -8.0, 1.0));
+        assert!(close(eval("x^(1/3)", -8.0, 27.0).unwrap(), -2.0, 3.0));
+        assert!(close(eval("x^(2/3)", -8.0, 1.0).unwrap(), 0.0, 4.0));
+        assert!(close(eval("abs(x) + 1", -3.0, 2.0).unwrap(), 1.0, 4.0));
+        assert!(eval("x^2 + 1", -2.0, 3.0).unwrap().lo >= 1.0);
+
+        assert_eq!(eval("1/x", -1.0, 2.0).unwrap(), Interval::entire());
+        let half = eval("1/x", 0.0, 2.0).unwrap();
+        assert!(half.lo <= 0.5 && half.lo > 0.49 && half.hi == f64::INFINITY);
+        assert_eq!(eval("1/(x - x)", 0.0, 0.0), Err(EvalError::DivisionByZero));
+        assert_eq!(eval("x^(1/2)", -4.0, -1.0), Err(EvalError::NotReal));
+        assert!(close(eval("x^(1/2)", -4.0, 9.0).unwrap(), 0.0, 3.0));
+    }
+
+    #[test]
+    fn overflow_stays_finite() {
+        let bounds = eval("e^(1000*x) - e^(1000*x)", 1.0, 2.0).unwrap();
+        assert!(bounds.contains(0.0));
+        let huge = eval("e^(1000*x)", 1.0, 2.0).unwrap();
+        assert!(huge.lo.is_finite() && huge.lo > 1e307 && huge.hi == f64::INFINITY);
+        assert!(eval("10^400 - 10^400 + x", 1.0, 2.0).unwrap().contains(1.5));
+        assert!(eval("e^(1000*x) / e^(1000*x)", 1.0, 2.0).unwrap().contains(1.0));
+        assert_eq!(eval("inf - inf", 1.0, 2.0), Err(EvalError::NotReal));
+        assert_eq!(eval("inf/inf", 1.0, 2.0), Err(EvalError::NotReal));
+    }
+
+    #[test]
+    fn invalid_bounds() {
+        assert_eq!(Interval::checked_new(1.0, 0.0), Err(EvalError::InvalidInterval));
+        assert_eq!(Interval::checked_new(f64::NAN, 1.0), Err(EvalError::InvalidInterval));
+        assert_eq!(Interval::checked_new(0.0, f64::INFINITY), Ok(Interval::new(0.0, f64::INFINITY)));
+        let env = [(String::from("x"), Interval { lo: 1.0, hi: 0.0 })].iter().cloned().collect::<HashMap<String, Interval>>();
+        assert_eq!(parse("x + 1").unwrap().eval_interval(&env), Err(EvalError::InvalidInterval));
+    }
+
+    #[test]
+    fn trig_extrema() {
+        assert!(close(eval("sin(x)", 0.0, 2.0).unwrap(), 0.0, 1.0));
+        assert!(close(eval("cos(x)", 3.0, 4.0).unwrap(), -1.0, 4f64.cos()));
+        assert!(close(eval("sin(x)", -0.5, 0.5).unwrap(), (-0.5f64).sin(), 0.5f64.sin()));
+        assert_eq!(eval("cos(x)", -100.0, 100.0).unwrap(), Interval::new(-1.0, 1.0));
+        assert_eq!(eval("tan(x)", 1.0, 2.0).unwrap(), Interval::entire());
+        assert!(close(eval("tan(x)", -1.0, 1.0).unwrap(), -(1f64.tan()), 1f64.tan()));
+    }
+
+    #[test]
+    fn encloses_point_evaluations() {
+        let inputs = ["sin(x)^2 * pi - x/7 + abs(x - 1)^(1/3)", "(x^2 - 3*x) / (2 + cos(x)) + e^x", "tan(x/4) - x^-2"];
+        for input in inputs.iter() {
+            let expr = parse(input).unwrap();
+            let bounds = eval(input, 0.25, 3.5).unwrap();
+            for idx in 0..=100 {
+                let x = 0.25 + 3.25 * idx as f64 / 100.0;
+                let val = expr.eval_f64(&[(String::from("x"), x)].iter().cloned().collect()).unwrap();
+                assert!(bounds.contains(val), "{} at {} = {} outside {}", input, x, val, bounds);
+            }
+        }
+    }
+}
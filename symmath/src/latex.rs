@@ -0,0 +1,761 @@
+use std::ops::Range;
+use crate::expr::*;
+use crate::num;
+use crate::parse::{decimal, ParseError, ParseErrorKind};
+use crate::pretty::unsigned;
+
+// Binding strength of a node when typeset. \frac{}{} and function calls delimit
+// themselves, so they bind as tightly as a plain symbol.
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.operator {
+        Operator::Add | Operator::Sub => 1,
+        Operator::Mul => 2,
+        Operator::Pow => 3,
+        _ => 4
+    }
+}
+
+fn frac_latex(frac: &num::Frac) -> String {
+    let sign = if frac.is_negative() { "-" } else { "" };
+    if frac.is_int() {
+        format!("{}{}", sign, frac.num().abs())
+    } else {
+        format!("{}\\frac{{{}}}{{{}}}", sign, frac.num().abs(), frac.denom().abs())
+    }
+}
+
+fn name_latex(name: &str) -> String {
+    if name.chars().count() == 1 { String::from(name) } else { format!("\\mathrm{{{}}}", name) }
+}
+
+fn parens(inner: String) -> String {
+    format!("\\left({}\\right)", inner)
+}
+
+impl Expr {
+    fn latex_operand(&self, min_precedence: u8, allow_minus: bool) -> String {
+        let latex = self.to_latex();
+        if precedence(self) < min_precedence || (!allow_minus && latex.starts_with('-')) {
+            parens(latex)
+        } else {
+            latex
+        }
+    }
+
+    pub fn to_latex(&self) -> String {
+        if let Some(term) = unsigned(self) {
+            return format!("-{}", term.latex_operand(2, false));
+        }
+
+        match &self.operator {
+            Operator::Frac(frac) => frac_latex(frac),
+            Operator::Add => {
+                let mut out = String::new();
+                for (idx, child) in self.children().iter().enumerate() {
+                    match unsigned(child) {
+                        Some(term) => {
+                            out.push_str(if idx == 0 { "-" } else { " - " });
+                            out.push_str(&term.latex_operand(2, false));
+                        },
+                        None => {
+                            if idx != 0 { out.push_str(" + "); }
+                            out.push_str(&child.latex_operand(1, idx == 0));
+                        }
+                    }
+                }
+                out
+            },
+            Operator::Sub => {
+                let mut out = String::new();
+                for (idx, child) in self.children().iter().enumerate() {
+                    if idx == 0 {
+                        out.push_str(&child.latex_operand(1, true));
+                    } else {
+                        out.push_str(" - ");
+                        out.push_str(&child.latex_operand(2, false));
+                    }
+                }
+                out
+            },
+            Operator::Mul => {
+                let els = self.children();
+                // A trailing reciprocal, as in x * (1/6), is typeset as \frac{x}{6}
+                if let Some(Operator::Frac(f)) = els.last().map(|el| &el.operator) {
+                    if f.num() == 1 && f.denom() > 1 && els.len() > 1 {
+                        let rest = &els[..els.len() - 1];
+                        let num = if rest.len() == 1 { rest[0].to_latex() } else { Expr::new(Operator::Mul, Some(rest.to_vec())).to_latex() };
+                        return format!("\\frac{{{}}}{{{}}}", num, f.denom());
+                    }
+                }
+
+                let mut out = String::new();
+                for (idx, child) in els.iter().enumerate() {
+                    let operand = child.latex_operand(2, idx == 0);
+                    if idx != 0 {
+                        // Juxtaposition reads as multiplication unless two numbers would run together,
+                        // or a parenthesised factor would read as a function argument
+                        let explicit = operand.starts_with(|c: char| c.is_ascii_digit()) || operand.starts_with("\\frac") || operand.starts_with("\\left(");
+                        out.push_str(if explicit { " \\cdot " } else { " " });
+                    }
+                    out.push_str(&operand);
+                }
+                out
+            },
+            Operator::Div => {
+                let els = self.children();
+                format!("\\frac{{{}}}{{{}}}", els[0].to_latex(), els[1].to_latex())
+            },
+            Operator::Pow => {
+                let els = self.children();
+                if let Operator::Frac(f) = &els[1].operator {
+                    if f.num() == 1 && f.denom() == 2 {
+                        return format!("\\sqrt{{{}}}", els[0].to_latex());
+                    } else if f.num() == 1 && f.denom() > 2 {
+                        return format!("\\sqrt[{}]{{{}}}", f.denom(), els[0].to_latex());
+                    }
+                }
+
+                let base = match &els[0].operator {
+                    Operator::Div => parens(els[0].to_latex()),
+                    Operator::Frac(f) if !f.is_int() => parens(els[0].to_latex()),
+                    _ => els[0].latex_operand(4, false)
+                };
+                format!("{}^{{{}}}", base, els[1].to_latex())
+            },
+            Operator::Name(name) => name_latex(name),
+            Operator::Func(ident) => {
+                let args = self.children().iter().map(|el| el.to_latex()).collect::<Vec<String>>().join(", ");
+                match ident {
+                    FuncIdentifier::Sine => format!("\\sin{}", parens(args)),
+                    FuncIdentifier::Cosine => format!("\\cos{}", parens(args)),
+                    FuncIdentifier::Tangent => format!("\\tan{}", parens(args)),
+                    FuncIdentifier::Abs => format!("\\left|{}\\right|", args),
+                    FuncIdentifier::Name(name) => {
+                        let head = if name.chars().count() == 1 { name.clone() } else { format!("\\operatorname{{{}}}", name) };
+                        format!("{}{}", head, parens(args))
+                    }
+                }
+            },
+            Operator::Const(Constant::Pi) => String::from("\\pi"),
+            Operator::Const(Constant::E) => String::from("e"),
+            Operator::Const(Constant::I) => String::from("i"),
+            Operator::Const(Constant::Infinity) => String::from("\\infty")
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+enum Token {
+    Number(num::Int, num::Int),
+    Letter(char),
+    Command(String),
+    Plus,
+    Minus,
+    Times,
+    Slash,
+    Caret,
+    Underscore,
+    Comma,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    // `\left|` and `\right|`, which unlike a bare `|` say which side they are on
+    AbsOpen,
+    AbsClose,
+    Pipe,
+    End
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(num, denom) => if *denom == 1 { format!("{}", num) } else { format!("{}/{}", num, denom) },
+            Token::Letter(c) => c.to_string(),
+            Token::Command(name) => format!("\\{}", name),
+            Token::Plus => String::from("+"),
+            Token::Minus => String::from("-"),
+            Token::Times => String::from("\\cdot"),
+            Token::Slash => String::from("/"),
+            Token::Caret => String::from("^"),
+            Token::Underscore => String::from("_"),
+            Token::Comma => String::from(","),
+            Token::LBrace => String::from("{"),
+            Token::RBrace => String::from("}"),
+            Token::LBracket => String::from("["),
+            Token::RBracket => String::from("]"),
+            Token::LParen => String::from("("),
+            Token::RParen => String::from(")"),
+            Token::AbsOpen => String::from("\\left|"),
+            Token::AbsClose => String::from("\\right|"),
+            Token::Pipe => String::from("|"),
+            Token::End => String::from("end of input")
+        }
+    }
+
+    // Whether this token can begin an operand, which makes `2x` or `x \sin y` an implicit product
+    fn starts_operand(&self) -> bool {
+        matches!(self, Token::Number(_, _) | Token::Letter(_) | Token::Command(_) | Token::LBrace | Token::LParen | Token::AbsOpen)
+    }
+}
+
+// Commands that only affect spacing
+const SPACING: [&str; 6] = [",", ";", ":", "!", "quad", "qquad"];
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    // Set after `^` or `_`, where "x^23" raises to 2 only
+    script: bool
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Lexer<'a> {
+        Lexer {
+            input,
+            pos: 0,
+            script: false
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, pred: F) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek_char() {
+            if !pred(c) { break; }
+            self.pos += c.len_utf8();
+        }
+        return &self.input[start..self.pos];
+    }
+
+    fn number(&mut self, start: usize) -> Result<Token, ParseError> {
+        let invalid = |end: usize| ParseError::new(start..end, ParseErrorKind::InvalidNumber);
+
+        // A script takes a single digit, so x^.5 has no exponent to read
+        if self.script {
+            self.pos += 1;
+            let f = decimal(&self.input[start..self.pos], "").ok_or_else(|| invalid(self.pos))?;
+            return Ok(Token::Number(f.num().clone(), f.denom().clone()));
+        }
+
+        let int = self.take_while(|c| c.is_ascii_digit());
+        let mut frac = "";
+        if self.peek_char() == Some('.') {
+            self.pos += 1;
+            frac = self.take_while(|c| c.is_ascii_digit());
+        }
+
+        if (int.is_empty() && frac.is_empty()) || self.peek_char() == Some('.') {
+            self.take_while(|c| c.is_ascii_digit() || c == '.');
+            return Err(invalid(self.pos));
+        }
+
+        let f = decimal(int, frac).ok_or_else(|| ParseError::new(start..self.pos, ParseErrorKind::InvalidNumber))?;
+        return Ok(Token::Number(f.num().clone(), f.denom().clone()));
+    }
+
+    // The delimiter after `\left` or `\right`
+    fn delimiter(&mut self, start: usize, open: bool) -> Result<Token, ParseError> {
+        self.take_while(char::is_whitespace);
+        let c = self.peek_char();
+        if let Some(c) = c {
+            self.pos += c.len_utf8();
+        }
+
+        return match (c, open) {
+            (Some('('), _) => Ok(Token::LParen),
+            (Some(')'), _) => Ok(Token::RParen),
+            (Some('['), _) => Ok(Token::LBracket),
+            (Some(']'), _) => Ok(Token::RBracket),
+            (Some('|'), true) => Ok(Token::AbsOpen),
+            (Some('|'), false) => Ok(Token::AbsClose),
+            (Some(c), _) => Err(ParseError::new(start..self.pos, ParseErrorKind::UnknownCharacter(c))),
+            (None, _) => Err(ParseError::new(self.pos..self.pos, ParseErrorKind::UnexpectedEnd { expected: vec![String::from("delimiter")] }))
+        };
+    }
+
+    fn next_token(&mut self) -> Result<(Token, Range<usize>), ParseError> {
+        loop {
+            self.take_while(char::is_whitespace);
+
+            let start = self.pos;
+            let c = match self.peek_char() {
+                Some(c) => c,
+                None => return Ok((Token::End, start..start))
+            };
+
+            let token = if c.is_ascii_digit() || c == '.' {
+                self.number(start)?
+            } else if c.is_alphabetic() {
+                self.pos += c.len_utf8();
+                Token::Letter(c)
+            } else if c == '\\' {
+                self.pos += 1;
+                let name = self.take_while(|c| c.is_ascii_alphabetic());
+                let name = if name.is_empty() {
+                    // Single-symbol commands such as `\,` or `\{`
+                    let c = self.peek_char().ok_or_else(|| ParseError::new(start..self.pos, ParseErrorKind::UnknownCharacter('\\')))?;
+                    self.pos += c.len_utf8();
+                    &self.input[start + 1..self.pos]
+                } else {
+                    name
+                };
+
+                match name {
+                    "left" => self.delimiter(start, true)?,
+                    "right" => self.delimiter(start, false)?,
+                    "cdot" | "times" => Token::Times,
+                    "{" => Token::LBrace,
+                    "}" => Token::RBrace,
+                    " " => continue,
+                    _ if SPACING.contains(&name) => continue,
+                    _ => Token::Command(String::from(name))
+                }
+            } else {
+                self.pos += c.len_utf8();
+                match c {
+                    '+' => Token::Plus,
+                    '-' => Token::Minus,
+                    '*' => Token::Times,
+                    '/' => Token::Slash,
+                    '^' => Token::Caret,
+                    '_' => Token::Underscore,
+                    ',' => Token::Comma,
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '|' => Token::Pipe,
+                    _ => return Err(ParseError::new(start..self.pos, ParseErrorKind::UnknownCharacter(c)))
+                }
+            };
+
+            self.script = matches!(token, Token::Caret | Token::Underscore);
+            return Ok((token, start..self.pos));
+        }
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    token: Token,
+    span: Range<usize>
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Result<Parser<'a>, ParseError> {
+        let mut lexer = Lexer::new(input);
+        let (token, span) = lexer.next_token()?;
+        Ok(Parser {
+            lexer, token, span
+        })
+    }
+
+    fn advance(&mut self) -> Result<Token, ParseError> {
+        let (token, span) = self.lexer.next_token()?;
+        self.span = span;
+        return Ok(std::mem::replace(&mut self.token, token));
+    }
+
+    fn unexpected(&self, expected: &[&str]) -> ParseError {
+        let expected = expected.iter().map(|e| String::from(*e)).collect();
+        let kind = match self.token {
+            Token::End => ParseErrorKind::UnexpectedEnd { expected },
+            _ => ParseErrorKind::UnexpectedToken { found: self.token.describe(), expected }
+        };
+        return ParseError::new(self.span.clone(), kind);
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), ParseError> {
+        if self.token != token {
+            return Err(self.unexpected(&[&token.describe()]));
+        }
+        self.advance()?;
+        return Ok(());
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.product()?;
+        let mut chained = false;
+
+        loop {
+            match self.token {
+                Token::Plus => {
+                    self.advance()?;
+                    let rhs = self.product()?;
+                    if chained {
+                        lhs.elements.as_mut().unwrap().push(rhs);
+                    } else {
+                        lhs = lhs + rhs;
+                        chained = true;
+                    }
+                },
+                Token::Minus => {
+                    self.advance()?;
+                    lhs = lhs - self.product()?;
+                    chained = false;
+                },
+                _ => return Ok(lhs)
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+        let mut chained = false;
+
+        loop {
+            match self.token {
+                Token::Slash => {
+                    self.advance()?;
+                    lhs = lhs / self.unary()?;
+                    chained = false;
+                    continue;
+                },
+                Token::Times => {
+                    self.advance()?;
+                },
+                ref t if t.starts_operand() => {},
+                _ => return Ok(lhs)
+            }
+
+            let rhs = self.unary()?;
+            if chained {
+                lhs.elements.as_mut().unwrap().push(rhs);
+            } else {
+                lhs = lhs * rhs;
+                chained = true;
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.token {
+            Token::Minus => {
+                self.advance()?;
+                let mut operand = self.unary()?;
+                if let Operator::Frac(ref mut f) = operand.operator {
+                    *f = -&*f;
+                    return Ok(operand);
+                }
+                return Ok(Expr::int(-1) * operand);
+            },
+            Token::Plus => {
+                self.advance()?;
+                return self.unary();
+            },
+            _ => self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.primary()?;
+        if self.token != Token::Caret {
+            return Ok(base);
+        }
+
+        self.advance()?;
+        let exponent = self.script()?;
+        return Ok(Expr::pow(base, exponent));
+    }
+
+    // The argument of `^`: a braced group or a single symbol
+    fn script(&mut self) -> Result<Expr, ParseError> {
+        match self.token {
+            Token::LBrace => self.group(),
+            Token::Minus => {
+                self.advance()?;
+                let operand = self.script()?;
+                return Ok(Expr::int(-1) * operand);
+            },
+            _ => self.primary()
+        }
+    }
+
+    fn group(&mut self) -> Result<Expr, ParseError> {
+        self.expect(Token::LBrace)?;
+        let inner = self.sum()?;
+        self.expect(Token::RBrace)?;
+        return Ok(inner);
+    }
+
+    // The literal text of a group such as `\mathrm{speed}` or `x_{12}`
+    fn text(&mut self) -> Result<String, ParseError> {
+        let braced = self.token == Token::LBrace;
+        if braced {
+            self.advance()?;
+        }
+
+        let mut text = String::new();
+        loop {
+            match self.token.clone() {
+                Token::Letter(c) => text.push(c),
+                Token::Number(num, denom) if denom == 1 => text.push_str(&num.to_string()),
+                Token::Underscore if braced => text.push('_'),
+                _ => break
+            }
+            self.advance()?;
+            if !braced { break; }
+        }
+
+        if text.is_empty() {
+            return Err(self.unexpected(&["letter", "digit"]));
+        }
+        if braced {
+            self.expect(Token::RBrace)?;
+        }
+        return Ok(text);
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        if !self.token.starts_operand() && self.token != Token::Pipe {
+            return Err(self.unexpected(&["number", "letter", "command", "("]));
+        }
+
+        let start = self.span.start;
+        match self.advance()? {
+            Token::Number(num, denom) => Ok(Expr::frac(num, denom)),
+            Token::Letter(c) => {
+                let mut name = c.to_string();
+                if self.token == Token::Underscore {
+                    self.advance()?;
+                    name.push('_');
+                    name.push_str(&self.text()?);
+                }
+
+                if self.token == Token::LParen {
+                    return self.call(FuncIdentifier::Name(name), start);
+                }
+                return Ok(match name.as_str() {
+                    "e" => Expr::e(),
+                    "i" => Expr::i(),
+                    _ => Expr::var(&name)
+                });
+            },
+            Token::LBrace => {
+                let inner = self.sum()?;
+                self.expect(Token::RBrace)?;
+                return Ok(inner);
+            },
+            Token::LParen => {
+                let inner = self.sum()?;
+                self.expect(Token::RParen)?;
+                return Ok(inner);
+            },
+            Token::AbsOpen => {
+                let inner = self.sum()?;
+                self.expect(Token::AbsClose)?;
+                return Ok(Expr::func(FuncIdentifier::Abs, vec![inner]));
+            },
+            Token::Pipe => {
+                let inner = self.sum()?;
+                self.expect(Token::Pipe)?;
+                return Ok(Expr::func(FuncIdentifier::Abs, vec![inner]));
+            },
+            Token::Command(name) => self.command(name, start),
+            _ => unreachable!()
+        }
+    }
+
+    fn command(&mut self, name: String, start: usize) -> Result<Expr, ParseError> {
+        match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.group()?;
+                let denom = self.group()?;
+                // \frac{1}{2} is a rational literal, as "1/2" is in infix
+                if let (Operator::Frac(a), Operator::Frac(b)) = (&num.operator, &denom.operator) {
+                    if a.is_int() && b.is_int() && !b.is_zero() && !b.is_negative() {
+                        return Ok(Expr::frac(a.num().clone(), b.num().clone()));
+                    }
+                }
+                return Ok(num / denom);
+            },
+            "sqrt" => {
+                let mut index = num::Int::from(2);
+                if self.token == Token::LBracket {
+                    self.advance()?;
+                    index = match self.token {
+                        Token::Number(ref n, ref denom) if *denom == 1 && *n >= 2 => n.clone(),
+                        _ => return Err(self.unexpected(&["integer root index"]))
+                    };
+                    self.advance()?;
+                    self.expect(Token::RBracket)?;
+                }
+
+                let radicand = self.group()?;
+                return Ok(Expr::pow(radicand, Expr::frac(1, index)));
+            },
+            "sin" => self.builtin(FuncIdentifier::Sine, start),
+            "cos" => self.builtin(FuncIdentifier::Cosine, start),
+            "tan" => self.builtin(FuncIdentifier::Tangent, start),
+            "pi" => Ok(Expr::pi()),
+            "infty" => Ok(Expr::infinity()),
+            "mathrm" | "mathit" | "text" => {
+                let text = self.text()?;
+                return Ok(match text.as_str() {
+                    "e" => Expr::e(),
+                    "i" => Expr::i(),
+                    _ => Expr::var(&text)
+                });
+            },
+            "operatorname" => {
+                let text = self.text()?;
+                let ident = match text.as_str() {
+                    "sin" => FuncIdentifier::Sine,
+                    "cos" => FuncIdentifier::Cosine,
+                    "tan" => FuncIdentifier::Tangent,
+                    "abs" => FuncIdentifier::Abs,
+                    _ => FuncIdentifier::Name(text)
+                };
+                return self.call(ident, start);
+            },
+            _ => {
+                // An unknown command is taken as a function only when it is given an argument
+                if self.token == Token::LParen || self.token == Token::LBrace {
+                    return self.call(FuncIdentifier::Name(name), start);
+                }
+                return Err(ParseError::new(start..self.span.start, ParseErrorKind::UnknownCommand(name)));
+            }
+        }
+    }
+
+    // `\sin` and friends, which also accept `\sin^2 x` and an unbracketed argument as in `\sin x`
+    fn builtin(&mut self, ident: FuncIdentifier, start: usize) -> Result<Expr, ParseError> {
+        let mut exponent = None;
+        if self.token == Token::Caret {
+            self.advance()?;
+            exponent = Some(self.script()?);
+        }
+
+        let func = match self.token {
+            Token::LParen | Token::LBrace => self.call(ident, start)?,
+            _ => Expr::func(ident, vec![self.power()?])
+        };
+
+        return Ok(match exponent {
+            Some(exponent) => Expr::pow(func, exponent),
+            None => func
+        });
+    }
+
+    fn call(&mut self, ident: FuncIdentifier, start: usize) -> Result<Expr, ParseError> {
+        let close = match self.token {
+            Token::LBrace => Token::RBrace,
+            _ => Token::RParen
+        };
+        self.advance()?;
+
+        let mut params = vec![self.sum()?];
+        while self.token == Token::Comma {
+            self.advance()?;
+            params.push(self.sum()?);
+        }
+
+        let end = self.span.end;
+        if self.token != close {
+            return Err(self.unexpected(&[",", &close.describe()]));
+        }
+        self.advance()?;
+
+        if !matches!(ident, FuncIdentifier::Name(_)) && params.len() != 1 {
+            return Err(ParseError::new(start..end, ParseErrorKind::ArityMismatch {
+                name: ident.get_name(),
+                expected: 1,
+                found: params.len()
+            }));
+        }
+
+        return Ok(Expr::func(ident, params));
+    }
+}
+
+// Parses a LaTeX formula such as "\frac{1}{2} \sin\left(\pi x\right)^{2}"
+pub fn parse_latex(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser::new(input)?;
+    let expr = parser.sum()?;
+    if parser.token != Token::End {
+        return Err(parser.unexpected(&["operator", "end of input"]));
+    }
+    return Ok(expr);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+    use crate::parse::{parse, ParseErrorKind};
+    use super::parse_latex;
+
+    fn latex(input: &str) -> String {
+        parse(input).unwrap().to_latex()
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        assert_eq!(latex("1/2 + 3/2*x"), "\\frac{1}{2} + \\frac{3}{2} x");
+        assert_eq!(latex("a - (b - c) + (d + e)*f"), "a - \\left(b - c\\right) + \\left(d + e\\right) f");
+        assert_eq!(latex("(x + 1)^(2*y) * 3"), "\\left(x + 1\\right)^{2 y} \\cdot 3");
+        assert_eq!(latex("x^y^z + (x^y)^z"), "x^{y^{z}} + \\left(x^{y}\\right)^{z}");
+        assert_eq!(latex("(-2)^2 - -3"), "\\left(-2\\right)^{2} - \\left(-3\\right)");
+        assert_eq!(latex("(-2)^2 - x*-3*y"), "\\left(-2\\right)^{2} - x \\cdot \\left(-3\\right) y");
+        assert_eq!(latex("-(x + 1)/6 + 2/3*x"), "\\frac{-\\left(x + 1\\right)}{6} + \\frac{2}{3} x");
+    }
+
+    #[test]
+    fn functions_and_constants() {
+        assert_eq!(latex("sin(x)^2 + cos(pi/4) - e"), "\\sin\\left(x\\right)^{2} + \\cos\\left(\\frac{\\pi}{4}\\right) - e");
+        assert_eq!(latex("abs(x - 1) * tan(y)"), "\\left|x - 1\\right| \\tan\\left(y\\right)");
+        assert_eq!(latex("1 - inf"), "1 - \\infty");
+        assert_eq!(latex("f(x, y) + gamma(t)*speed"), "f\\left(x, y\\right) + \\operatorname{gamma}\\left(t\\right) \\mathrm{speed}");
+        assert_eq!(Expr::frac(-1, 2).to_latex(), "-\\frac{1}{2}");
+    }
+
+    #[test]
+    fn parse_subset() {
+        assert_eq!(parse_latex("\\frac{1}{2} + \\frac{x}{y}").unwrap(), Expr::frac(1, 2) + Expr::var("x") / Expr::var("y"));
+        assert_eq!(parse_latex("2x^{2} \\cdot 3").unwrap(), parse("2*x^2*3").unwrap());
+        assert_eq!(parse_latex("x^23").unwrap(), parse("x^2*3").unwrap());
+        assert_eq!(parse_latex("\\sqrt{x} \\times \\sqrt[3]{y}").unwrap(), parse("x^(1/2) * y^(1/3)").unwrap());
+        assert_eq!(parse_latex("\\sin^2 x + \\cos\\left(\\pi t\\right)").unwrap(), parse("sin(x)^2 + cos(pi*t)").unwrap());
+        assert_eq!(parse_latex("\\left| a - b \\right| + |c|").unwrap(), parse("abs(a - b) + abs(c)").unwrap());
+        assert_eq!(parse_latex("\\Gamma(z) + \\mathrm{speed} \\, x_{1}").unwrap(), parse("Gamma(z) + speed*x_1").unwrap());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = parse_latex("x + \\alpha").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::UnknownCommand(String::from("alpha")));
+        assert_eq!(err.span, 4..10);
+
+        assert_eq!(parse_latex("\\frac{1}").unwrap_err().span, 8..8);
+        assert_eq!(parse_latex("\\sin(x, y)").unwrap_err().kind, ParseErrorKind::ArityMismatch { name: String::from("sin"), expected: 1, found: 2 });
+
+        let err = parse_latex("x^.5").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::InvalidNumber);
+        assert_eq!(err.span, 2..3);
+        assert_eq!(parse_latex("1.2.3").unwrap_err().kind, ParseErrorKind::InvalidNumber);
+    }
+
+    #[test]
+    fn round_trip() {
+        let inputs = [
+            "1/2 + 3/2*x - y/(2*z)",
+            "sin(x)^2 * cos(2*pi*t) + tan(-x)",
+            "abs(x - 1)^3 * (x + 1) * (x - 1)",
+            "-e^(x^2) + (-2)^x - f(x, y)",
+            "speed * x^(1/2) - (y + 1)^(1/3) + 2*3",
+            "x*inf - inf^2"
+        ];
+        for input in inputs.iter() {
+            let latex = parse(input).unwrap().to_latex();
+            assert_eq!(parse_latex(&latex).unwrap().to_latex(), latex);
+        }
+    }
+}
@@ -1,5 +1,31 @@
+#![allow(clippy::needless_return)]
+#![allow(clippy::single_match)]
+#![allow(clippy::should_implement_trait)]
+#![allow(clippy::len_zero)]
+#![allow(clippy::get_first)]
+#![allow(clippy::only_used_in_recursion)]
+
+pub mod art;
+mod bigint;
+pub mod compile;
+pub mod complex;
+pub mod diff;
+pub mod eval;
 pub mod expr;
+pub mod factor;
+pub mod integrate;
+pub mod interval;
+pub mod latex;
+pub mod limit;
+pub mod mathml;
 pub mod num;
+pub mod parse;
+pub mod poly;
+pub mod precise;
+pub mod pretty;
+#[cfg(feature = "serde")]
+pub mod serial;
+pub mod series;
 pub mod simplify;
 
 #[cfg(test)]
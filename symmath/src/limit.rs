@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::expr::*;
+use crate::num::{Int, NumError};
+use crate::series::{Series, SeriesError};
+
+// How many times l'Hôpital's rule is applied to one quotient before giving up
+const LHOPITAL_DEPTH: u32 = 8;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Direction {
+    Left,
+    Right,
+    Both
+}
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Limit {
+    // A finite value, inf or -inf
+    Value(Expr),
+    DoesNotExist
+}
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum LimitError {
+    Num(NumError),
+    // None of the methods settled the limit, such as for 0 * inf after the depth runs out
+    // or a pole whose sign depends on another variable
+    Undecided
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitError::Num(err) => write!(f, "{}", err),
+            LimitError::Undecided => write!(f, "could not decide the limit")
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+impl From<NumError> for LimitError {
+    fn from(err: NumError) -> LimitError {
+        LimitError::Num(err)
+    }
+}
+
+// Written as the parser reads "-inf"
+fn signed_infinity(sign: i32) -> Expr {
+    if sign < 0 { Expr::int(-1) * Expr::infinity() } else { Expr::infinity() }
+}
+
+// 1 for inf and -1 for -inf, whether the sign is written before or after it
+fn infinity_sign(expr: &Expr) -> Option<i32> {
+    match &expr.operator {
+        Operator::Const(Constant::Infinity) => Some(1),
+        Operator::Mul => match expr.children().as_slice() {
+            [Expr { operator: Operator::Const(Constant::Infinity), .. }, Expr { operator: Operator::Frac(f), .. }] |
+            [Expr { operator: Operator::Frac(f), .. }, Expr { operator: Operator::Const(Constant::Infinity), .. }] if !f.is_zero() => {
+                Some(if f.is_negative() { -1 } else { 1 })
+            },
+            _ => None
+        },
+        _ => None
+    }
+}
+
+fn sign(expr: &Expr) -> Result<i32, LimitError> {
+    match expr.eval_f64(&HashMap::new()) {
+        Ok(val) if val > 0.0 => Ok(1),
+        Ok(val) if val < 0.0 => Ok(-1),
+        _ => Err(LimitError::Undecided)
+    }
+}
+
+// 1/u written so that e^x becomes e^-x rather than 1/e^x, which keeps derivatives small
+fn reciprocal(expr: &Expr) -> Expr {
+    match &expr.operator {
+        Operator::Pow => {
+            let els = expr.children();
+            Expr::pow(els[0].clone(), negate(els[1].clone()))
+        },
+        Operator::Div => {
+            let els = expr.children();
+            els[1].clone() / els[0].clone()
+        },
+        _ => Expr::int(1) / expr.clone()
+    }
+}
+
+fn is_zero(limit: &Limit) -> bool {
+    *limit == Limit::Value(Expr::int(0))
+}
+
+fn infinite(limit: &Limit) -> Option<i32> {
+    match limit {
+        Limit::Value(val) => infinity_sign(val),
+        Limit::DoesNotExist => None
+    }
+}
+
+// The limit from the leading term c h^s of a series in h, approaching 0 from `direction`
+fn leading_term(series: &Series, direction: Direction) -> Result<Limit, LimitError> {
+    let start = series.start();
+    if start > 0 {
+        return Ok(Limit::Value(Expr::int(0)));
+    }
+    let coeff = series.coefficient(start);
+    if start == 0 {
+        return Ok(Limit::Value(coeff));
+    }
+    let sign = sign(&coeff)?;
+    if start % 2 == 0 {
+        return Ok(Limit::Value(signed_infinity(sign)));
+    }
+    return Ok(match direction {
+        Direction::Right => Limit::Value(signed_infinity(sign)),
+        Direction::Left => Limit::Value(signed_infinity(-sign)),
+        Direction::Both => Limit::DoesNotExist
+    });
+}
+
+impl Expr {
+    // The expansion in h about the point, where x = 1/h for inf and x = -1/h for -inf, and
+    // the side of 0 that h approaches from
+    fn side_series(&self, var: &str, point: &Expr, direction: Direction, order: i64) -> Result<(Series, Direction), SeriesError> {
+        match infinity_sign(point) {
+            Some(sign) => {
+                let h = format!("{}'", var);
+                let substituted = self.substitute(var, &(Expr::int(sign) / Expr::var(&h)));
+                substituted.series_terms(&h, &Expr::int(0), order).map(|series| (series, Direction::Right))
+            },
+            None => self.series_terms(var, point, order).map(|series| (series, direction))
+        }
+    }
+
+    fn limit_series(&self, var: &str, point: &Expr, direction: Direction) -> Result<Option<Limit>, LimitError> {
+        match self.side_series(var, point, direction, 1) {
+            Ok((series, direction)) => Ok(Some(leading_term(&series, direction)?)),
+            Err(SeriesError::Num(err)) => Err(err.into()),
+            Err(_) => Ok(None)
+        }
+    }
+
+    // The sign of something that approaches 0, read from the first nonzero term of its
+    // series. Approaching from both sides, -1 means it is negative on at least one of them.
+    fn sign_near(&self, var: &str, point: &Expr, direction: Direction) -> Result<Option<i32>, LimitError> {
+        for order in 1..=LHOPITAL_DEPTH as i64 {
+            let (series, direction) = match self.side_series(var, point, direction, order) {
+                Ok(found) => found,
+                Err(SeriesError::Num(err)) => return Err(err.into()),
+                Err(_) => return Ok(None)
+            };
+            let start = series.start();
+            if start >= order {
+                continue;
+            }
+            let coeff = match sign(&series.coefficient(start)) {
+                Ok(coeff) => coeff,
+                Err(_) => return Ok(None)
+            };
+            let flipped = if start % 2 == 0 { coeff } else { -coeff };
+            return Ok(Some(match direction {
+                Direction::Right => coeff,
+                Direction::Left => flipped,
+                Direction::Both => coeff.min(flipped)
+            }));
+        }
+        return Ok(None);
+    }
+
+    fn limit_impl(&self, var: &str, point: &Expr, direction: Direction, depth: u32) -> Result<Limit, LimitError> {
+        if !self.depends_on(var) {
+            return Ok(Limit::Value(self.clone().simplified()?));
+        }
+        if let Some(limit) = self.limit_series(var, point, direction)? {
+            return Ok(limit);
+        }
+
+        // Without a series, such as at an essential singularity, the limit is put together
+        // from the limits of the parts
+        let limit = |expr: &Expr| expr.limit_impl(var, point, direction, depth);
+        match &self.operator {
+            Operator::Add => {
+                let limits = self.children().iter().map(limit).collect::<Result<Vec<Limit>, LimitError>>()?;
+                let signs = limits.iter().filter_map(infinite).collect::<Vec<i32>>();
+                let missing = limits.iter().filter(|limit| **limit == Limit::DoesNotExist).count();
+                if missing > 0 {
+                    // One oscillating term cannot be cancelled by terms that settle
+                    return if missing == 1 && signs.is_empty() { Ok(Limit::DoesNotExist) } else { Err(LimitError::Undecided) };
+                }
+                if signs.contains(&1) && signs.contains(&-1) {
+                    return Err(LimitError::Undecided);
+                }
+                if let Some(sign) = signs.first() {
+                    return Ok(Limit::Value(signed_infinity(*sign)));
+                }
+                let terms = limits.into_iter().map(|limit| match limit {
+                    Limit::Value(val) => val,
+                    Limit::DoesNotExist => unreachable!()
+                }).collect();
+                Ok(Limit::Value(Expr::new(Operator::Add, Some(terms)).simplified()?))
+            },
+            Operator::Mul => {
+                let els = self.children();
+                let limits = els.iter().map(limit).collect::<Result<Vec<Limit>, LimitError>>()?;
+                if limits.contains(&Limit::DoesNotExist) {
+                    return Err(LimitError::Undecided);
+                }
+                let zero = limits.iter().any(is_zero);
+                let unbounded = limits.iter().any(|limit| infinite(limit).is_some());
+                if zero && unbounded {
+                    // 0 * inf, rewritten as inf / (1/0) for l'Hôpital
+                    let mut num = Vec::new();
+                    let mut denom = Vec::new();
+                    for (el, limit) in els.iter().zip(limits.iter()) {
+                        if is_zero(limit) { denom.push(reciprocal(el)); } else { num.push(el.clone()); }
+                    }
+                    let quotient = Expr::new(Operator::Mul, Some(num)) / Expr::new(Operator::Mul, Some(denom));
+                    return quotient.lhopital(var, point, direction, depth);
+                }
+                if unbounded {
+                    let mut product = 1;
+                    for limit in limits.iter() {
+                        product *= match (infinite(limit), limit) {
+                            (Some(sign), _) => sign,
+                            (None, Limit::Value(val)) => sign(val)?,
+                            (None, Limit::DoesNotExist) => unreachable!()
+                        };
+                    }
+                    return Ok(Limit::Value(signed_infinity(product)));
+                }
+                let factors = limits.into_iter().map(|limit| match limit {
+                    Limit::Value(val) => val,
+                    Limit::DoesNotExist => unreachable!()
+                }).collect();
+                Ok(Limit::Value(Expr::new(Operator::Mul, Some(factors)).simplified()?))
+            },
+            Operator::Div => self.lhopital(var, point, direction, depth),
+            Operator::Pow => {
+                let els = self.children();
+                let (u, v) = (&els[0], &els[1]);
+                if let Operator::Const(Constant::E) = u.operator {
+                    return Ok(match limit(v)? {
+                        Limit::Value(val) => match infinity_sign(&val) {
+                            Some(1) => Limit::Value(Expr::infinity()),
+                            Some(_) => Limit::Value(Expr::int(0)),
+                            None => Limit::Value(Expr::pow(Expr::e(), val).simplified()?)
+                        },
+                        Limit::DoesNotExist => return Err(LimitError::Undecided)
+                    });
+                }
+                let exponent = match &v.operator {
+                    Operator::Frac(f) => f,
+                    _ => return Err(LimitError::Undecided)
+                };
+                // A fractional power of a base approaching 0 or inf. Even roots are only real
+                // where the base is not negative, so a base approaching 0 has to do so from above.
+                let base = limit(u)?;
+                if is_zero(&base) {
+                    if exponent.is_negative() {
+                        return Err(LimitError::Undecided);
+                    }
+                    if exponent.denom() % &Int::from(2) == 0 {
+                        match u.sign_near(var, point, direction)? {
+                            Some(1) => {},
+                            Some(_) => return Ok(Limit::DoesNotExist),
+                            None => return Err(LimitError::Undecided)
+                        }
+                    }
+                    return Ok(base);
+                }
+                match infinite(&base) {
+                    Some(1) => Ok(Limit::Value(if exponent.is_negative() { Expr::int(0) } else { Expr::infinity() })),
+                    Some(_) => Err(LimitError::Undecided),
+                    None => match base {
+                        Limit::Value(val) => Ok(Limit::Value(Expr::pow(val, v.clone()).simplified()?)),
+                        Limit::DoesNotExist => Err(LimitError::Undecided)
+                    }
+                }
+            },
+            // sin and cos keep oscillating as their argument grows without bound
+            Operator::Func(ident @ (FuncIdentifier::Sine | FuncIdentifier::Cosine)) => {
+                match limit(&self.children()[0])? {
+                    Limit::Value(val) if infinity_sign(&val).is_some() => Ok(Limit::DoesNotExist),
+                    Limit::Value(val) => Ok(Limit::Value(Expr::func(ident.clone(), vec![val]).simplified()?)),
+                    Limit::DoesNotExist => Err(LimitError::Undecided)
+                }
+            },
+            _ => Err(LimitError::Undecided)
+        }
+    }
+
+    // u/v from the limits of u and v, differentiating both when they are 0/0 or inf/inf
+    fn lhopital(&self, var: &str, point: &Expr, direction: Direction, depth: u32) -> Result<Limit, LimitError> {
+        let els = self.children();
+        let (u, v) = (&els[0], &els[1]);
+        let num = u.limit_impl(var, point, direction, depth)?;
+        let denom = v.limit_impl(var, point, direction, depth)?;
+        let (num, denom) = match (num, denom) {
+            (Limit::Value(num), Limit::Value(denom)) => (num, denom),
+            _ => return Err(LimitError::Undecided)
+        };
+        let indeterminate = match (infinity_sign(&num), infinity_sign(&denom)) {
+            (Some(_), Some(_)) => true,
+            (None, None) => num == Expr::int(0) && denom == Expr::int(0),
+            _ => false
+        };
+        if indeterminate {
+            if depth >= LHOPITAL_DEPTH {
+                return Err(LimitError::Undecided);
+            }
+            let quotient = u.diff(var)? / v.diff(var)?;
+            return quotient.limit_impl(var, point, direction, depth + 1);
+        }
+        match (infinity_sign(&num), infinity_sign(&denom)) {
+            (None, Some(_)) => Ok(Limit::Value(Expr::int(0))),
+            (Some(a), None) if denom != Expr::int(0) => Ok(Limit::Value(signed_infinity(a * sign(&denom)?))),
+            // A nonzero value over 0 needs the side the denominator approaches from
+            (None, None) if denom != Expr::int(0) => Ok(Limit::Value((num / denom).simplified()?)),
+            _ => Err(LimitError::Undecided)
+        }
+    }
+
+    // The limit as `var` approaches `point`, which may be inf or -inf, from `direction`.
+    // Series expansion is tried first, and l'Hôpital's rule where a quotient has none.
+    pub fn limit(&self, var: &str, point: &Expr, direction: Direction) -> Result<Limit, LimitError> {
+        let point = point.clone().simplified()?;
+        self.limit_impl(var, &point, direction, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+    use crate::parse::parse;
+    use super::{Direction, Limit, LimitError};
+
+    fn limit(input: &str, point: &str, direction: Direction) -> Result<Limit, LimitError> {
+        parse(input).unwrap().limit("x", &parse(point).unwrap(), direction)
+    }
+
+    fn value(expr: Expr) -> Result<Limit, LimitError> {
+        Ok(Limit::Value(expr))
+    }
+
+    #[test]
+    fn finite_points() {
+        assert_eq!(limit("sin(x)/x", "0", Direction::Both), value(Expr::int(1)));
+        assert_eq!(limit("(1 - cos(x))/x^2", "0", Direction::Both), value(Expr::frac(1, 2)));
+        assert_eq!(limit("(x^2 - 1)/(x - 1)", "1", Direction::Both), value(Expr::int(2)));
+        assert_eq!(limit("(e^x - 1)/x", "0", Direction::Both), value(Expr::int(1)));
+        assert_eq!(limit("x^2 + pi", "0", Direction::Both), value(Expr::pi()));
+        assert_eq!(limit("x^(1/2)", "0", Direction::Right), value(Expr::int(0)));
+        assert_eq!(limit("x^(1/2)", "0", Direction::Left), Ok(Limit::DoesNotExist));
+        assert_eq!(limit("x^(1/2)", "0", Direction::Both), Ok(Limit::DoesNotExist));
+        assert_eq!(limit("(-x)^(1/2)", "0", Direction::Left), value(Expr::int(0)));
+        assert_eq!(limit("(x^2)^(3/2)", "0", Direction::Both), value(Expr::int(0)));
+        assert_eq!(limit("x^(1/3)", "0", Direction::Both), value(Expr::int(0)));
+    }
+
+    #[test]
+    fn poles_and_sides() {
+        assert_eq!(limit("1/x", "0", Direction::Right), value(Expr::infinity()));
+        assert_eq!(limit("1/x", "0", Direction::Left), value(parse("-inf").unwrap()));
+        assert_eq!(limit("1/x", "0", Direction::Both), Ok(Limit::DoesNotExist));
+        assert_eq!(limit("-1/(x - 2)^2", "2", Direction::Both), value(parse("-inf").unwrap()));
+        assert_eq!(limit("e^(1/x)", "0", Direction::Right), value(Expr::infinity()));
+        assert_eq!(limit("e^(1/x)", "0", Direction::Left), value(Expr::int(0)));
+        assert_eq!(limit("y/x", "0", Direction::Right), Err(LimitError::Undecided));
+        // Both are |x|/x, which has no series at 0 to read the sides from
+        assert_eq!(limit("(x^2)^(1/2)/x", "0", Direction::Both), Err(LimitError::Undecided));
+        assert_eq!(limit("abs(x)/x", "0", Direction::Both), Err(LimitError::Undecided));
+    }
+
+    #[test]
+    fn infinities() {
+        assert_eq!(limit("(3*x^2 + x)/(2*x^2 - 5)", "inf", Direction::Both), value(Expr::frac(3, 2)));
+        assert_eq!(limit("(3*x^2 + x)/(2*x^2 - 5)", "-inf", Direction::Both), value(Expr::frac(3, 2)));
+        assert_eq!(limit("x^3 - x", "-inf", Direction::Both), value(parse("-inf").unwrap()));
+        assert_eq!(limit("e^x", "-inf", Direction::Both), value(Expr::int(0)));
+        assert_eq!(limit("x^2 * e^-x", "inf", Direction::Both), value(Expr::int(0)));
+        assert_eq!(limit("x*sin(1/x)", "inf", Direction::Both), value(Expr::int(1)));
+        assert_eq!(limit("sin(x)", "inf", Direction::Both), Ok(Limit::DoesNotExist));
+    }
+}
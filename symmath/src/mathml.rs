@@ -0,0 +1,633 @@
+use std::ops::Range;
+use crate::expr::*;
+use crate::num;
+use crate::parse::{decimal, ParseError, ParseErrorKind};
+use crate::pretty::unsigned;
+
+const NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Binding strength in presentation markup. <mfrac> and function applications delimit
+// themselves, so they bind as tightly as a plain symbol.
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.operator {
+        Operator::Add | Operator::Sub => 1,
+        Operator::Mul => 2,
+        Operator::Pow => 3,
+        _ => 4
+    }
+}
+
+fn mo(op: &str) -> String {
+    format!("<mo>{}</mo>", op)
+}
+
+fn mrow(inner: String) -> String {
+    format!("<mrow>{}</mrow>", inner)
+}
+
+fn parens(inner: String) -> String {
+    mrow(format!("{}{}{}", mo("("), inner, mo(")")))
+}
+
+fn frac_presentation(frac: &num::Frac) -> String {
+    let magnitude = if frac.is_int() {
+        format!("<mn>{}</mn>", frac.num().abs())
+    } else {
+        format!("<mfrac><mn>{}</mn><mn>{}</mn></mfrac>", frac.num().abs(), frac.denom().abs())
+    };
+
+    if frac.is_negative() {
+        mrow(format!("{}{}", mo("-"), magnitude))
+    } else {
+        magnitude
+    }
+}
+
+fn frac_content(frac: &num::Frac) -> String {
+    if frac.is_int() {
+        format!("<cn type=\"integer\">{}</cn>", frac.num())
+    } else {
+        format!("<cn type=\"rational\">{}<sep/>{}</cn>", frac.num(), frac.denom())
+    }
+}
+
+impl Expr {
+    fn presentation_operand(&self, min_precedence: u8) -> String {
+        if precedence(self) < min_precedence || unsigned(self).is_some() {
+            parens(self.presentation())
+        } else {
+            self.presentation()
+        }
+    }
+
+    fn presentation(&self) -> String {
+        if let Some(term) = unsigned(self) {
+            return mrow(format!("{}{}", mo("-"), term.presentation_operand(2)));
+        }
+
+        match &self.operator {
+            Operator::Frac(frac) => frac_presentation(frac),
+            Operator::Add => {
+                let mut out = String::new();
+                for (idx, child) in self.children().iter().enumerate() {
+                    match unsigned(child) {
+                        Some(term) => {
+                            out.push_str(&mo("-"));
+                            out.push_str(&term.presentation_operand(2));
+                        },
+                        None => {
+                            if idx != 0 { out.push_str(&mo("+")); }
+                            // a - b + c needs no parentheses around its leading difference
+                            out.push_str(&child.presentation_operand(if idx == 0 { 1 } else { 2 }));
+                        }
+                    }
+                }
+                mrow(out)
+            },
+            Operator::Sub => {
+                let mut out = String::new();
+                for (idx, child) in self.children().iter().enumerate() {
+                    if idx == 0 {
+                        out.push_str(&child.presentation_operand(1));
+                    } else {
+                        out.push_str(&mo("-"));
+                        out.push_str(&child.presentation_operand(2));
+                    }
+                }
+                mrow(out)
+            },
+            Operator::Mul => {
+                let els = self.children();
+                // A trailing reciprocal, as in x * (1/6), is stacked as x over 6
+                if let Some(Operator::Frac(f)) = els.last().map(|el| &el.operator) {
+                    if f.num() == 1 && f.denom() > 1 && els.len() > 1 {
+                        let rest = &els[..els.len() - 1];
+                        let num = if rest.len() == 1 { rest[0].presentation() } else { Expr::new(Operator::Mul, Some(rest.to_vec())).presentation() };
+                        return format!("<mfrac>{}<mn>{}</mn></mfrac>", num, f.denom());
+                    }
+                }
+
+                let mut out = String::new();
+                for (idx, child) in els.iter().enumerate() {
+                    if idx != 0 { out.push_str(&mo("&#x22C5;")); }
+                    out.push_str(&child.presentation_operand(3));
+                }
+                mrow(out)
+            },
+            Operator::Div => {
+                let els = self.children();
+                format!("<mfrac>{}{}</mfrac>", els[0].presentation(), els[1].presentation())
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let base = match &els[0].operator {
+                    Operator::Div => parens(els[0].presentation()),
+                    Operator::Frac(f) if !f.is_int() => parens(els[0].presentation()),
+                    _ => els[0].presentation_operand(4)
+                };
+                format!("<msup>{}{}</msup>", base, els[1].presentation())
+            },
+            Operator::Name(name) => format!("<mi>{}</mi>", escape(name)),
+            Operator::Func(FuncIdentifier::Abs) => {
+                mrow(format!("{}{}{}", mo("|"), self.children()[0].presentation(), mo("|")))
+            },
+            Operator::Func(ident) => {
+                let args = self.children().iter().map(|el| el.presentation()).collect::<Vec<String>>().join(&mo(","));
+                // U+2061 is the invisible function application operator
+                mrow(format!("<mi>{}</mi>{}{}", escape(&ident.get_name()), mo("&#x2061;"), parens(args)))
+            },
+            Operator::Const(Constant::Pi) => String::from("<mi>&#x3C0;</mi>"),
+            Operator::Const(Constant::E) => String::from("<mi>e</mi>"),
+            Operator::Const(Constant::I) => String::from("<mi>i</mi>"),
+            Operator::Const(Constant::Infinity) => String::from("<mi>&#x221E;</mi>")
+        }
+    }
+
+    fn content(&self) -> String {
+        let apply = |head: String| {
+            let args = self.children().iter().map(|el| el.content()).collect::<String>();
+            format!("<apply>{}{}</apply>", head, args)
+        };
+
+        match &self.operator {
+            Operator::Frac(frac) => frac_content(frac),
+            Operator::Add => apply(String::from("<plus/>")),
+            Operator::Mul => apply(String::from("<times/>")),
+            Operator::Div => apply(String::from("<divide/>")),
+            Operator::Sub => {
+                // <minus/> is binary, so longer differences nest to the left
+                let els = self.children();
+                let mut out = els[0].content();
+                for el in els[1..].iter() {
+                    out = format!("<apply><minus/>{}{}</apply>", out, el.content());
+                }
+                out
+            },
+            Operator::Pow => apply(String::from("<power/>")),
+            Operator::Name(name) => format!("<ci>{}</ci>", escape(name)),
+            Operator::Func(FuncIdentifier::Name(name)) => apply(format!("<ci type=\"function\">{}</ci>", escape(name))),
+            Operator::Func(FuncIdentifier::Sine) => apply(String::from("<sin/>")),
+            Operator::Func(FuncIdentifier::Cosine) => apply(String::from("<cos/>")),
+            Operator::Func(FuncIdentifier::Tangent) => apply(String::from("<tan/>")),
+            Operator::Func(FuncIdentifier::Abs) => apply(String::from("<abs/>")),
+            Operator::Const(Constant::Pi) => String::from("<pi/>"),
+            Operator::Const(Constant::E) => String::from("<exponentiale/>"),
+            Operator::Const(Constant::I) => String::from("<imaginaryi/>"),
+            Operator::Const(Constant::Infinity) => String::from("<infinity/>")
+        }
+    }
+
+    pub fn to_mathml_presentation(&self) -> String {
+        format!("<math xmlns=\"{}\">{}</math>", NAMESPACE, self.presentation())
+    }
+
+    pub fn to_mathml_content(&self) -> String {
+        format!("<math xmlns=\"{}\">{}</math>", NAMESPACE, self.content())
+    }
+}
+
+// Just enough XML to read content MathML: elements, attributes, text and character references
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+    span: Range<usize>
+}
+
+enum Node {
+    Element(Element),
+    Text(String)
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn elements(&self) -> Vec<&Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(el) => Some(el),
+            Node::Text(_) => None
+        }).collect()
+    }
+
+    fn text(&self) -> String {
+        self.children.iter().filter_map(|node| match node {
+            Node::Text(text) => Some(text.as_str()),
+            Node::Element(_) => None
+        }).collect::<String>().trim().to_string()
+    }
+
+    fn error(&self, found: &str, expected: &[&str]) -> ParseError {
+        ParseError::new(self.span.clone(), ParseErrorKind::UnexpectedToken {
+            found: String::from(found),
+            expected: expected.iter().map(|e| String::from(*e)).collect()
+        })
+    }
+}
+
+struct XmlReader<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl<'a> XmlReader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let expected = vec![String::from(expected)];
+        match self.rest().chars().next() {
+            Some(c) => ParseError::new(self.pos..self.pos + c.len_utf8(), ParseErrorKind::UnexpectedToken { found: c.to_string(), expected }),
+            None => ParseError::new(self.pos..self.pos, ParseErrorKind::UnexpectedEnd { expected })
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.input.len() - trimmed.len();
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            return true;
+        }
+        return false;
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), ParseError> {
+        if self.eat(s) { Ok(()) } else { Err(self.unexpected(s)) }
+    }
+
+    // Skips until just past `end`
+    fn skip_past(&mut self, end: &str) -> Result<(), ParseError> {
+        match self.rest().find(end) {
+            Some(idx) => {
+                self.pos += idx + end.len();
+                return Ok(());
+            },
+            None => {
+                self.pos = self.input.len();
+                return Err(self.unexpected(end));
+            }
+        }
+    }
+
+    // Skips the prolog, comments and processing instructions
+    fn skip_misc(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            if self.eat("<?") {
+                self.skip_past("?>")?;
+            } else if self.eat("<!--") {
+                self.skip_past("-->")?;
+            } else if self.eat("<!DOCTYPE") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ParseError> {
+        let len = self.rest().find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '=').unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.unexpected("name"));
+        }
+        let name = &self.rest()[..len];
+        self.pos += len;
+        // Namespace prefixes such as <m:apply> are not significant here
+        return Ok(String::from(name.rsplit(':').next().unwrap()));
+    }
+
+    fn unescape(&self, text: &str, start: usize) -> Result<String, ParseError> {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(idx) = rest.find('&') {
+            out.push_str(&rest[..idx]);
+            let end = rest[idx..].find(';').ok_or_else(|| ParseError::new(start..start + text.len(), ParseErrorKind::UnexpectedEnd { expected: vec![String::from(";")] }))?;
+            let entity = &rest[idx + 1..idx + end];
+            let c = match entity {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok().and_then(std::char::from_u32),
+                _ if entity.starts_with('#') => entity[1..].parse::<u32>().ok().and_then(std::char::from_u32),
+                _ => None
+            };
+            let c = c.ok_or_else(|| {
+                let offset = start + (text.len() - rest.len()) + idx;
+                ParseError::new(offset..offset + end + 1, ParseErrorKind::UnexpectedToken { found: format!("&{};", entity), expected: vec![String::from("character reference")] })
+            })?;
+            out.push(c);
+            rest = &rest[idx + end + 1..];
+        }
+        out.push_str(rest);
+        return Ok(out);
+    }
+
+    fn element(&mut self) -> Result<Element, ParseError> {
+        let start = self.pos;
+        self.expect("<")?;
+        let name = self.name()?;
+
+        let mut attrs = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.eat("/>") {
+                return Ok(Element { name, attrs, children: Vec::new(), span: start..self.pos });
+            }
+            if self.eat(">") {
+                break;
+            }
+
+            let key = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = if self.eat("\"") { "\"" } else { self.expect("'")?; "'" };
+            let value_start = self.pos;
+            let len = self.rest().find(quote).ok_or_else(|| self.unexpected(quote))?;
+            let value = self.unescape(&self.rest()[..len], value_start)?;
+            self.pos += len + 1;
+            attrs.push((key, value));
+        }
+
+        let mut children = Vec::new();
+        loop {
+            let text_start = self.pos;
+            let len = self.rest().find('<').unwrap_or(self.rest().len());
+            if len > 0 {
+                let text = self.unescape(&self.rest()[..len], text_start)?;
+                self.pos += len;
+                if !text.trim().is_empty() {
+                    children.push(Node::Text(text));
+                }
+            }
+
+            if self.eat("<!--") {
+                self.skip_past("-->")?;
+            } else if self.eat("</") {
+                let close_start = self.pos - 2;
+                let close = self.name()?;
+                self.skip_whitespace();
+                self.expect(">")?;
+                if close != name {
+                    return Err(ParseError::new(close_start..self.pos, ParseErrorKind::UnexpectedToken {
+                        found: format!("</{}>", close),
+                        expected: vec![format!("</{}>", name)]
+                    }));
+                }
+                return Ok(Element { name, attrs, children, span: start..self.pos });
+            } else if self.rest().is_empty() {
+                return Err(self.unexpected(&format!("</{}>", name)));
+            } else {
+                children.push(Node::Element(self.element()?));
+            }
+        }
+    }
+}
+
+fn read_cn(el: &Element) -> Result<Expr, ParseError> {
+    let invalid = || ParseError::new(el.span.clone(), ParseErrorKind::InvalidNumber);
+    let int = |text: &str| num::Int::parse(text.trim()).ok_or_else(invalid);
+
+    match el.attr("type").unwrap_or("real") {
+        "integer" => Ok(Expr::int(int(&el.text())?)),
+        "rational" => {
+            // The numerator and denominator are the text either side of <sep/>
+            let mut parts = Vec::new();
+            let mut current = String::new();
+            for node in el.children.iter() {
+                match node {
+                    Node::Text(text) => current.push_str(text),
+                    Node::Element(sep) if sep.name == "sep" => parts.push(std::mem::take(&mut current)),
+                    Node::Element(other) => return Err(other.error(&format!("<{}>", other.name), &["<sep/>"]))
+                }
+            }
+            parts.push(current);
+            if parts.len() != 2 {
+                return Err(invalid());
+            }
+            let denom = int(&parts[1])?;
+            if denom == 0 {
+                return Err(invalid());
+            }
+            return Ok(Expr::frac(int(&parts[0])?, denom));
+        },
+        "real" => {
+            let text = el.text();
+            let (negative, digits) = match text.strip_prefix('-') {
+                Some(digits) => (true, digits),
+                None => (false, text.as_str())
+            };
+            let mut parts = digits.splitn(2, '.');
+            let int = parts.next().unwrap();
+            let frac = parts.next().unwrap_or("");
+            if int.is_empty() && frac.is_empty() || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            let value = Expr::new_empty(Operator::Frac(decimal(int, frac).ok_or_else(invalid)?));
+            return Ok(if negative { negate(value) } else { value });
+        },
+        other => Err(el.error(other, &["integer", "rational", "real"]))
+    }
+}
+
+fn read_apply(el: &Element) -> Result<Expr, ParseError> {
+    let els = el.elements();
+    let head = match els.first() {
+        Some(head) => head,
+        None => return Err(el.error("<apply/>", &["operator"]))
+    };
+
+    let mut args = Vec::new();
+    let mut degree = None;
+    for arg in els[1..].iter() {
+        if arg.name == "degree" {
+            // Only roots have a degree
+            if head.name != "root" {
+                return Err(arg.error("<degree>", &["argument"]));
+            }
+            degree = Some(read_only_child(arg)?);
+        } else {
+            args.push(read(arg)?);
+        }
+    }
+
+    let arity = |n: usize| {
+        if args.len() == n {
+            return Ok(());
+        }
+        return Err(ParseError::new(el.span.clone(), ParseErrorKind::ArityMismatch {
+            name: head.name.clone(),
+            expected: n,
+            found: args.len()
+        }));
+    };
+
+    let func = |ident: FuncIdentifier, args: Vec<Expr>| Expr::func(ident, args);
+
+    // Sums, products and named functions take any number of arguments, but not none
+    let variadic = matches!(head.name.as_str(), "plus" | "times" | "ci" | "csymbol");
+    if variadic && args.is_empty() {
+        return Err(el.error("<apply/>", &["argument"]));
+    }
+
+    match head.name.as_str() {
+        "plus" => Ok(Expr::new(Operator::Add, Some(args))),
+        "times" => Ok(Expr::new(Operator::Mul, Some(args))),
+        "minus" if args.len() == 1 => Ok(negate(args.pop().unwrap())),
+        "minus" => {
+            arity(2)?;
+            Ok(Expr::new(Operator::Sub, Some(args)))
+        },
+        "divide" => {
+            arity(2)?;
+            Ok(Expr::new(Operator::Div, Some(args)))
+        },
+        "power" => {
+            arity(2)?;
+            Ok(Expr::new(Operator::Pow, Some(args)))
+        },
+        "root" => {
+            arity(1)?;
+            let exponent = match degree {
+                Some(degree) => match degree.operator {
+                    Operator::Frac(ref f) if f.is_int() && !f.is_zero() => Expr::frac(1, f.num().clone()),
+                    _ => Expr::int(1) / degree
+                },
+                None => Expr::frac(1, 2)
+            };
+            args.push(exponent);
+            Ok(Expr::new(Operator::Pow, Some(args)))
+        },
+        "sin" | "cos" | "tan" | "abs" => {
+            arity(1)?;
+            let ident = match head.name.as_str() {
+                "sin" => FuncIdentifier::Sine,
+                "cos" => FuncIdentifier::Cosine,
+                "tan" => FuncIdentifier::Tangent,
+                _ => FuncIdentifier::Abs
+            };
+            Ok(func(ident, args))
+        },
+        "ci" | "csymbol" => Ok(func(FuncIdentifier::Name(head.text()), args)),
+        other => Err(head.error(&format!("<{}>", other), &["plus", "times", "minus", "divide", "power", "root", "sin", "cos", "tan", "abs", "ci"]))
+    }
+}
+
+fn read_only_child(el: &Element) -> Result<Expr, ParseError> {
+    match el.elements().as_slice() {
+        [child] => read(child),
+        _ => Err(el.error(&format!("<{}>", el.name), &["a single child element"]))
+    }
+}
+
+fn read(el: &Element) -> Result<Expr, ParseError> {
+    match el.name.as_str() {
+        "math" => read_only_child(el),
+        // Annotated expressions carry the content tree as their first child
+        "semantics" => match el.elements().first() {
+            Some(first) => read(first),
+            None => Err(el.error("<semantics/>", &["expression"]))
+        },
+        "apply" => read_apply(el),
+        "cn" => read_cn(el),
+        "ci" => Ok(Expr::var(&el.text())),
+        "pi" => Ok(Expr::pi()),
+        "exponentiale" => Ok(Expr::e()),
+        "imaginaryi" => Ok(Expr::i()),
+        "infinity" => Ok(Expr::infinity()),
+        other => Err(el.error(&format!("<{}>", other), &["apply", "cn", "ci", "pi", "exponentiale", "imaginaryi", "infinity"]))
+    }
+}
+
+// Reads a content MathML document, or a bare content element, into an Expr
+pub fn parse_mathml_content(input: &str) -> Result<Expr, ParseError> {
+    let mut reader = XmlReader {
+        input,
+        pos: 0
+    };
+    reader.skip_misc()?;
+    let root = reader.element()?;
+    reader.skip_misc()?;
+    if !reader.rest().is_empty() {
+        return Err(reader.unexpected("end of input"));
+    }
+    return read(&root);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+    use crate::parse::{parse, ParseErrorKind};
+    use super::parse_mathml_content;
+
+    #[test]
+    fn presentation() {
+        let expr = parse("(x + 1)^2/3 - sin(pi*x) + abs(-e)").unwrap();
+        assert_eq!(expr.to_mathml_presentation(), concat!(
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><mrow>",
+            "<mfrac><msup><mrow><mo>(</mo><mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow><mo>)</mo></mrow><mn>2</mn></msup><mn>3</mn></mfrac>",
+            "<mo>-</mo><mrow><mi>sin</mi><mo>&#x2061;</mo><mrow><mo>(</mo><mrow><mi>&#x3C0;</mi><mo>&#x22C5;</mo><mi>x</mi></mrow><mo>)</mo></mrow></mrow></mrow>",
+            "<mo>+</mo><mrow><mo>|</mo><mrow><mo>-</mo><mi>e</mi></mrow><mo>|</mo></mrow>",
+            "</mrow></math>"
+        ));
+    }
+
+    #[test]
+    fn content() {
+        let expr = Expr::frac(-1, 2) * Expr::func(FuncIdentifier::Name(String::from("f")), vec![Expr::e(), Expr::var("x") - Expr::pi()]);
+        assert_eq!(expr.to_mathml_content(), concat!(
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><apply><times/>",
+            "<cn type=\"rational\">-1<sep/>2</cn>",
+            "<apply><ci type=\"function\">f</ci><exponentiale/><apply><minus/><ci>x</ci><pi/></apply></apply>",
+            "</apply></math>"
+        ));
+    }
+
+    #[test]
+    fn content_round_trip() {
+        let inputs = ["sin(x)^2 + 3/4*pi - e", "abs(x - 1)/(2*y) * tan(cos(-x))", "f(x, y)^-1 - 0.25", "r + 1/(i*w*c)", "x - inf"];
+        for input in inputs.iter() {
+            let expr = parse(input).unwrap();
+            assert_eq!(parse_mathml_content(&expr.to_mathml_content()).unwrap(), expr);
+        }
+        let sub = Expr::var("a") - Expr::var("b");
+        assert_eq!(parse_mathml_content(&sub.to_mathml_content()).unwrap(), sub);
+    }
+
+    #[test]
+    fn import() {
+        let input = r#"<?xml version="1.0"?>
+            <m:math xmlns:m="http://www.w3.org/1998/Math/MathML">
+              <!-- from another CAS -->
+              <m:apply><m:plus/>
+                <m:apply><m:root/><m:degree><m:cn>3</m:cn></m:degree><m:ci>x</m:ci></m:apply>
+                <m:apply><m:minus/><m:cn type="real">1.5</m:cn></m:apply>
+                <m:apply><m:csymbol>&#x393;</m:csymbol><m:ci>t</m:ci></m:apply>
+              </m:apply>
+            </m:math>"#;
+        assert_eq!(parse_mathml_content(input).unwrap(), parse("x^(1/3) + -3/2 + Γ(t)").unwrap());
+
+        let err = parse_mathml_content("<apply><log/><ci>x</ci></apply>").unwrap_err();
+        assert_eq!(err.span, 7..13);
+        assert_eq!(parse_mathml_content("<apply><sin/></apply>").unwrap_err().kind, ParseErrorKind::ArityMismatch { name: String::from("sin"), expected: 1, found: 0 });
+        assert_eq!(parse_mathml_content("<apply><plus/></times>").unwrap_err().span, 14..22);
+
+        for input in ["<apply><plus/></apply>", "<apply><times/></apply>", "<apply><ci>f</ci></apply>", "<apply><csymbol>f</csymbol></apply>"].iter() {
+            assert!(matches!(parse_mathml_content(input).unwrap_err().kind, ParseErrorKind::UnexpectedToken { .. }), "{}", input);
+        }
+        let err = parse_mathml_content("<apply><sin/><degree><cn>3</cn></degree><ci>x</ci></apply>").unwrap_err();
+        assert_eq!(err.span, 13..40);
+    }
+}
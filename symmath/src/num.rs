@@ -1,4 +1,9 @@
-use std::ops::{Add, Sub, Mul, Div};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Div, Rem, Neg, Range};
+use std::str::FromStr;
+use crate::bigint::BigInt;
+use crate::parse::{decimal, ParseError, ParseErrorKind};
 
 pub fn gcdi64(mut a: i64, mut b: i64) -> i64 {
     while b != 0 {
@@ -13,34 +18,405 @@ pub fn lcmi64(a: i64, b: i64) -> i64 {
     return (a * b) / gcdi64(a, b);
 }
 
-// PartialEq: This assumes that both fractions are fully simplified
+#[derive(Clone)]
 #[derive(PartialEq)]
+#[derive(Debug)]
+pub enum NumError {
+    DivisionByZero,
+    // A result does not fit in the fixed-width integer it was asked for
+    Overflow
+}
+
+impl fmt::Display for NumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumError::DivisionByZero => write!(f, "division by zero"),
+            NumError::Overflow => write!(f, "integer overflow")
+        }
+    }
+}
+
+impl std::error::Error for NumError {}
+
+pub fn checked_gcdi64(mut a: i64, mut b: i64) -> Result<i64, NumError> {
+    while b != 0 {
+        let tmp = a.checked_rem(b).ok_or(NumError::Overflow)?;
+        a = b;
+        b = tmp;
+    }
+    return Ok(a);
+}
+
+pub fn checked_lcmi64(a: i64, b: i64) -> Result<i64, NumError> {
+    let g = checked_gcdi64(a, b)?;
+    if g == 0 {
+        return Err(NumError::DivisionByZero);
+    }
+    let prod = a.checked_mul(b).ok_or(NumError::Overflow)?;
+    return prod.checked_div(g).ok_or(NumError::Overflow);
+}
+
+// An integer of any size. Values that fit in an i64 are kept there and only move to a
+// big integer when an operation overflows; results that fit again move back, so each
+// value has exactly one representation.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub struct Int(Repr);
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+enum Repr {
+    Small(i64),
+    Big(BigInt)
+}
+
+impl Int {
+    fn from_big(big: BigInt) -> Int {
+        match big.to_i64() {
+            Some(val) => Int(Repr::Small(val)),
+            None => Int(Repr::Big(big))
+        }
+    }
+
+    fn to_big(&self) -> BigInt {
+        match &self.0 {
+            Repr::Small(val) => BigInt::from_i64(*val),
+            Repr::Big(big) => big.clone()
+        }
+    }
+
+    // Reads an optionally signed string of decimal digits of any length
+    pub fn parse(text: &str) -> Option<Int> {
+        match text.parse::<i64>() {
+            Ok(val) if !text.starts_with('+') => Some(Int(Repr::Small(val))),
+            _ => BigInt::parse(text).map(Int::from_big)
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match &self.0 {
+            Repr::Small(val) => Some(*val),
+            Repr::Big(_) => None
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match &self.0 {
+            Repr::Small(val) => *val as f64,
+            Repr::Big(big) => big.to_f64()
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        *self == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match &self.0 {
+            Repr::Small(val) => *val < 0,
+            Repr::Big(big) => big.is_negative()
+        }
+    }
+
+    pub fn abs(&self) -> Int {
+        if self.is_negative() { -self } else { self.clone() }
+    }
+
+    // By repeated squaring
+    pub fn pow(&self, exp: u32) -> Int {
+        let mut out = Int::from(1);
+        let mut base = self.clone();
+        let mut exp = exp;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                out = &out * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        return out;
+    }
+
+    pub(crate) fn bits(&self) -> u64 {
+        match &self.0 {
+            Repr::Small(val) => 64 - val.unsigned_abs().leading_zeros() as u64,
+            Repr::Big(big) => big.bits()
+        }
+    }
+
+    // Multiplies by 2^bits
+    pub(crate) fn shl(&self, bits: u64) -> Int {
+        if self.bits() + bits < 63 {
+            if let Repr::Small(val) = &self.0 {
+                return Int(Repr::Small(val << bits));
+            }
+        }
+        return Int::from_big(self.to_big().shl(bits));
+    }
+
+    // Divides by 2^bits, rounding towards negative infinity
+    pub(crate) fn shr_floor(&self, bits: u64) -> Int {
+        if let Repr::Small(val) = &self.0 {
+            return Int(Repr::Small(if bits >= 64 { if *val < 0 { -1 } else { 0 } } else { val >> bits }));
+        }
+        let quot = Int::from_big(self.to_big().shr(bits));
+        if self.is_negative() && quot.shl(bits) != *self {
+            return quot - Int::from(1);
+        }
+        return quot;
+    }
+
+    // The nth root of a non-negative integer if it is a whole number, found by bisection
+    pub(crate) fn exact_root(&self, n: u32) -> Option<Int> {
+        // A root of a number with fewer than n bits lies below 2
+        if n as u64 > self.bits() {
+            return if *self <= Int::from(1) { Some(self.clone()) } else { None };
+        }
+        let mut lo = Int::from(0);
+        let mut hi = Int::from(1).shl(self.bits() / n as u64 + 1);
+        while lo < hi {
+            let mid = (&lo + &hi).shr_floor(1);
+            if mid.pow(n) < *self { lo = mid + Int::from(1); } else { hi = mid; }
+        }
+        if lo.pow(n) == *self { Some(lo) } else { None }
+    }
+
+    // Euclid's algorithm on truncated remainders, so the sign follows the same rules as gcdi64
+    pub fn gcd(&self, other: &Int) -> Int {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        while !b.is_zero() {
+            let tmp = &a % &b;
+            a = b;
+            b = tmp;
+        }
+        return a;
+    }
+
+    pub fn lcm(&self, other: &Int) -> Int {
+        return &(self * other) / &self.gcd(other);
+    }
+}
+
+impl From<i64> for Int {
+    fn from(val: i64) -> Int {
+        Int(Repr::Small(val))
+    }
+}
+
+impl From<i32> for Int {
+    fn from(val: i32) -> Int {
+        Int(Repr::Small(val as i64))
+    }
+}
+
+// Each operator tries the i64 fast path first and redoes the operation on big integers
+// only when it overflows
+fn add_int(a: &Int, b: &Int) -> Int {
+    if let (Repr::Small(x), Repr::Small(y)) = (&a.0, &b.0) {
+        if let Some(val) = x.checked_add(*y) { return Int(Repr::Small(val)); }
+    }
+    return Int::from_big(a.to_big().add(&b.to_big()));
+}
+
+fn sub_int(a: &Int, b: &Int) -> Int {
+    if let (Repr::Small(x), Repr::Small(y)) = (&a.0, &b.0) {
+        if let Some(val) = x.checked_sub(*y) { return Int(Repr::Small(val)); }
+    }
+    return Int::from_big(a.to_big().sub(&b.to_big()));
+}
+
+fn mul_int(a: &Int, b: &Int) -> Int {
+    if let (Repr::Small(x), Repr::Small(y)) = (&a.0, &b.0) {
+        if let Some(val) = x.checked_mul(*y) { return Int(Repr::Small(val)); }
+    }
+    return Int::from_big(a.to_big().mul(&b.to_big()));
+}
+
+fn div_int(a: &Int, b: &Int) -> Int {
+    if let (Repr::Small(x), Repr::Small(y)) = (&a.0, &b.0) {
+        if let Some(val) = x.checked_div(*y) { return Int(Repr::Small(val)); }
+    }
+    return Int::from_big(a.to_big().divrem(&b.to_big()).0);
+}
+
+fn rem_int(a: &Int, b: &Int) -> Int {
+    if let (Repr::Small(x), Repr::Small(y)) = (&a.0, &b.0) {
+        if let Some(val) = x.checked_rem(*y) { return Int(Repr::Small(val)); }
+    }
+    return Int::from_big(a.to_big().divrem(&b.to_big()).1);
+}
+
+macro_rules! int_op {
+    ($op:ident, $method:ident, $func:ident) => {
+        impl $op<&Int> for &Int {
+            type Output = Int;
+
+            fn $method(self, other: &Int) -> Int {
+                $func(self, other)
+            }
+        }
+
+        impl $op for Int {
+            type Output = Int;
+
+            fn $method(self, other: Int) -> Int {
+                $func(&self, &other)
+            }
+        }
+    };
+}
+
+int_op!(Add, add, add_int);
+int_op!(Sub, sub, sub_int);
+int_op!(Mul, mul, mul_int);
+int_op!(Div, div, div_int);
+int_op!(Rem, rem, rem_int);
+
+impl Neg for &Int {
+    type Output = Int;
+
+    fn neg(self) -> Int {
+        match &self.0 {
+            Repr::Small(val) => match val.checked_neg() {
+                Some(val) => Int(Repr::Small(val)),
+                None => Int::from_big(BigInt::from_i64(*val).neg())
+            },
+            Repr::Big(big) => Int::from_big(big.neg())
+        }
+    }
+}
+
+impl Neg for Int {
+    type Output = Int;
+
+    fn neg(self) -> Int {
+        -&self
+    }
+}
+
+impl Ord for Int {
+    fn cmp(&self, other: &Int) -> Ordering {
+        match (&self.0, &other.0) {
+            (Repr::Small(x), Repr::Small(y)) => x.cmp(y),
+            _ => self.to_big().cmp(&other.to_big())
+        }
+    }
+}
+
+impl PartialOrd for Int {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<i64> for Int {
+    fn eq(&self, other: &i64) -> bool {
+        self.0 == Repr::Small(*other)
+    }
+}
+
+impl PartialOrd<i64> for Int {
+    fn partial_cmp(&self, other: &i64) -> Option<Ordering> {
+        match &self.0 {
+            Repr::Small(val) => val.partial_cmp(other),
+            // A big value is beyond the range of any i64
+            Repr::Big(big) => Some(if big.is_negative() { Ordering::Less } else { Ordering::Greater })
+        }
+    }
+}
+
+impl PartialEq<i64> for &Int {
+    fn eq(&self, other: &i64) -> bool {
+        **self == *other
+    }
+}
+
+impl PartialOrd<i64> for &Int {
+    fn partial_cmp(&self, other: &i64) -> Option<Ordering> {
+        (**self).partial_cmp(other)
+    }
+}
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Repr::Small(val) => write!(f, "{}", val),
+            Repr::Big(big) => write!(f, "{}", big)
+        }
+    }
+}
+
+impl fmt::Debug for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// Always stored in canonical form: reduced, with the sign on the numerator and a positive
+// denominator, so equal values compare and hash equal. A zero denominator is kept as 1/0,
+// -1/0 or 0/0 and is rejected by the checked operations.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
 pub struct Frac {
-    pub num: i64,
-    pub denom: i64
+    num: Int,
+    denom: Int
 }
 
 impl Frac {
-    pub fn new(num: i64, denom: i64) -> Frac {
+    pub fn new(num: impl Into<Int>, denom: impl Into<Int>) -> Frac {
+        let mut num = num.into();
+        let mut denom = denom.into();
+
+        let g = num.gcd(&denom).abs();
+        if !g.is_zero() && g != 1 {
+            num = &num / &g;
+            denom = &denom / &g;
+        }
+        if denom.is_negative() {
+            num = -num;
+            denom = -denom;
+        }
+
         Frac {
             num, denom
         }
     }
 
-    pub fn gcd(&self) -> i64 {
-        gcdi64(self.num, self.denom)
+    pub fn checked_new(num: impl Into<Int>, denom: impl Into<Int>) -> Result<Frac, NumError> {
+        let f = Frac::new(num, denom);
+        f.check()?;
+        return Ok(f);
+    }
+
+    // A zero denominator can still be built with `new`, so every checked operation
+    // validates its operands before using them
+    fn check(&self) -> Result<(), NumError> {
+        if self.denom.is_zero() {
+            return Err(NumError::DivisionByZero);
+        }
+        return Ok(());
+    }
+
+    pub fn num(&self) -> &Int {
+        &self.num
     }
 
-    pub fn denom_lcm(&self, other: &Frac) -> i64 {
-        lcmi64(self.denom, other.denom)
+    pub fn denom(&self) -> &Int {
+        &self.denom
     }
 
-    pub fn simplify(&mut self) -> bool {
-        let g = self.gcd();
-        if g == 1 { return false; }
-        self.num /= g;
-        self.denom /= g;
-        return true;
+    pub fn denom_lcm(&self, other: &Frac) -> Int {
+        self.denom.lcm(&other.denom)
     }
 
     pub fn is_zero(&self) -> bool {
@@ -48,7 +424,7 @@ impl Frac {
     }
 
     pub fn is_one(&self) -> bool {
-        self.num == self.denom
+        self.num == 1 && self.denom == 1
     }
 
     pub fn is_int(&self) -> bool {
@@ -56,14 +432,47 @@ impl Frac {
     }
 
     pub fn is_negative(&self) -> bool {
-        self.denom * self.num < 0
+        self.num.is_negative()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.num.to_f64() / self.denom.to_f64()
     }
     
     pub fn inv(&self) -> Self {
-        Frac {
-            num: self.denom,
-            denom: self.num
+        Frac::new(self.denom.clone(), self.num.clone())
+    }
+
+    pub fn checked_inv(&self) -> Result<Frac, NumError> {
+        self.check()?;
+        return Frac::checked_new(self.denom.clone(), self.num.clone());
+    }
+
+    pub fn checked_add(self, other: Frac) -> Result<Frac, NumError> {
+        self.check()?;
+        other.check()?;
+        return Ok(self + other);
+    }
+
+    pub fn checked_sub(self, other: Frac) -> Result<Frac, NumError> {
+        self.check()?;
+        other.check()?;
+        return Ok(self - other);
+    }
+
+    pub fn checked_mul(self, other: Frac) -> Result<Frac, NumError> {
+        self.check()?;
+        other.check()?;
+        return Ok(self * other);
+    }
+
+    pub fn checked_div(self, other: Frac) -> Result<Frac, NumError> {
+        self.check()?;
+        other.check()?;
+        if other.is_zero() {
+            return Err(NumError::DivisionByZero);
         }
+        return Ok(self / other);
     }
 }
 
@@ -72,9 +481,7 @@ impl Add for Frac {
 
     fn add(self, other: Self) -> Self {
         let denom = self.denom_lcm(&other);
-        let mut f = Frac::new((self.num * (denom / self.denom)) + (other.num * (denom / other.denom)), denom);
-        f.simplify();
-        return f;
+        return Frac::new(&self.num * &(&denom / &self.denom) + &other.num * &(&denom / &other.denom), denom);
     }
 }
 
@@ -83,9 +490,7 @@ impl Sub for Frac {
 
     fn sub(self, other: Self) -> Self {
         let denom = self.denom_lcm(&other);
-        let mut f = Frac::new((self.num * (denom / self.denom)) - (other.num * (denom / other.denom)), denom);
-        f.simplify();
-        return f;
+        return Frac::new(&self.num * &(&denom / &self.denom) - &other.num * &(&denom / &other.denom), denom);
     }
 }
 
@@ -93,9 +498,7 @@ impl Mul for Frac {
     type Output = Self;
 
     fn mul(self, other: Self) -> Self {
-        let mut f = Frac::new(self.num * other.num, self.denom * other.denom);
-        f.simplify();
-        return f;
+        return Frac::new(self.num * other.num, self.denom * other.denom);
     }
 }
 
@@ -103,8 +506,264 @@ impl Div for Frac {
     type Output = Self;
 
     fn div(self, other: Self) -> Self {
-        let mut f = Frac::new(self.num * other.denom, self.denom * other.num);
-        f.simplify();
-        return f;
+        return Frac::new(self.num * other.denom, self.denom * other.num);
+    }
+}
+impl Neg for &Frac {
+    type Output = Frac;
+
+    fn neg(self) -> Frac {
+        Frac {
+            num: -&self.num,
+            denom: self.denom.clone()
+        }
+    }
+}
+
+impl Neg for Frac {
+    type Output = Frac;
+
+    fn neg(self) -> Frac {
+        -&self
+    }
+}
+
+impl Ord for Frac {
+    fn cmp(&self, other: &Frac) -> Ordering {
+        // Denominators are never negative, so cross-multiplying keeps the order. The tie-break
+        // only matters for zero denominators, where it keeps Ord consistent with Eq.
+        (&self.num * &other.denom).cmp(&(&other.num * &self.denom)).then_with(|| self.num.cmp(&other.num))
+    }
+}
+
+impl PartialOrd for Frac {
+    fn partial_cmp(&self, other: &Frac) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Frac {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_int() {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.denom)
+        }
     }
-}
\ No newline at end of file
+}
+
+// Reads an integer, a decimal or a ratio with a signed numerator, such as "5", "-0.75" or "-3/4"
+impl FromStr for Frac {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Frac, ParseError> {
+        let invalid = |span: Range<usize>| ParseError::new(span, ParseErrorKind::InvalidNumber);
+        let (num_text, denom_text) = match text.find('/') {
+            Some(idx) => (&text[..idx], Some(&text[idx + 1..])),
+            None => (text, None)
+        };
+
+        let (negative, digits) = match num_text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, num_text)
+        };
+        let mut parts = digits.splitn(2, '.');
+        let int = parts.next().unwrap();
+        let frac = parts.next().unwrap_or("");
+        if int.is_empty() && frac.is_empty() || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(invalid(0..num_text.len()));
+        }
+        let num = decimal(int, frac).ok_or_else(|| invalid(0..num_text.len()))?;
+        let num = if negative { -num } else { num };
+
+        let denom = match denom_text {
+            Some(denom_text) => match Int::parse(denom_text) {
+                Some(denom) if denom > 0 && denom_text.bytes().all(|b| b.is_ascii_digit()) => denom,
+                _ => return Err(invalid(num_text.len() + 1..text.len()))
+            },
+            None => return Ok(num)
+        };
+        return Ok(num / Frac::new(denom, 1));
+    }
+}
+
+// A complex number with rational parts, re + im*i
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
+pub struct Gaussian {
+    pub re: Frac,
+    pub im: Frac
+}
+
+impl Gaussian {
+    pub fn new(re: Frac, im: Frac) -> Gaussian {
+        Gaussian { re, im }
+    }
+
+    pub fn real(re: Frac) -> Gaussian {
+        Gaussian::new(re, Frac::new(0, 1))
+    }
+
+    pub fn i() -> Gaussian {
+        Gaussian::new(Frac::new(0, 1), Frac::new(1, 1))
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im.is_zero()
+    }
+
+    pub fn conj(&self) -> Gaussian {
+        Gaussian::new(self.re.clone(), -&self.im)
+    }
+
+    pub fn checked_add(self, other: Gaussian) -> Result<Gaussian, NumError> {
+        Ok(Gaussian::new(self.re.checked_add(other.re)?, self.im.checked_add(other.im)?))
+    }
+
+    pub fn checked_sub(self, other: Gaussian) -> Result<Gaussian, NumError> {
+        Ok(Gaussian::new(self.re.checked_sub(other.re)?, self.im.checked_sub(other.im)?))
+    }
+
+    pub fn checked_mul(self, other: Gaussian) -> Result<Gaussian, NumError> {
+        let re = self.re.clone().checked_mul(other.re.clone())?.checked_sub(self.im.clone().checked_mul(other.im.clone())?)?;
+        let im = self.re.checked_mul(other.im)?.checked_add(self.im.checked_mul(other.re)?)?;
+        Ok(Gaussian::new(re, im))
+    }
+
+    // Multiplies through by the conjugate, so the divisor becomes the real |other|^2
+    pub fn checked_div(self, other: Gaussian) -> Result<Gaussian, NumError> {
+        let norm = other.re.clone().checked_mul(other.re.clone())?.checked_add(other.im.clone().checked_mul(other.im.clone())?)?;
+        let num = self.checked_mul(other.conj())?;
+        Ok(Gaussian::new(num.re.checked_div(norm.clone())?, num.im.checked_div(norm)?))
+    }
+
+    // The bits in both numerators and denominators, which a power multiplies by at most
+    // its exponent
+    pub(crate) fn bits(&self) -> u64 {
+        self.re.num().bits() + self.re.denom().bits() + self.im.num().bits() + self.im.denom().bits()
+    }
+
+    // Repeated squaring, with negative exponents taken as powers of the reciprocal
+    pub fn checked_pow(self, exp: &Int) -> Result<Gaussian, NumError> {
+        let mut out = Gaussian::real(Frac::new(1, 1));
+        let mut base = if exp.is_negative() { Gaussian::real(Frac::new(1, 1)).checked_div(self)? } else { self };
+        let mut n = exp.abs();
+        let two = Int::from(2);
+        while !n.is_zero() {
+            if &n % &two != 0 {
+                out = out.checked_mul(base.clone())?;
+            }
+            n = &n / &two;
+            if !n.is_zero() {
+                base = base.clone().checked_mul(base)?;
+            }
+        }
+        return Ok(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checked_lcmi64, Frac, Gaussian, Int, NumError};
+    use crate::expr::*;
+    use crate::simplify::SimplifcationOpts;
+
+    #[test]
+    fn overflow_moves_to_big_integers() {
+        let max = Frac::new(i64::MAX, 1);
+        let square = max.clone() * max.clone();
+        assert_eq!(square.num.to_i64(), None);
+        assert_eq!(square.num.to_string(), "85070591730234615847396907784232501249");
+        // Dividing back down returns to the i64 representation, so the values compare equal
+        assert_eq!(square / max.clone(), max);
+
+        assert_eq!(-Int::from(i64::MIN), Int::parse("9223372036854775808").unwrap());
+        assert!(Int::parse("-9223372036854775809").unwrap() < i64::MIN);
+        assert_eq!(Frac::new(1, i64::MAX) + Frac::new(1, i64::MAX - 1), Frac::new(Int::from(i64::MAX) + Int::from(i64::MAX - 1), Int::from(i64::MAX) * Int::from(i64::MAX - 1)));
+    }
+
+    #[test]
+    fn simplify_large_products() {
+        let mut expr = Expr::new(Operator::Mul, Some(vec![Expr::frac(1, 3_i64.pow(39)); 5]));
+        expr.simplify(&SimplifcationOpts::default()).unwrap();
+        assert_eq!(expr, Expr::frac(1, Int::from(3).pow(195)));
+
+        let mut sum = Expr::frac(i64::MAX, 2) + Expr::frac(i64::MAX, 2) + Expr::int(1);
+        sum.simplify(&SimplifcationOpts::default()).unwrap();
+        assert_eq!(sum.to_string(), "9223372036854775808");
+    }
+
+    #[test]
+    fn checked_operations() {
+        assert_eq!(Frac::checked_new(1, 0), Err(NumError::DivisionByZero));
+        assert_eq!(Frac::new(0, 1).checked_inv(), Err(NumError::DivisionByZero));
+        assert_eq!(Frac::new(1, 2).checked_div(Frac::new(0, 3)), Err(NumError::DivisionByZero));
+        assert_eq!(Frac::new(1, 0).checked_add(Frac::new(1, 2)), Err(NumError::DivisionByZero));
+        assert_eq!(Frac::new(1, 2).checked_sub(Frac::new(1, 3)), Ok(Frac::new(1, 6)));
+        assert_eq!(Frac::new(2, 3).checked_mul(Frac::new(3, 4)), Ok(Frac::new(1, 2)));
+
+        assert_eq!(checked_lcmi64(4, 6), Ok(12));
+        assert_eq!(checked_lcmi64(0, 0), Err(NumError::DivisionByZero));
+        assert_eq!(checked_lcmi64(i64::MAX, i64::MAX - 1), Err(NumError::Overflow));
+    }
+
+    #[test]
+    fn canonical_form() {
+        assert_eq!(Frac::new(1, -2), Frac::new(-1, 2));
+        assert_eq!(Frac::new(-6, -4), Frac::new(3, 2));
+        assert_eq!(Frac::new(0, -5), Frac::new(0, 1));
+        assert!(Frac::new(1, -2).is_negative());
+        assert_eq!(-Frac::new(3, 4), Frac::new(3, -4));
+
+        let mut sorted = vec![Frac::new(1, 2), Frac::new(-3, 4), Frac::new(2, 3), Frac::new(4, 8), Frac::new(-1, 1)];
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, vec![Frac::new(-1, 1), Frac::new(-3, 4), Frac::new(1, 2), Frac::new(2, 3)]);
+
+        let mut counts = std::collections::HashMap::new();
+        for f in [Frac::new(2, 4), Frac::new(-1, -2), Frac::new(1, 3)].iter() {
+            *counts.entry(f.clone()).or_insert(0) += 1;
+        }
+        assert_eq!(counts[&Frac::new(1, 2)], 2);
+    }
+
+    #[test]
+    fn display_and_from_str() {
+        assert_eq!("-3/4".parse::<Frac>().unwrap(), Frac::new(-3, 4));
+        assert_eq!("0.75".parse::<Frac>().unwrap(), Frac::new(3, 4));
+        assert_eq!("-1.5/3".parse::<Frac>().unwrap(), Frac::new(-1, 2));
+        assert_eq!("12".parse::<Frac>().unwrap().to_string(), "12");
+        assert_eq!(Frac::new(6, -8).to_string(), "-3/4");
+
+        assert_eq!("3/0".parse::<Frac>().unwrap_err().span, 2..3);
+        assert_eq!("3/-4".parse::<Frac>().unwrap_err().span, 2..4);
+        assert_eq!("x/4".parse::<Frac>().unwrap_err().span, 0..1);
+        assert!(".".parse::<Frac>().is_err());
+    }
+
+    #[test]
+    fn exact_roots() {
+        assert_eq!(Int::from(0).exact_root(3), Some(Int::from(0)));
+        assert_eq!(Int::from(81).exact_root(4), Some(Int::from(3)));
+        assert_eq!(Int::from(80).exact_root(2), None);
+        let big = Int::parse("123456789012345678901").unwrap();
+        assert_eq!(big.pow(3).exact_root(3), Some(big.clone()));
+        assert_eq!(big.exact_root(100000), None);
+        assert_eq!(Int::from(1).exact_root(u32::MAX), Some(Int::from(1)));
+        assert_eq!((big.pow(3) + Int::from(1)).exact_root(3), None);
+    }
+
+    #[test]
+    fn gaussian_arithmetic() {
+        let a = Gaussian::new(Frac::new(1, 1), Frac::new(2, 1));
+        let b = Gaussian::new(Frac::new(3, 1), Frac::new(-1, 2));
+        assert_eq!(a.clone().checked_mul(b.clone()), Ok(Gaussian::new(Frac::new(4, 1), Frac::new(11, 2))));
+        assert_eq!(a.clone().checked_mul(b.clone()).unwrap().checked_div(b.clone()), Ok(a.clone()));
+        assert_eq!(Gaussian::i().checked_pow(&Int::from(-7)), Ok(Gaussian::i()));
+        assert_eq!(a.clone().checked_pow(&Int::from(2)), Ok(Gaussian::new(Frac::new(-3, 1), Frac::new(4, 1))));
+        assert_eq!(a.checked_div(Gaussian::real(Frac::new(0, 1))), Err(NumError::DivisionByZero));
+    }
+}
@@ -0,0 +1,462 @@
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use crate::expr::*;
+use crate::num;
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    // A character that cannot start any token
+    UnknownCharacter(char),
+    // A numeric literal that is malformed, such as "1.2.3", or does not fit in a Frac
+    InvalidNumber,
+    // A token was found where one of `expected` should have been
+    UnexpectedToken { found: String, expected: Vec<String> },
+    // The input ended where one of `expected` should have been
+    UnexpectedEnd { expected: Vec<String> },
+    // A built-in function was called with the wrong number of arguments
+    ArityMismatch { name: String, expected: usize, found: usize },
+    // A LaTeX control sequence with no known meaning and no argument to apply it to
+    UnknownCommand(String)
+}
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub struct ParseError {
+    // Byte range of the offending input
+    pub span: Range<usize>,
+    pub kind: ParseErrorKind
+}
+
+impl ParseError {
+    pub(crate) fn new(span: Range<usize>, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            span, kind
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnknownCharacter(c) => write!(f, "unknown character '{}'", c)?,
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number")?,
+            ParseErrorKind::UnexpectedToken { found, expected } => write!(f, "unexpected '{}', expected {}", found, expected.join(" or "))?,
+            ParseErrorKind::UnexpectedEnd { expected } => write!(f, "unexpected end of input, expected {}", expected.join(" or "))?,
+            ParseErrorKind::ArityMismatch { name, expected, found } => write!(f, "{} expects {} argument(s), found {}", name, expected, found)?,
+            ParseErrorKind::UnknownCommand(name) => write!(f, "unknown command '\\{}'", name)?
+        }
+        return write!(f, " at {}..{}", self.span.start, self.span.end);
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Reads the digits either side of a decimal point as an exact fraction, so "0.75" is 3/4
+pub(crate) fn decimal(int: &str, frac: &str) -> Option<num::Frac> {
+    let denom = num::Int::from(10).pow(frac.len() as u32);
+    let digits = format!("{}{}", int, frac);
+    let num = if digits.is_empty() { num::Int::from(0) } else { num::Int::parse(&digits)? };
+
+    return Some(num::Frac::new(num, denom));
+}
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+enum Token {
+    Number(num::Int, num::Int),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LParen,
+    RParen,
+    Comma,
+    End
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(num, denom) => if *denom == 1 { format!("{}", num) } else { format!("{}/{}", num, denom) },
+            Token::Ident(name) => name.clone(),
+            Token::Plus => String::from("+"),
+            Token::Minus => String::from("-"),
+            Token::Star => String::from("*"),
+            Token::Slash => String::from("/"),
+            Token::Caret => String::from("^"),
+            Token::LParen => String::from("("),
+            Token::RParen => String::from(")"),
+            Token::Comma => String::from(","),
+            Token::End => String::from("end of input")
+        }
+    }
+
+    // Whether this token can begin an operand, which makes `2x` or `2(x + 1)` an implicit product
+    fn starts_operand(&self) -> bool {
+        matches!(self, Token::Number(_, _) | Token::Ident(_) | Token::LParen)
+    }
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Lexer<'a> {
+        Lexer {
+            input,
+            pos: 0
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, pred: F) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek_char() {
+            if !pred(c) { break; }
+            self.pos += c.len_utf8();
+        }
+        return &self.input[start..self.pos];
+    }
+
+    fn number(&mut self, start: usize) -> Result<Token, ParseError> {
+        let int = self.take_while(|c| c.is_ascii_digit());
+        let mut frac = "";
+        if self.peek_char() == Some('.') {
+            self.pos += 1;
+            frac = self.take_while(|c| c.is_ascii_digit());
+        }
+
+        // A lone "." has no digits, and "1.2.3" runs straight into a second point
+        if (int.is_empty() && frac.is_empty()) || self.peek_char() == Some('.') {
+            self.take_while(|c| c.is_ascii_digit() || c == '.');
+            return Err(ParseError::new(start..self.pos, ParseErrorKind::InvalidNumber));
+        }
+
+        let f = decimal(int, frac).ok_or_else(|| ParseError::new(start..self.pos, ParseErrorKind::InvalidNumber))?;
+        return Ok(Token::Number(f.num().clone(), f.denom().clone()));
+    }
+
+    fn next_token(&mut self) -> Result<(Token, Range<usize>), ParseError> {
+        self.take_while(char::is_whitespace);
+
+        let start = self.pos;
+        let c = match self.peek_char() {
+            Some(c) => c,
+            None => return Ok((Token::End, start..start))
+        };
+
+        let token = if c.is_ascii_digit() || c == '.' {
+            self.number(start)?
+        } else if c.is_alphabetic() || c == '_' {
+            self.take_while(|c| c.is_alphanumeric() || c == '_');
+            // Trailing primes name a derivative, as in f'(x) or f''(x)
+            self.take_while(|c| c == '\'');
+            Token::Ident(String::from(&self.input[start..self.pos]))
+        } else {
+            self.pos += c.len_utf8();
+            match c {
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '*' => Token::Star,
+                '/' => Token::Slash,
+                '^' => Token::Caret,
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                _ => return Err(ParseError::new(start..self.pos, ParseErrorKind::UnknownCharacter(c)))
+            }
+        };
+
+        return Ok((token, start..self.pos));
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    token: Token,
+    span: Range<usize>
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Result<Parser<'a>, ParseError> {
+        let mut lexer = Lexer::new(input);
+        let (token, span) = lexer.next_token()?;
+        Ok(Parser {
+            lexer, token, span
+        })
+    }
+
+    fn advance(&mut self) -> Result<Token, ParseError> {
+        let (token, span) = self.lexer.next_token()?;
+        self.span = span;
+        return Ok(std::mem::replace(&mut self.token, token));
+    }
+
+    fn unexpected(&self, expected: &[&str]) -> ParseError {
+        let expected = expected.iter().map(|e| String::from(*e)).collect();
+        let kind = match self.token {
+            Token::End => ParseErrorKind::UnexpectedEnd { expected },
+            _ => ParseErrorKind::UnexpectedToken { found: self.token.describe(), expected }
+        };
+        return ParseError::new(self.span.clone(), kind);
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), ParseError> {
+        if self.token != token {
+            return Err(self.unexpected(&[&token.describe()]));
+        }
+        self.advance()?;
+        return Ok(());
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.product()?;
+        // Only extend an Add built by this loop, so "(a + b) + c" keeps its grouping
+        let mut chained = false;
+
+        loop {
+            match self.token {
+                Token::Plus => {
+                    self.advance()?;
+                    let rhs = self.product()?;
+                    if chained {
+                        lhs.elements.as_mut().unwrap().push(rhs);
+                    } else {
+                        lhs = lhs + rhs;
+                        chained = true;
+                    }
+                },
+                Token::Minus => {
+                    self.advance()?;
+                    lhs = lhs - self.product()?;
+                    chained = false;
+                },
+                _ => return Ok(lhs)
+            }
+        }
+    }
+
+    // product := unary (('*' | '/') unary | operand)*
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        let (mut lhs, mut literal) = self.unary()?;
+        let mut chained = false;
+
+        loop {
+            match self.token {
+                Token::Slash => {
+                    self.advance()?;
+                    let (rhs, rhs_literal) = self.unary()?;
+                    // Two bare integers either side of a slash are a single rational literal
+                    lhs = match (&lhs.operator, &rhs.operator, literal && rhs_literal) {
+                        (Operator::Frac(a), Operator::Frac(b), true) if a.is_int() && b.is_int() && !b.is_zero() => Expr::frac(a.num().clone(), b.num().clone()),
+                        _ => lhs / rhs
+                    };
+                    chained = false;
+                    literal = false;
+                    continue;
+                },
+                Token::Star => {
+                    self.advance()?;
+                },
+                ref t if t.starts_operand() => {},
+                _ => return Ok(lhs)
+            }
+
+            let (rhs, _) = self.unary()?;
+            if chained {
+                lhs.elements.as_mut().unwrap().push(rhs);
+            } else {
+                lhs = lhs * rhs;
+                chained = true;
+            }
+            literal = false;
+        }
+    }
+
+    // unary := '-' unary | '+' unary | power
+    // The flag reports whether the operand is a bare integer literal
+    fn unary(&mut self) -> Result<(Expr, bool), ParseError> {
+        match self.token {
+            Token::Minus => {
+                self.advance()?;
+                let (mut operand, literal) = self.unary()?;
+                if let Operator::Frac(ref mut f) = operand.operator {
+                    *f = -&*f;
+                    return Ok((operand, literal));
+                }
+                return Ok((Expr::int(-1) * operand, false));
+            },
+            Token::Plus => {
+                self.advance()?;
+                return self.unary();
+            },
+            _ => self.power()
+        }
+    }
+
+    // power := primary ('^' unary)?
+    // The exponent is parsed with `unary`, which makes '^' right associative and allows "x^-1"
+    fn power(&mut self) -> Result<(Expr, bool), ParseError> {
+        let (base, literal) = self.primary()?;
+        if self.token != Token::Caret {
+            return Ok((base, literal));
+        }
+
+        self.advance()?;
+        let (exponent, _) = self.unary()?;
+        return Ok((Expr::pow(base, exponent), false));
+    }
+
+    fn primary(&mut self) -> Result<(Expr, bool), ParseError> {
+        let start = self.span.start;
+        match self.token.clone() {
+            Token::Number(num, denom) => {
+                self.advance()?;
+                let literal = denom == 1;
+                return Ok((Expr::frac(num, denom), literal));
+            },
+            Token::LParen => {
+                self.advance()?;
+                let inner = self.sum()?;
+                self.expect(Token::RParen)?;
+                return Ok((inner, false));
+            },
+            Token::Ident(name) => {
+                self.advance()?;
+                if self.token == Token::LParen {
+                    return Ok((self.call(name, start)?, false));
+                }
+
+                let expr = match name.as_str() {
+                    "pi" => Expr::pi(),
+                    "e" => Expr::e(),
+                    "i" => Expr::i(),
+                    "inf" => Expr::infinity(),
+                    _ => Expr::var(&name)
+                };
+                return Ok((expr, false));
+            },
+            _ => Err(self.unexpected(&["number", "identifier", "("]))
+        }
+    }
+
+    fn call(&mut self, name: String, start: usize) -> Result<Expr, ParseError> {
+        self.expect(Token::LParen)?;
+
+        let mut params = Vec::new();
+        if self.token != Token::RParen {
+            params.push(self.sum()?);
+            while self.token == Token::Comma {
+                self.advance()?;
+                params.push(self.sum()?);
+            }
+        }
+
+        let end = self.span.end;
+        if self.token != Token::RParen {
+            return Err(self.unexpected(&[",", ")"]));
+        }
+        self.advance()?;
+
+        let ident = match name.as_str() {
+            "sin" => FuncIdentifier::Sine,
+            "cos" => FuncIdentifier::Cosine,
+            "tan" => FuncIdentifier::Tangent,
+            "abs" => FuncIdentifier::Abs,
+            _ => FuncIdentifier::Name(name)
+        };
+
+        if !matches!(ident, FuncIdentifier::Name(_)) && params.len() != 1 {
+            return Err(ParseError::new(start..end, ParseErrorKind::ArityMismatch {
+                name: ident.get_name(),
+                expected: 1,
+                found: params.len()
+            }));
+        }
+
+        return Ok(Expr::func(ident, params));
+    }
+}
+
+// Parses an infix expression such as "sin(x)^2 + 3/4*pi - e"
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser::new(input)?;
+    let expr = parser.sum()?;
+    if parser.token != Token::End {
+        return Err(parser.unexpected(&["operator", "end of input"]));
+    }
+    return Ok(expr);
+}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Expr, ParseError> {
+        parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precedence() {
+        let expr = parse("sin(x)^2 + 3/4*pi - e").unwrap();
+        let expected = (Expr::pow(Expr::func(FuncIdentifier::Sine, vec![Expr::var("x")]), Expr::int(2)) + Expr::frac(3, 4) * Expr::pi()) - Expr::e();
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        let expr = parse("-x^2^3").unwrap();
+        let expected = Expr::int(-1) * Expr::pow(Expr::var("x"), Expr::pow(Expr::int(2), Expr::int(3)));
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        let expr = parse("2(y + 1)x / 0.5").unwrap();
+        let expected = Expr::new(Operator::Mul, Some(vec![Expr::int(2), Expr::var("y") + Expr::int(1), Expr::var("x")])) / Expr::frac(1, 2);
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn derivative_names() {
+        let expr = parse("f''(x) + g_1(x, y)").unwrap();
+        let expected = Expr::func(FuncIdentifier::Name(String::from("f''")), vec![Expr::var("x")])
+            + Expr::func(FuncIdentifier::Name(String::from("g_1")), vec![Expr::var("x"), Expr::var("y")]);
+        assert_eq!(expr, expected);
+        assert_eq!(parse("'x").unwrap_err().kind, ParseErrorKind::UnknownCharacter('\''));
+    }
+
+    #[test]
+    fn errors() {
+        let err = parse("1 + * 2").unwrap_err();
+        assert_eq!(err.span, 4..5);
+        assert_eq!(err.kind, ParseErrorKind::UnexpectedToken {
+            found: String::from("*"),
+            expected: vec![String::from("number"), String::from("identifier"), String::from("(")]
+        });
+
+        assert_eq!(parse("(x + 1").unwrap_err().span, 6..6);
+        assert_eq!(parse("sin(x, y)").unwrap_err().kind, ParseErrorKind::ArityMismatch { name: String::from("sin"), expected: 1, found: 2 });
+        assert_eq!(parse("x $ 1").unwrap_err().kind, ParseErrorKind::UnknownCharacter('$'));
+        assert_eq!(parse("1.2.3").unwrap_err(), ParseError::new(0..5, ParseErrorKind::InvalidNumber));
+        assert_eq!(parse("x + 2..5").unwrap_err().span, 4..8);
+        assert_eq!(parse(".").unwrap_err().kind, ParseErrorKind::InvalidNumber);
+        assert_eq!(parse(".5 + 1.").unwrap(), Expr::frac(1, 2) + Expr::int(1));
+    }
+}
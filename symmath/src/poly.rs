@@ -0,0 +1,483 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use crate::expr::*;
+use crate::num::{Frac, Int, NumError};
+use crate::simplify::{multinomial_terms, MAX_POWER_BITS};
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum PolyError {
+    Num(NumError),
+    // A function, constant, division by a variable or non-integer power
+    NotPolynomial,
+    // A power of a sum that would expand to more terms than simplification allows
+    TooLarge
+}
+
+impl fmt::Display for PolyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolyError::Num(err) => write!(f, "{}", err),
+            PolyError::NotPolynomial => write!(f, "expression is not a polynomial"),
+            PolyError::TooLarge => write!(f, "expansion has too many terms")
+        }
+    }
+}
+
+impl std::error::Error for PolyError {}
+
+impl From<NumError> for PolyError {
+    fn from(err: NumError) -> PolyError {
+        PolyError::Num(err)
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Debug)]
+pub enum MonomialOrder {
+    Lex,
+    // Total degree first, then lex
+    Grlex,
+    // Total degree first, then the smaller power of the last variable wins
+    Grevlex
+}
+
+impl MonomialOrder {
+    // Compares exponent vectors over the same variables
+    pub fn cmp(&self, a: &[u32], b: &[u32]) -> Ordering {
+        let degree = |m: &[u32]| m.iter().map(|e| *e as u64).sum::<u64>();
+        match self {
+            MonomialOrder::Lex => a.cmp(b),
+            MonomialOrder::Grlex => degree(a).cmp(&degree(b)).then_with(|| a.cmp(b)),
+            MonomialOrder::Grevlex => degree(a).cmp(&degree(b)).then_with(|| b.iter().rev().cmp(a.iter().rev()))
+        }
+    }
+}
+
+type Terms = BTreeMap<Vec<u32>, Frac>;
+
+fn insert(terms: &mut Terms, exps: Vec<u32>, c: Frac) {
+    let sum = match terms.remove(&exps) {
+        Some(old) => old + c,
+        None => c
+    };
+    if !sum.is_zero() {
+        terms.insert(exps, sum);
+    }
+}
+
+// By repeated squaring, as powers above i32::MAX are out of reach of powi
+fn powu(base: f64, exp: u32) -> f64 {
+    let mut out = 1.0;
+    let mut base = base;
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            out *= base;
+        }
+        exp >>= 1;
+        base *= base;
+    }
+    return out;
+}
+
+fn mul_monomials(a: &[u32], b: &[u32]) -> Vec<u32> {
+    return a.iter().zip(b.iter()).map(|(e, f)| e + f).collect();
+}
+
+// A sparse polynomial with rational coefficients. The variables are kept sorted and only
+// those that occur are listed, so every exponent vector lines up with `vars` and equal
+// polynomials compare equal.
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+#[derive(Debug)]
+pub struct Polynomial {
+    vars: Vec<String>,
+    terms: Terms
+}
+
+impl Polynomial {
+    pub fn zero() -> Polynomial {
+        Polynomial {
+            vars: Vec::new(),
+            terms: Terms::new()
+        }
+    }
+
+    pub fn constant(c: Frac) -> Polynomial {
+        let mut terms = Terms::new();
+        insert(&mut terms, Vec::new(), c);
+        Polynomial {
+            vars: Vec::new(),
+            terms
+        }
+    }
+
+    pub fn var(name: &str) -> Polynomial {
+        Polynomial {
+            vars: vec![String::from(name)],
+            terms: std::iter::once((vec![1], Frac::new(1, 1))).collect()
+        }
+    }
+
+    // Drops the variables that no longer occur in any term
+    fn from_terms(vars: Vec<String>, terms: Terms) -> Polynomial {
+        let used: Vec<usize> = (0..vars.len()).filter(|i| terms.keys().any(|exps| exps[*i] > 0)).collect();
+        if used.len() == vars.len() {
+            return Polynomial { vars, terms };
+        }
+        Polynomial {
+            vars: used.iter().map(|i| vars[*i].clone()).collect(),
+            terms: terms.into_iter().map(|(exps, c)| (used.iter().map(|i| exps[*i]).collect(), c)).collect()
+        }
+    }
+
+    // The terms rewritten over `vars`, which must include every variable of self
+    fn aligned(&self, vars: &[String]) -> Terms {
+        let idx: Vec<usize> = self.vars.iter().map(|var| vars.iter().position(|v| v == var).unwrap()).collect();
+        return self.terms.iter().map(|(exps, c)| {
+            let mut out = vec![0; vars.len()];
+            for (i, e) in idx.iter().zip(exps.iter()) {
+                out[*i] = *e;
+            }
+            (out, c.clone())
+        }).collect();
+    }
+
+    fn merged_vars(&self, other: &Polynomial) -> Vec<String> {
+        let mut vars: Vec<String> = self.vars.iter().chain(other.vars.iter()).cloned().collect();
+        vars.sort();
+        vars.dedup();
+        return vars;
+    }
+
+    fn scale(&self, c: &Frac) -> Polynomial {
+        if c.is_zero() {
+            return Polynomial::zero();
+        }
+        Polynomial {
+            vars: self.vars.clone(),
+            terms: self.terms.iter().map(|(exps, x)| (exps.clone(), x.clone() * c.clone())).collect()
+        }
+    }
+
+    pub fn vars(&self) -> &[String] {
+        &self.vars
+    }
+
+    // Exponent vectors in the order of `vars` with their coefficients, ascending in lex order
+    pub fn terms(&self) -> impl Iterator<Item = (&[u32], &Frac)> {
+        self.terms.iter().map(|(exps, c)| (exps.as_slice(), c))
+    }
+
+    pub fn num_terms(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    // The value of a polynomial without variables
+    pub fn constant_value(&self) -> Option<Frac> {
+        if !self.vars.is_empty() {
+            return None;
+        }
+        return Some(self.terms.values().next().cloned().unwrap_or_else(|| Frac::new(0, 1)));
+    }
+
+    // The total degree, or None for the zero polynomial
+    pub fn degree(&self) -> Option<u32> {
+        self.terms.keys().map(|exps| exps.iter().sum()).max()
+    }
+
+    pub fn degree_in(&self, var: &str) -> Option<u32> {
+        if self.is_zero() {
+            return None;
+        }
+        return Some(match self.vars.iter().position(|v| v == var) {
+            Some(i) => self.terms.keys().map(|exps| exps[i]).max().unwrap(),
+            None => 0
+        });
+    }
+
+    pub fn leading_term(&self, order: MonomialOrder) -> Option<(&[u32], &Frac)> {
+        match order {
+            MonomialOrder::Lex => self.terms.iter().next_back(),
+            _ => self.terms.iter().max_by(|a, b| order.cmp(a.0, b.0))
+        }.map(|(exps, c)| (exps.as_slice(), c))
+    }
+
+    pub fn leading_coefficient(&self, order: MonomialOrder) -> Option<&Frac> {
+        self.leading_term(order).map(|(_, c)| c)
+    }
+
+    // By repeated squaring
+    pub fn pow(&self, exp: u32) -> Polynomial {
+        let mut out = Polynomial::constant(Frac::new(1, 1));
+        let mut base = self.clone();
+        let mut exp = exp;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                out = out * base.clone();
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.clone() * base;
+            }
+        }
+        return out;
+    }
+
+    // The powers of each variable are tabulated once, so every term costs one
+    // multiplication per variable. `values` follows the order of `vars`.
+    pub fn eval(&self, values: &[Frac]) -> Result<Frac, NumError> {
+        assert_eq!(values.len(), self.vars.len(), "Expected a value for each variable");
+        let mut sum = Frac::new(0, 1);
+        for (exps, c) in self.terms.iter() {
+            let mut term = c.clone();
+            for (val, e) in values.iter().zip(exps.iter()) {
+                let power = Frac::new(val.num().pow(*e), val.denom().pow(*e));
+                term = term.checked_mul(power)?;
+            }
+            sum = sum.checked_add(term)?;
+        }
+        return Ok(sum);
+    }
+
+    pub fn eval_f64(&self, values: &[f64]) -> f64 {
+        assert_eq!(values.len(), self.vars.len(), "Expected a value for each variable");
+        return self.terms.iter().map(|(exps, c)| {
+            values.iter().zip(exps.iter()).fold(c.to_f64(), |acc, (val, e)| acc * powu(*val, *e))
+        }).sum();
+    }
+
+    // Roughly the bits each power adds to the coefficients, counted like MAX_POWER_BITS
+    // counts them for numbers. A lone x^2 with coefficient 1 adds none
+    fn coefficient_growth(&self) -> u64 {
+        let largest = self.terms.values().map(|c| {
+            if c.num().abs() == Int::from(1) && c.denom() == &Int::from(1) { 0 } else { c.num().bits() + c.denom().bits() }
+        }).max().unwrap_or(0);
+        return largest + (self.terms.len() as u64).next_power_of_two().trailing_zeros() as u64;
+    }
+
+    // A sum in descending lex order, with each coefficient after its variables
+    pub fn to_expr(&self) -> Expr {
+        let mut terms = Vec::new();
+        for (exps, c) in self.terms.iter().rev() {
+            let mut factors = Vec::new();
+            for (var, e) in self.vars.iter().zip(exps.iter()) {
+                match e {
+                    0 => {},
+                    1 => factors.push(Expr::var(var)),
+                    _ => factors.push(Expr::pow(Expr::var(var), Expr::int(*e as i64)))
+                }
+            }
+            if factors.is_empty() || !c.is_one() {
+                factors.push(Expr::frac_cpy(c));
+            }
+            terms.push(if factors.len() == 1 { factors.pop().unwrap() } else { Expr::new(Operator::Mul, Some(factors)) });
+        }
+        return match terms.len() {
+            0 => Expr::int(0),
+            1 => terms.pop().unwrap(),
+            _ => Expr::new(Operator::Add, Some(terms))
+        };
+    }
+}
+
+impl Add for Polynomial {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let vars = self.merged_vars(&other);
+        let mut terms = self.aligned(&vars);
+        for (exps, c) in other.aligned(&vars) {
+            insert(&mut terms, exps, c);
+        }
+        return Polynomial::from_terms(vars, terms);
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        return self + -other;
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let vars = self.merged_vars(&other);
+        let (a, b) = (self.aligned(&vars), other.aligned(&vars));
+        let mut terms = Terms::new();
+        for (x_exps, x) in a.iter() {
+            for (y_exps, y) in b.iter() {
+                insert(&mut terms, mul_monomials(x_exps, y_exps), x.clone() * y.clone());
+            }
+        }
+        return Polynomial::from_terms(vars, terms);
+    }
+}
+
+impl Neg for Polynomial {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        return self.scale(&Frac::new(-1, 1));
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_expr())
+    }
+}
+
+impl Expr {
+    // Reads a sum of products of names and rational numbers. Quotients are allowed only by
+    // nonzero constants and powers only to non-negative integers.
+    pub fn to_polynomial(&self) -> Result<Polynomial, PolyError> {
+        match &self.operator {
+            Operator::Frac(f) => Ok(Polynomial::constant(Frac::checked_new(f.num().clone(), f.denom().clone())?)),
+            Operator::Name(name) => Ok(Polynomial::var(name)),
+            Operator::Add => {
+                let mut sum = Polynomial::zero();
+                for child in self.children().iter() {
+                    sum = sum + child.to_polynomial()?;
+                }
+                Ok(sum)
+            },
+            Operator::Sub => {
+                let els = self.children();
+                let mut diff = els[0].to_polynomial()?;
+                for child in els[1..].iter() {
+                    diff = diff - child.to_polynomial()?;
+                }
+                Ok(diff)
+            },
+            Operator::Mul => {
+                let mut product = Polynomial::constant(Frac::new(1, 1));
+                for child in self.children().iter() {
+                    product = product * child.to_polynomial()?;
+                }
+                Ok(product)
+            },
+            Operator::Div => {
+                let els = self.children();
+                let num = els[0].to_polynomial()?;
+                match els[1].to_polynomial()?.constant_value() {
+                    Some(denom) => Ok(num.scale(&denom.checked_inv()?)),
+                    None => Err(PolyError::NotPolynomial)
+                }
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let base = els[0].to_polynomial()?;
+                match els[1].to_polynomial()?.constant_value() {
+                    Some(exp) if exp.is_int() && !exp.is_negative() => match exp.num().to_i64() {
+                        Some(n) if n <= u32::MAX as i64 => match multinomial_terms(base.num_terms(), n as u64) {
+                            Some(_) if base.coefficient_growth().saturating_mul(n as u64) <= MAX_POWER_BITS => Ok(base.pow(n as u32)),
+                            _ => Err(PolyError::TooLarge)
+                        },
+                        _ => Err(PolyError::NotPolynomial)
+                    },
+                    _ => Err(PolyError::NotPolynomial)
+                }
+            },
+            Operator::Func(_) | Operator::Const(_) => Err(PolyError::NotPolynomial)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use crate::expr::*;
+    use crate::num::Frac;
+    use crate::parse::parse;
+    use crate::simplify::SimplifcationOpts;
+    use super::{MonomialOrder, PolyError, Polynomial};
+
+    fn poly(input: &str) -> Polynomial {
+        parse(input).unwrap().to_polynomial().unwrap()
+    }
+
+    #[test]
+    fn conversion() {
+        assert_eq!(poly("(x + 1)*(x - 1) - x^2"), Polynomial::constant(Frac::new(-1, 1)));
+        assert_eq!(poly("x - x + y"), Polynomial::var("y"));
+        assert_eq!(poly("(x + y)^2 / 2").to_string(), "x^2*(1/2) + x*y + y^2*(1/2)");
+
+        let mut expected = parse("3*x^2*y - x + 7/2").unwrap();
+        expected.simplify(&SimplifcationOpts::default()).unwrap();
+        assert_eq!(poly("7/2 - x + y*x^2*3").to_expr(), expected);
+        assert_eq!(Polynomial::zero().to_expr(), Expr::int(0));
+
+        for input in ["sin(x) + 1", "x^(1/2)", "1/x", "pi*x", "x^y"].iter() {
+            assert_eq!(parse(input).unwrap().to_polynomial(), Err(PolyError::NotPolynomial), "{}", input);
+        }
+        assert!(matches!(parse("x/0").unwrap().to_polynomial(), Err(PolyError::Num(_))));
+        assert_eq!(parse("(x + y)^4000000000").unwrap().to_polynomial(), Err(PolyError::TooLarge));
+        assert_eq!(poly("x^4000000000").degree(), Some(4000000000));
+        assert_eq!(parse("2^100000000*x").unwrap().to_polynomial(), Err(PolyError::TooLarge));
+        assert_eq!(parse("(1/3)^40000*x").unwrap().to_polynomial(), Err(PolyError::TooLarge));
+        assert_eq!(poly("(2*x)^10").to_string(), "x^10*1024");
+    }
+
+    #[test]
+    fn arithmetic_and_degrees() {
+        let (x, y) = (Polynomial::var("x"), Polynomial::var("y"));
+        let sum = x.clone() + y.clone();
+        assert_eq!(sum.pow(3), poly("x^3 + 3*x^2*y + 3*x*y^2 + y^3"));
+        assert_eq!(sum.pow(0), Polynomial::constant(Frac::new(1, 1)));
+        assert_eq!((sum.clone() * (x.clone() - y.clone())) - x.pow(2), -y.pow(2));
+        assert_eq!(sum.pow(5).num_terms(), 6);
+
+        let p = poly("x^2*y^3 + x^4 - 2*z + 1");
+        assert_eq!(p.vars(), ["x", "y", "z"]);
+        assert_eq!(p.degree(), Some(5));
+        assert_eq!(p.degree_in("x"), Some(4));
+        assert_eq!(p.degree_in("w"), Some(0));
+        assert_eq!(Polynomial::zero().degree(), None);
+    }
+
+    #[test]
+    fn orderings() {
+        let p = poly("x*y^2*z^2 + x^3*y*z + x^2*y^2*z + x^4 + y^6");
+        assert_eq!(p.leading_term(MonomialOrder::Lex).unwrap().0, [4, 0, 0]);
+        assert_eq!(p.leading_term(MonomialOrder::Grlex).unwrap().0, [0, 6, 0]);
+        // All of total degree 5, so grlex falls back to lex while grevlex prefers the
+        // smallest power of z
+        let r = poly("x*y^2*z^2 + x^2*y^2*z + x^3*z^2");
+        assert_eq!(r.leading_term(MonomialOrder::Grlex).unwrap().0, [3, 0, 2]);
+        assert_eq!(r.leading_term(MonomialOrder::Grevlex).unwrap().0, [2, 2, 1]);
+        assert_eq!(MonomialOrder::Grevlex.cmp(&[1, 1, 0], &[0, 0, 2]), Ordering::Greater);
+        assert_eq!(poly("5 - 3*x").leading_coefficient(MonomialOrder::Lex), Some(&Frac::new(-3, 1)));
+    }
+
+    #[test]
+    fn evaluation() {
+        let p = poly("x^3*y - y^2/4 + 2");
+        assert_eq!(p.eval(&[Frac::new(1, 2), Frac::new(2, 1)]).unwrap(), Frac::new(5, 4));
+        assert_eq!(p.eval_f64(&[0.5, 2.0]), 1.25);
+        assert_eq!(Polynomial::constant(Frac::new(3, 1)).eval(&[]).unwrap(), Frac::new(3, 1));
+
+        let huge = poly("x^4000000000 + x");
+        assert_eq!(huge.eval(&[Frac::new(1, 1)]).unwrap(), Frac::new(2, 1));
+        assert_eq!(huge.eval(&[Frac::new(-1, 1)]).unwrap(), Frac::new(0, 1));
+        assert_eq!(huge.eval_f64(&[-1.0]), 0.0);
+        assert_eq!(huge.eval_f64(&[0.5]), 0.5);
+    }
+}
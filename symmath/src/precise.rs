@@ -0,0 +1,553 @@
+use std::fmt;
+use crate::eval::{check_arity, EvalError};
+use crate::expr::*;
+use crate::num::{Frac, Int};
+
+// Attempts before giving up, each carrying twice as many extra bits as the last
+const ESCALATION_STEPS: u32 = 10;
+
+// The largest integer power of an exact fraction worked out exactly, in bits of numerator
+// and denominator. Larger powers are bounded like any other real power.
+const MAX_EXACT_POWER_BITS: u64 = 1 << 20;
+
+// A value rounded half away from zero to a fixed number of decimal places, stored as
+// `value / 10^places`
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Debug)]
+pub struct Decimal {
+    value: Int,
+    places: u32
+}
+
+impl Decimal {
+    pub fn places(&self) -> u32 {
+        self.places
+    }
+
+    pub fn to_frac(&self) -> Frac {
+        Frac::new(self.value.clone(), Int::from(10).pow(self.places))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.value.abs().to_string();
+        let places = self.places as usize;
+        let digits = format!("{:0>width$}", digits, width = places + 1);
+        let (int, frac) = digits.split_at(digits.len() - places);
+        if self.value.is_negative() {
+            write!(f, "-")?;
+        }
+        if places == 0 {
+            return write!(f, "{}", int);
+        }
+        return write!(f, "{}.{}", int, frac);
+    }
+}
+
+// Rounds n / d half away from zero
+fn round_div(n: &Int, d: &Int) -> Int {
+    let quot = &(&n.abs().shl(1) + &d.abs()) / &d.abs().shl(1);
+    if n.is_negative() != d.is_negative() { -quot } else { quot }
+}
+
+enum Failure {
+    Eval(EvalError),
+    // The working precision was too low to decide, such as whether a divisor is zero
+    Imprecise
+}
+
+impl From<EvalError> for Failure {
+    fn from(err: EvalError) -> Failure {
+        Failure::Eval(err)
+    }
+}
+
+// Extra bits carried by the series below so that their rounding errors, a few units per
+// term, stay below one unit of the requested precision
+fn guard_bits(prec: u64) -> u64 {
+    40 + (64 - prec.leading_zeros() as u64)
+}
+
+// Sum of sign^k / ((2k + 1) n^(2k + 1)), which is atan(1/n) for sign -1 and atanh(1/n) for
+// sign 1, with `prec` fractional bits
+fn arctan_inv(n: i64, alternating: bool, prec: u64) -> Int {
+    let n2 = Int::from(n * n);
+    let mut power = &Int::from(1).shl(prec) / &Int::from(n);
+    let mut sum = Int::from(0);
+    let mut k = 0i64;
+    while !power.is_zero() {
+        let term = &power / &Int::from(2 * k + 1);
+        sum = if alternating && k % 2 == 1 { sum - term } else { sum + term };
+        power = &power / &n2;
+        k += 1;
+    }
+    return sum;
+}
+
+fn pi(prec: u64) -> Int {
+    let work = prec + guard_bits(prec);
+    let sum = arctan_inv(5, true, work).shl(4) - arctan_inv(239, true, work).shl(2);
+    return sum.shr_floor(work - prec);
+}
+
+fn ln2(prec: u64) -> Int {
+    let work = prec + guard_bits(prec);
+    return arctan_inv(3, false, work).shl(1).shr_floor(work - prec);
+}
+
+// exp(x / 2^prec) with `prec` fractional bits, reduced to exp(t) * 2^n with |t| < ln 2
+fn exp_point(x: &Int, prec: u64) -> Result<Int, Failure> {
+    let estimate = x.to_f64() / 2f64.powi(prec as i32) / std::f64::consts::LN_2;
+    if !estimate.is_finite() || estimate.abs() > 1e9 {
+        return Err(Failure::Imprecise);
+    }
+    let shift = estimate.max(0.0) as u64 + 64 - (estimate.abs() as u64).leading_zeros() as u64;
+    let work = prec + guard_bits(prec) + shift + 2;
+    let log2 = ln2(work);
+    let xw = x.shl(work - prec);
+    let n = &xw / &log2;
+    let t = &xw - &(&n * &log2);
+
+    let mut sum = Int::from(0);
+    let mut term = Int::from(1).shl(work);
+    let mut k = 1i64;
+    while !term.is_zero() {
+        sum = &sum + &term;
+        term = &(&term * &t).shr_floor(work) / &Int::from(k);
+        k += 1;
+    }
+
+    let n = n.to_i64().unwrap();
+    let scaled = if n >= 0 { sum.shl(n as u64) } else { sum.shr_floor(n.unsigned_abs()) };
+    return Ok(scaled.shr_floor(work - prec));
+}
+
+// ln(x / 2^prec) for positive x, from x = 2^k y with y in [1, 2) and
+// ln y = 2 atanh((y - 1) / (y + 1))
+fn ln_point(x: &Int, prec: u64) -> Int {
+    let k = x.bits() as i64 - prec as i64 - 1;
+    let work = prec + guard_bits(prec) + 64 - k.unsigned_abs().leading_zeros() as u64;
+    let xw = x.shl(work - prec);
+    let y = if k >= 0 { xw.shr_floor(k as u64) } else { xw.shl(k.unsigned_abs()) };
+    let one = Int::from(1).shl(work);
+    let z = &(&y - &one).shl(work) / &(&y + &one);
+    let z2 = (&z * &z).shr_floor(work);
+
+    let mut sum = Int::from(0);
+    let mut power = z;
+    let mut k2 = 1i64;
+    while !power.is_zero() {
+        sum = &sum + &(&power / &Int::from(k2));
+        power = (&power * &z2).shr_floor(work);
+        k2 += 2;
+    }
+
+    let ln = sum.shl(1) + &ln2(work) * &Int::from(k);
+    return ln.shr_floor(work - prec);
+}
+
+// (sin, cos) of x / 2^prec, reduced by multiples of pi/2 to |t| < pi/2
+fn sin_cos_point(x: &Int, prec: u64) -> (Int, Int) {
+    let int_bits = x.bits().saturating_sub(prec);
+    let work = prec + guard_bits(prec) + int_bits + 2;
+    let half_pi = pi(work).shr_floor(1);
+    let xw = x.shl(work - prec);
+    let n = &xw / &half_pi;
+    let t = &xw - &(&n * &half_pi);
+    let t2 = (&t * &t).shr_floor(work);
+
+    let mut sin = Int::from(0);
+    let mut term = t;
+    let mut k = 1i64;
+    while !term.is_zero() {
+        sin = &sin + &term;
+        term = -(&term * &t2).shr_floor(work) / Int::from((k + 1) * (k + 2));
+        k += 2;
+    }
+
+    let mut cos = Int::from(0);
+    let mut term = Int::from(1).shl(work);
+    let mut k = 0i64;
+    while !term.is_zero() {
+        cos = &cos + &term;
+        term = -(&term * &t2).shr_floor(work) / Int::from((k + 1) * (k + 2));
+        k += 2;
+    }
+
+    let quadrant = (&(&n % &Int::from(4)) + &Int::from(4)) % Int::from(4);
+    let (sin, cos) = match quadrant.to_i64().unwrap() {
+        0 => (sin, cos),
+        1 => (cos, -sin),
+        2 => (-sin, -cos),
+        _ => (-cos, sin)
+    };
+    return (sin.shr_floor(work - prec), cos.shr_floor(work - prec));
+}
+
+// The interval (mid - rad, mid + rad) / 2^prec, which always contains the true value
+#[derive(Clone)]
+struct Ball {
+    mid: Int,
+    rad: Int
+}
+
+impl Ball {
+    // A point computed by one of the series above, which are within two units
+    fn point(mid: Int) -> Ball {
+        Ball { mid, rad: Int::from(2) }
+    }
+
+    fn from_frac(f: &Frac, prec: u64) -> Ball {
+        Ball {
+            mid: &f.num().shl(prec) / f.denom(),
+            rad: Int::from(if f.is_int() { 0 } else { 1 })
+        }
+    }
+
+    fn lo(&self) -> Int {
+        &self.mid - &self.rad
+    }
+
+    fn hi(&self) -> Int {
+        &self.mid + &self.rad
+    }
+
+    fn is_positive(&self) -> bool {
+        self.lo() > 0
+    }
+
+    fn is_negative(&self) -> bool {
+        self.hi() < 0
+    }
+
+    fn hull(a: Ball, b: Ball) -> Ball {
+        let lo = a.lo().min(b.lo());
+        let hi = a.hi().max(b.hi());
+        Ball {
+            mid: (&lo + &hi).shr_floor(1),
+            rad: (&hi - &lo).shr_floor(1) + Int::from(1)
+        }
+    }
+
+    fn neg(self) -> Ball {
+        Ball { mid: -self.mid, rad: self.rad }
+    }
+
+    fn add(&self, other: &Ball) -> Ball {
+        Ball { mid: &self.mid + &other.mid, rad: &self.rad + &other.rad }
+    }
+
+    fn mul(&self, other: &Ball, prec: u64) -> Ball {
+        let spread = &(&self.mid.abs() * &other.rad) + &(&(&other.mid.abs() * &self.rad) + &(&self.rad * &other.rad));
+        Ball {
+            mid: (&self.mid * &other.mid).shr_floor(prec),
+            rad: spread.shr_floor(prec) + Int::from(2)
+        }
+    }
+
+    fn div(&self, other: &Ball, prec: u64) -> Result<Ball, Failure> {
+        let divisor = other.mid.abs();
+        if divisor <= other.rad {
+            return Err(Failure::Imprecise);
+        }
+        let spread = &(&self.rad * &divisor) + &(&other.rad * &self.mid.abs());
+        Ok(Ball {
+            mid: &self.mid.shl(prec) / &other.mid,
+            rad: &spread.shl(prec) / &(&divisor * &(&divisor - &other.rad)) + Int::from(2)
+        })
+    }
+
+    fn pow_int(&self, exp: &Int, prec: u64) -> Result<Ball, Failure> {
+        let mut out = Ball::from_frac(&Frac::new(1, 1), prec);
+        let mut base = self.clone();
+        let mut n = exp.abs();
+        while !n.is_zero() {
+            if &n % &Int::from(2) != 0 {
+                out = out.mul(&base, prec);
+            }
+            base = base.mul(&base, prec);
+            n = n.shr_floor(1);
+        }
+        if exp.is_negative() {
+            return Ball::from_frac(&Frac::new(1, 1), prec).div(&out, prec);
+        }
+        return Ok(out);
+    }
+
+    // exp and ln are increasing, so the image of the interval is spanned by its endpoints
+    fn exp(&self, prec: u64) -> Result<Ball, Failure> {
+        Ok(Ball::hull(Ball::point(exp_point(&self.lo(), prec)?), Ball::point(exp_point(&self.hi(), prec)?)))
+    }
+
+    fn ln(&self, prec: u64) -> Result<Ball, Failure> {
+        if self.hi() <= 0 {
+            return Err(Failure::Eval(EvalError::NotReal));
+        }
+        if !self.is_positive() {
+            return Err(Failure::Imprecise);
+        }
+        Ok(Ball::hull(Ball::point(ln_point(&self.lo(), prec)), Ball::point(ln_point(&self.hi(), prec))))
+    }
+
+    // sin and cos change by at most the change in their argument
+    fn sin_cos(&self, prec: u64) -> (Ball, Ball) {
+        let (sin, cos) = sin_cos_point(&self.mid, prec);
+        let rad = &self.rad + &Int::from(2);
+        (Ball { mid: sin, rad: rad.clone() }, Ball { mid: cos, rad })
+    }
+}
+
+enum Value {
+    Exact(Frac),
+    Approx(Ball)
+}
+
+impl Value {
+    fn ball(&self, prec: u64) -> Ball {
+        match self {
+            Value::Exact(f) => Ball::from_frac(f, prec),
+            Value::Approx(ball) => ball.clone()
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            Value::Exact(f) => f.is_zero(),
+            Value::Approx(_) => false
+        }
+    }
+}
+
+fn add(a: Value, b: Value, prec: u64) -> Value {
+    match (a, b) {
+        (Value::Exact(a), Value::Exact(b)) => Value::Exact(a.checked_add(b).unwrap()),
+        (a, b) => Value::Approx(a.ball(prec).add(&b.ball(prec)))
+    }
+}
+
+fn mul(a: Value, b: Value, prec: u64) -> Value {
+    if a.is_zero() || b.is_zero() {
+        return Value::Exact(Frac::new(0, 1));
+    }
+    match (a, b) {
+        (Value::Exact(a), Value::Exact(b)) => Value::Exact(a.checked_mul(b).unwrap()),
+        (a, b) => Value::Approx(a.ball(prec).mul(&b.ball(prec), prec))
+    }
+}
+
+fn div(a: Value, b: Value, prec: u64) -> Result<Value, Failure> {
+    if b.is_zero() {
+        return Err(Failure::Eval(EvalError::DivisionByZero));
+    }
+    match (a, b) {
+        (Value::Exact(a), Value::Exact(b)) => Ok(Value::Exact(a.checked_div(b).unwrap())),
+        (a, b) => Ok(Value::Approx(a.ball(prec).div(&b.ball(prec), prec)?))
+    }
+}
+
+// exp(exponent * ln |base|), negated for a negative base under an odd power
+fn real_pow(base: &Ball, exponent: &Ball, negate: bool, prec: u64) -> Result<Value, Failure> {
+    let magnitude = if base.is_negative() { base.clone().neg() } else { base.clone() };
+    let ball = exponent.mul(&magnitude.ln(prec)?, prec).exp(prec)?;
+    Ok(Value::Approx(if negate { ball.neg() } else { ball }))
+}
+
+fn pow(base: Value, exponent: Value, prec: u64) -> Result<Value, Failure> {
+    if base.is_zero() {
+        let sign = match &exponent {
+            Value::Exact(f) if f.is_zero() => return Ok(Value::Exact(Frac::new(1, 1))),
+            Value::Exact(f) => !f.is_negative(),
+            Value::Approx(ball) if ball.is_positive() => true,
+            Value::Approx(ball) if ball.is_negative() => false,
+            Value::Approx(_) => return Err(Failure::Imprecise)
+        };
+        if !sign {
+            return Err(Failure::Eval(EvalError::DivisionByZero));
+        }
+        return Ok(Value::Exact(Frac::new(0, 1)));
+    }
+
+    match (&base, &exponent) {
+        (Value::Exact(b), Value::Exact(e)) if e.is_int() => {
+            let n = e.num().abs().to_i64().unwrap_or(i64::MAX) as u64;
+            if (b.num().bits() + b.denom().bits()).saturating_mul(n) <= MAX_EXACT_POWER_BITS {
+                let power = Frac::new(b.num().pow(n as u32), b.denom().pow(n as u32));
+                return Ok(Value::Exact(if e.is_negative() { power.inv() } else { power }));
+            }
+            // Past that a power is either too large to write out, or close enough to zero
+            // to be bounded through exp and ln below
+            let magnitude = b.num().abs();
+            if magnitude != *b.denom() && (magnitude > *b.denom()) != e.is_negative() {
+                return Err(Failure::Imprecise);
+            }
+        },
+        (Value::Approx(b), Value::Exact(e)) if e.is_int() => return Ok(Value::Approx(b.pow_int(e.num(), prec)?)),
+        _ => {}
+    }
+
+    let b = base.ball(prec);
+    let e = exponent.ball(prec);
+    if b.is_positive() {
+        return real_pow(&b, &e, false, prec);
+    }
+    if !b.is_negative() {
+        return Err(Failure::Imprecise);
+    }
+    // Odd roots of negative numbers are real, as in eval_f64
+    match &exponent {
+        Value::Exact(f) if f.denom() % &Int::from(2) != 0 => real_pow(&b, &e, f.num() % &Int::from(2) != 0, prec),
+        _ => Err(Failure::Eval(EvalError::NotReal))
+    }
+}
+
+impl Expr {
+    fn eval_ball(&self, prec: u64) -> Result<Value, Failure> {
+        match &self.operator {
+            Operator::Frac(f) => {
+                if f.denom().is_zero() {
+                    return Err(Failure::Eval(EvalError::DivisionByZero));
+                }
+                Ok(Value::Exact(f.clone()))
+            },
+            Operator::Add => {
+                let mut sum = Value::Exact(Frac::new(0, 1));
+                for child in self.children().iter() {
+                    sum = add(sum, child.eval_ball(prec)?, prec);
+                }
+                Ok(sum)
+            },
+            Operator::Mul => {
+                let mut product = Value::Exact(Frac::new(1, 1));
+                for child in self.children().iter() {
+                    product = mul(product, child.eval_ball(prec)?, prec);
+                }
+                Ok(product)
+            },
+            Operator::Div => {
+                let els = self.children();
+                div(els[0].eval_ball(prec)?, els[1].eval_ball(prec)?, prec)
+            },
+            Operator::Sub => {
+                let els = self.children();
+                let mut diff = els[0].eval_ball(prec)?;
+                for child in els[1..].iter() {
+                    let negated = mul(child.eval_ball(prec)?, Value::Exact(Frac::new(-1, 1)), prec);
+                    diff = add(diff, negated, prec);
+                }
+                Ok(diff)
+            },
+            Operator::Pow => {
+                let els = self.children();
+                pow(els[0].eval_ball(prec)?, els[1].eval_ball(prec)?, prec)
+            },
+            Operator::Name(name) => Err(Failure::Eval(EvalError::UnboundVariable(name.clone()))),
+            Operator::Func(FuncIdentifier::Name(name)) => Err(Failure::Eval(EvalError::UnknownFunction(name.clone()))),
+            Operator::Func(ident) => {
+                let args = self.children();
+                check_arity(&ident.get_name(), args, 1)?;
+                let x = args[0].eval_ball(prec)?;
+                if let FuncIdentifier::Abs = ident {
+                    return Ok(match x {
+                        Value::Exact(f) => Value::Exact(if f.is_negative() { -f } else { f }),
+                        Value::Approx(ball) => Value::Approx(Ball { mid: ball.mid.abs(), rad: ball.rad })
+                    });
+                }
+                if x.is_zero() {
+                    let cosine = match ident { FuncIdentifier::Cosine => 1, _ => 0 };
+                    return Ok(Value::Exact(Frac::new(cosine, 1)));
+                }
+                let (sin, cos) = x.ball(prec).sin_cos(prec);
+                Ok(Value::Approx(match ident {
+                    FuncIdentifier::Sine => sin,
+                    FuncIdentifier::Cosine => cos,
+                    _ => sin.div(&cos, prec)?
+                }))
+            },
+            Operator::Const(Constant::Pi) => Ok(Value::Approx(Ball::point(pi(prec)))),
+            Operator::Const(Constant::E) => Ok(Value::Approx(Ball::point(exp_point(&Int::from(1).shl(prec), prec)?))),
+            Operator::Const(Constant::I) | Operator::Const(Constant::Infinity) => Err(Failure::Eval(EvalError::NotReal))
+        }
+    }
+
+    // Evaluates to `digits` decimal places, correctly rounded. Exact fractions are carried
+    // through unchanged and anything else is bounded in an interval, retrying with more bits
+    // until both ends of the interval round to the same digits.
+    pub fn eval_precise(&self, digits: u32) -> Result<Decimal, EvalError> {
+        let scale = Int::from(10).pow(digits);
+        let target = digits as u64 * 3322 / 1000 + 1;
+        for step in 0..ESCALATION_STEPS {
+            let prec = target + (32 << step);
+            let one = Int::from(1).shl(prec);
+            match self.eval_ball(prec) {
+                Ok(Value::Exact(f)) => {
+                    return Ok(Decimal {
+                        value: round_div(&(f.num() * &scale), f.denom()),
+                        places: digits
+                    });
+                },
+                Ok(Value::Approx(ball)) => {
+                    let lo = round_div(&(&ball.lo() * &scale), &one);
+                    let hi = round_div(&(&ball.hi() * &scale), &one);
+                    if lo == hi {
+                        return Ok(Decimal { value: lo, places: digits });
+                    }
+                },
+                Err(Failure::Eval(err)) => return Err(err),
+                Err(Failure::Imprecise) => {}
+            }
+        }
+        return Err(EvalError::PrecisionExhausted(digits));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::EvalError;
+    use crate::num::Frac;
+    use crate::parse::parse;
+
+    fn eval(input: &str, digits: u32) -> String {
+        parse(input).unwrap().eval_precise(digits).unwrap().to_string()
+    }
+
+    #[test]
+    fn constants_and_functions() {
+        assert_eq!(eval("pi", 50), "3.14159265358979323846264338327950288419716939937511");
+        assert_eq!(eval("e", 40), "2.7182818284590452353602874713526624977572");
+        assert_eq!(eval("sin(1)", 30), "0.841470984807896506652502321630");
+        assert_eq!(eval("cos(100)", 25), "0.8623188722876839341019385");
+        assert_eq!(eval("tan(-1/3)", 25), "-0.3462535495105754910385436");
+        assert_eq!(eval("2^(1/2)", 40), "1.4142135623730950488016887242096980785697");
+        assert_eq!(eval("(-8)^(1/3) + e^-2", 20), "-1.86466471676338730811");
+        assert_eq!(eval("pi^e", 20), "22.45915771836104547343");
+    }
+
+    #[test]
+    fn exact_values_and_identities() {
+        assert_eq!(eval("1/3 - 2^-2", 10), "0.0833333333");
+        assert_eq!(eval("(2/3)^10 * abs(-3)", 0), "0");
+        assert_eq!(eval("-5/2", 0), "-3");
+        assert_eq!(parse("1/8").unwrap().eval_precise(2).unwrap().to_frac(), Frac::new(13, 100));
+        assert_eq!(eval("sin(1/7)^2 + cos(1/7)^2 - 1", 60), format!("0.{}", "0".repeat(60)));
+        assert_eq!(eval("e^(pi*3) - (e^pi)^3", 30), format!("0.{}", "0".repeat(30)));
+        assert_eq!(eval("2^-100000 + 3^-400000 + 1", 10), "1.0000000000");
+        assert_eq!(eval("(-1/2)^100001 + (-1)^-100000001", 5), "-1.00000");
+        assert_eq!(eval("2^100000", 0).len(), 30103);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("1/(2 - 2)").unwrap().eval_precise(5), Err(EvalError::DivisionByZero));
+        assert_eq!(parse("(-2)^(1/2)").unwrap().eval_precise(5), Err(EvalError::NotReal));
+        assert_eq!(parse("x + 1").unwrap().eval_precise(5), Err(EvalError::UnboundVariable(String::from("x"))));
+        assert_eq!(parse("1/(pi - pi)").unwrap().eval_precise(5), Err(EvalError::PrecisionExhausted(5)));
+        assert_eq!(parse("3^4000000").unwrap().eval_precise(0), Err(EvalError::PrecisionExhausted(0)));
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+use crate::expr::*;
+use crate::num;
+
+// Binding strength in infix text. A fraction such as 3/4 is written with a slash,
+// so it binds like a product.
+fn precedence(expr: &Expr) -> u8 {
+    match &expr.operator {
+        Operator::Add | Operator::Sub => 1,
+        Operator::Mul | Operator::Div => 2,
+        Operator::Frac(f) if !f.is_int() => 2,
+        Operator::Pow => 3,
+        _ => 4
+    }
+}
+
+fn frac_infix(frac: &num::Frac) -> String {
+    if frac.is_int() {
+        format!("{}", frac.num())
+    } else {
+        format!("{}/{}", frac.num(), frac.denom())
+    }
+}
+
+fn parens(inner: String) -> String {
+    format!("({})", inner)
+}
+
+// If `expr` is a term with a negative coefficient that the typeset renderers can write as
+// a subtraction, returns the term with that sign removed. This undoes both `negate` and the
+// parser's unary minus: the coefficient is the leading Frac of a product, or else its
+// trailing Frac, and a trailing coefficient of exactly -1 is dropped only where negate
+// would put it back.
+pub(crate) fn unsigned(expr: &Expr) -> Option<Expr> {
+    match &expr.operator {
+        Operator::Frac(f) if f.is_negative() => Some(Expr::new_empty(Operator::Frac(-f))),
+        Operator::Mul => {
+            let els = expr.children();
+            if let Operator::Frac(f) = &els.first()?.operator {
+                if !f.is_negative() { return None; }
+                // The parser reads -x as -1 * x
+                if f.num() == -1 && f.is_int() && els.len() > 1 {
+                    let rest = els[1..].to_vec();
+                    return Some(if rest.len() == 1 { rest.into_iter().next().unwrap() } else { Expr::new(Operator::Mul, Some(rest)) });
+                }
+                let mut term = expr.clone();
+                term.elements.as_mut().unwrap()[0] = Expr::new_empty(Operator::Frac(-f));
+                return Some(term);
+            }
+
+            let f = match &els.last()?.operator {
+                Operator::Frac(f) if f.is_negative() => f,
+                _ => return None
+            };
+
+            let mut rest = els[..els.len() - 1].to_vec();
+            if f.num() != -1 || f.denom() != 1 {
+                rest.push(Expr::new_empty(Operator::Frac(-f)));
+                return Some(Expr::new(Operator::Mul, Some(rest)));
+            }
+
+            match rest.len() {
+                1 => match rest[0].operator {
+                    Operator::Mul | Operator::Frac(_) => None,
+                    _ => rest.pop()
+                },
+                _ => match rest.last().unwrap().operator {
+                    Operator::Frac(_) => None,
+                    _ => Some(Expr::new(Operator::Mul, Some(rest)))
+                }
+            }
+        },
+        _ => None
+    }
+}
+
+impl Expr {
+    // Infix text with only the parentheses that precedence, associativity and the
+    // parser's grouping rules require, so that `parse` reads back the same tree
+    fn infix(&self) -> String {
+        match &self.operator {
+            Operator::Frac(frac) => frac_infix(frac),
+            Operator::Add => {
+                let mut out = String::new();
+                for (idx, child) in self.children().iter().enumerate() {
+                    if idx == 0 {
+                        // A leading difference is read back as the first term, but a leading
+                        // sum would be merged into this one
+                        out.push_str(&match child.operator {
+                            Operator::Add => parens(child.infix()),
+                            _ => child.infix()
+                        });
+                    } else {
+                        out.push_str(" + ");
+                        out.push_str(&child.infix_operand(2, true));
+                    }
+                }
+                out
+            },
+            Operator::Sub => {
+                let mut out = String::new();
+                for (idx, child) in self.children().iter().enumerate() {
+                    if idx == 0 {
+                        out.push_str(&child.infix());
+                    } else {
+                        out.push_str(" - ");
+                        out.push_str(&child.infix_operand(2, true));
+                    }
+                }
+                out
+            },
+            Operator::Mul => {
+                let els = self.children();
+                // -x is read back as -1 * x, but a literal such as -3 is folded into one number
+                if els.len() == 2 && !matches!(els[1].operator, Operator::Frac(_)) {
+                    if let Operator::Frac(f) = &els[0].operator {
+                        if f.num() == -1 && f.is_int() {
+                            return format!("-{}", els[1].infix_operand(3, true));
+                        }
+                    }
+                }
+
+                let mut out = String::new();
+                for (idx, child) in els.iter().enumerate() {
+                    if idx == 0 {
+                        // A leading product would be merged into this one
+                        out.push_str(&match child.operator {
+                            Operator::Mul => parens(child.infix()),
+                            _ => child.infix_operand(2, true)
+                        });
+                    } else {
+                        // Later factors are read one at a time, so x*3/4 would become (x*3)/4
+                        out.push('*');
+                        out.push_str(&child.infix_operand(3, true));
+                    }
+                }
+                out
+            },
+            Operator::Div => {
+                let els = self.children();
+                // Two bare integers either side of the slash would be read back as one fraction
+                let denom = match (&els[0].operator, &els[1].operator) {
+                    (Operator::Frac(a), Operator::Frac(b)) if a.is_int() && b.is_int() && !b.is_zero() => parens(els[1].infix()),
+                    _ => els[1].infix_operand(3, true)
+                };
+                format!("{}/{}", els[0].infix_operand(2, true), denom)
+            },
+            Operator::Pow => {
+                let els = self.children();
+                format!("{}^{}", els[0].infix_operand(4, false), els[1].infix_operand(3, true))
+            },
+            Operator::Name(name) => name.clone(),
+            Operator::Func(ident) => {
+                let args = self.children().iter().map(|el| el.infix()).collect::<Vec<String>>().join(", ");
+                format!("{}({})", ident.get_name(), args)
+            },
+            Operator::Const(cst) => cst.get_name()
+        }
+    }
+
+    // A leading minus is read as part of any operand except the base of a power
+    fn infix_operand(&self, min_precedence: u8, allow_minus: bool) -> String {
+        let text = self.infix();
+        if precedence(self) < min_precedence || (!allow_minus && text.starts_with('-')) {
+            parens(text)
+        } else {
+            text
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.infix())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+    use crate::parse::parse;
+
+    fn round_trip(expr: Expr) -> String {
+        let text = expr.to_string();
+        assert_eq!(parse(&text).unwrap(), expr, "{}", text);
+        text
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        let x = || Expr::var("x");
+        assert_eq!(round_trip(Expr::frac(1, 2) + Expr::frac(3, 2) * x()), "1/2 + 3/2*x");
+        assert_eq!(round_trip(x() / Expr::int(6)), "x/6");
+        assert_eq!(round_trip(x() * Expr::frac(1, 6)), "x*(1/6)");
+        assert_eq!(round_trip(Expr::int(2) / Expr::int(6)), "2/(6)");
+        assert_eq!(round_trip((x() + Expr::int(1)) * (x() / Expr::var("y"))), "(x + 1)*(x/y)");
+        assert_eq!(round_trip((x() * Expr::var("y")) / (Expr::var("z") * Expr::int(2))), "x*y/(z*2)");
+        assert_eq!(round_trip(Expr::pow(Expr::frac(-1, 2), x() + Expr::int(1))), "(-1/2)^(x + 1)");
+        assert_eq!(round_trip(Expr::pow(x(), Expr::int(-2))), "x^-2");
+    }
+
+    #[test]
+    fn subtraction_and_negation() {
+        let x = || Expr::var("x");
+        let y = || Expr::var("y");
+        let z = || Expr::var("z");
+        assert_eq!(round_trip((x() - y()) - z()), "x - y - z");
+        assert_eq!(round_trip(x() - (y() - z())), "x - (y - z)");
+        assert_eq!(round_trip((x() - y()) + z()), "x - y + z");
+        assert_eq!(round_trip(x() - Expr::frac(1, 2)), "x - 1/2");
+        assert_eq!(round_trip(x() + Expr::frac(-1, 2)), "x + -1/2");
+        assert_eq!(round_trip(Expr::int(-1) * x() + y()), "-x + y");
+        assert_eq!(round_trip(Expr::int(-2) * x() - y()), "-2*x - y");
+        assert_eq!(round_trip(Expr::int(-1) * (x() + y())), "-(x + y)");
+        assert_eq!(round_trip(x() * Expr::int(-1)), "x*-1");
+        assert_eq!(round_trip(Expr::func(FuncIdentifier::Sine, vec![Expr::int(-1) * x()]) * Expr::int(3)), "sin(-x)*3");
+    }
+
+    #[test]
+    fn parsed_text_round_trips() {
+        let inputs = ["sin(x)^2 + 3/4*pi - e", "a - (b - c)/d + f(x, -y)^-1", "-x^2^3 + abs(x - 1)*2/3", "2(y + 1)x / 0.5", "1/2/3"];
+        for input in inputs.iter() {
+            round_trip(parse(input).unwrap());
+        }
+
+        // Simplified trees use shapes the parser never builds, such as a trailing -1 coefficient
+        round_trip(parse("x - 3*y/4 + (x - 1)^2").unwrap().simplified().unwrap());
+    }
+}
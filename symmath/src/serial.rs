@@ -0,0 +1,355 @@
+use std::convert::TryFrom;
+use std::fmt;
+use bincode::Options;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use crate::expr::*;
+use crate::num::{Frac, Int};
+
+// Bumped whenever a stored tree could be read differently. Readers accept any version up to
+// their own, since appending Operator variants leaves older documents valid. Version 2 tags
+// binary integers so that they can grow past an i64.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum SerialError {
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    // The document was written by a newer schema than this build understands
+    UnsupportedVersion(u32),
+    // The document decoded, but not to a tree the rest of the crate can work with, such
+    // as a sum with no terms or a fraction over zero
+    InvalidTree(String)
+}
+
+impl fmt::Display for SerialError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerialError::Json(err) => write!(f, "invalid JSON expression: {}", err),
+            SerialError::Binary(err) => write!(f, "invalid binary expression: {}", err),
+            SerialError::UnsupportedVersion(version) => write!(f, "unsupported schema version {} (newest supported is {})", version, SCHEMA_VERSION),
+            SerialError::InvalidTree(reason) => write!(f, "invalid expression tree: {}", reason)
+        }
+    }
+}
+
+impl std::error::Error for SerialError {}
+
+impl From<serde_json::Error> for SerialError {
+    fn from(err: serde_json::Error) -> SerialError {
+        SerialError::Json(err)
+    }
+}
+
+impl From<bincode::Error> for SerialError {
+    fn from(err: bincode::Error) -> SerialError {
+        SerialError::Binary(err)
+    }
+}
+
+// JSON writes an integer that fits in an i64 as a plain number and anything larger as a
+// string of digits, which JSON readers would otherwise round
+#[derive(Serialize)]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IntText {
+    Small(i64),
+    Big(String)
+}
+
+// The binary format is not self-describing, so the two cases are tagged
+#[derive(Serialize)]
+#[derive(Deserialize)]
+enum IntBinary {
+    Small(i64),
+    Big(String)
+}
+
+impl Serialize for Int {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self.to_i64(), serializer.is_human_readable()) {
+            (Some(val), true) => IntText::Small(val).serialize(serializer),
+            (Some(val), false) => IntBinary::Small(val).serialize(serializer),
+            (None, true) => IntText::Big(self.to_string()).serialize(serializer),
+            (None, false) => IntBinary::Big(self.to_string()).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Int {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Int, D::Error> {
+        let digits = if deserializer.is_human_readable() {
+            match IntText::deserialize(deserializer)? {
+                IntText::Small(val) => return Ok(Int::from(val)),
+                IntText::Big(digits) => digits
+            }
+        } else {
+            match IntBinary::deserialize(deserializer)? {
+                IntBinary::Small(val) => return Ok(Int::from(val)),
+                IntBinary::Big(digits) => digits
+            }
+        };
+        return Int::parse(&digits).ok_or_else(|| de::Error::custom(format!("invalid integer '{}'", digits)));
+    }
+}
+
+// Fractions are written as their two parts and rebuilt through Frac::checked_new, so a
+// stored value that is not in canonical form, such as 2/-4, reads back as -1/2
+#[derive(Serialize)]
+#[derive(Deserialize)]
+#[serde(rename = "Frac")]
+struct FracParts {
+    num: Int,
+    denom: Int
+}
+
+impl Serialize for Frac {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FracParts {
+            num: self.num().clone(),
+            denom: self.denom().clone()
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Frac {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Frac, D::Error> {
+        let parts = FracParts::deserialize(deserializer)?;
+        return Frac::checked_new(parts.num, parts.denom).map_err(de::Error::custom);
+    }
+}
+
+// Version 1 binary documents stored both parts of a fraction as plain i64s. JSON needs no
+// such reader, since an i64 is written the same way in both versions.
+#[derive(Deserialize)]
+struct FracV1 {
+    num: i64,
+    denom: i64
+}
+
+#[derive(Deserialize)]
+enum OperatorV1 {
+    Frac(FracV1),
+    Add,
+    Mul,
+    Div,
+    Sub,
+    Pow,
+    Name(String),
+    Func(FuncIdentifier),
+    Const(Constant)
+}
+
+#[derive(Deserialize)]
+struct ExprV1 {
+    operator: OperatorV1,
+    elements: Option<Vec<ExprV1>>
+}
+
+#[derive(Deserialize)]
+struct DocumentV1 {
+    #[serde(rename = "version")]
+    _version: u32,
+    expr: ExprV1
+}
+
+impl TryFrom<ExprV1> for Expr {
+    type Error = SerialError;
+
+    fn try_from(expr: ExprV1) -> Result<Expr, SerialError> {
+        let operator = match expr.operator {
+            OperatorV1::Frac(f) => match Frac::checked_new(f.num, f.denom) {
+                Ok(f) => Operator::Frac(f),
+                Err(err) => return Err(SerialError::InvalidTree(err.to_string()))
+            },
+            OperatorV1::Add => Operator::Add,
+            OperatorV1::Mul => Operator::Mul,
+            OperatorV1::Div => Operator::Div,
+            OperatorV1::Sub => Operator::Sub,
+            OperatorV1::Pow => Operator::Pow,
+            OperatorV1::Name(name) => Operator::Name(name),
+            OperatorV1::Func(ident) => Operator::Func(ident),
+            OperatorV1::Const(c) => Operator::Const(c)
+        };
+        let elements = match expr.elements {
+            Some(els) => Some(els.into_iter().map(Expr::try_from).collect::<Result<Vec<_>, _>>()?),
+            None => None
+        };
+        return Ok(Expr::new(operator, elements));
+    }
+}
+
+// Decoding only checks the shape of each node, so the children are counted here before
+// anything that expects, say, a power to have two of them gets the tree
+fn check_tree(expr: &Expr) -> Result<(), SerialError> {
+    let count = expr.elements.as_ref().map(|els| els.len());
+    let (name, valid) = match &expr.operator {
+        Operator::Frac(_) | Operator::Name(_) | Operator::Const(_) => ("leaf", count.is_none()),
+        Operator::Add | Operator::Mul | Operator::Sub => ("sum, product or difference", matches!(count, Some(n) if n >= 1)),
+        Operator::Div | Operator::Pow => ("quotient or power", count == Some(2)),
+        Operator::Func(FuncIdentifier::Name(_)) => ("function", count.is_some()),
+        Operator::Func(_) => ("built-in function", count == Some(1))
+    };
+    if !valid {
+        let found = match count {
+            Some(n) => format!("{} children", n),
+            None => String::from("no child list")
+        };
+        return Err(SerialError::InvalidTree(format!("{} with {}", name, found)));
+    }
+    for child in expr.elements.iter().flatten() {
+        check_tree(child)?;
+    }
+    return Ok(());
+}
+
+// The version comes first so that it can be checked before the tree is decoded
+#[derive(Serialize)]
+struct DocumentRef<'a> {
+    version: u32,
+    expr: &'a Expr
+}
+
+#[derive(Deserialize)]
+struct Document {
+    // Already checked through Header, but binary decoding still has to step over it
+    #[serde(rename = "version")]
+    _version: u32,
+    expr: Expr
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32
+}
+
+fn check_version(version: u32) -> Result<(), SerialError> {
+    if version > SCHEMA_VERSION {
+        return Err(SerialError::UnsupportedVersion(version));
+    }
+    return Ok(());
+}
+
+// Variable-length integers keep small trees to a few bytes per node
+fn binary_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+impl Expr {
+    pub fn to_json(&self) -> Result<String, SerialError> {
+        let doc = DocumentRef {
+            version: SCHEMA_VERSION,
+            expr: self
+        };
+        return Ok(serde_json::to_string(&doc)?);
+    }
+
+    pub fn from_json(input: &str) -> Result<Expr, SerialError> {
+        let header: Header = serde_json::from_str(input)?;
+        check_version(header.version)?;
+        let doc: Document = serde_json::from_str(input)?;
+        check_tree(&doc.expr)?;
+        return Ok(doc.expr);
+    }
+
+    pub fn to_binary(&self) -> Result<Vec<u8>, SerialError> {
+        let doc = DocumentRef {
+            version: SCHEMA_VERSION,
+            expr: self
+        };
+        return Ok(binary_options().serialize(&doc)?);
+    }
+
+    pub fn from_binary(input: &[u8]) -> Result<Expr, SerialError> {
+        let version: u32 = binary_options().allow_trailing_bytes().deserialize(input)?;
+        check_version(version)?;
+        if version == 1 {
+            let doc: DocumentV1 = binary_options().deserialize(input)?;
+            let expr = Expr::try_from(doc.expr)?;
+            check_tree(&expr)?;
+            return Ok(expr);
+        }
+        let doc: Document = binary_options().deserialize(input)?;
+        check_tree(&doc.expr)?;
+        return Ok(doc.expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+    use crate::parse::parse;
+    use super::{SerialError, SCHEMA_VERSION};
+
+    #[test]
+    fn json_schema() {
+        let expr = Expr::frac(1, 2) * Expr::func(FuncIdentifier::Sine, vec![Expr::var("x")]) + Expr::pi();
+        assert_eq!(expr.to_json().unwrap(), concat!(
+            r#"{"version":2,"expr":{"operator":"add","elements":["#,
+            r#"{"operator":"mul","elements":[{"operator":{"frac":{"num":1,"denom":2}},"elements":null},"#,
+            r#"{"operator":{"func":"sine"},"elements":[{"operator":{"name":"x"},"elements":null}]}]},"#,
+            r#"{"operator":{"const":"pi"},"elements":null}]}}"#
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        let expr = parse("f(x, -y)^-1 - abs(3/4*e) + cos(x)/tan(x)").unwrap();
+        assert_eq!(Expr::from_json(&expr.to_json().unwrap()).unwrap(), expr);
+
+        let bytes = expr.to_binary().unwrap();
+        assert_eq!(bytes[0] as u32, SCHEMA_VERSION);
+        assert_eq!(Expr::from_binary(&bytes).unwrap(), expr);
+
+        let big = parse("x*(1/123456789012345678901234567890)").unwrap();
+        let json = big.to_json().unwrap();
+        assert!(json.contains(r#"{"num":1,"denom":"123456789012345678901234567890"}"#), "{}", json);
+        assert_eq!(Expr::from_json(&json).unwrap(), big);
+        assert_eq!(Expr::from_binary(&big.to_binary().unwrap()).unwrap(), big);
+
+        let json = r#"{"version":1,"expr":{"operator":{"frac":{"num":2,"denom":-4}},"elements":null}}"#;
+        assert_eq!(Expr::from_json(json).unwrap(), Expr::frac(-1, 2));
+    }
+
+    #[test]
+    fn version_one_documents() {
+        // -3/4 + x, with the fraction's parts as untagged zigzag varints
+        let bytes = [1, 1, 1, 2, 0, 5, 8, 0, 6, 1, b'x', 0];
+        assert_eq!(Expr::from_binary(&bytes).unwrap(), Expr::new(Operator::Add, Some(vec![Expr::frac(-3, 4), Expr::var("x")])));
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let json = r#"{"version":3,"expr":{"operator":{"matrix":[2,2]},"elements":null}}"#;
+        assert!(matches!(Expr::from_json(json), Err(SerialError::UnsupportedVersion(3))));
+
+        let mut bytes = Expr::var("x").to_binary().unwrap();
+        bytes[0] = 3;
+        assert!(matches!(Expr::from_binary(&bytes), Err(SerialError::UnsupportedVersion(3))));
+        assert!(matches!(Expr::from_binary(&bytes[..2]), Err(SerialError::UnsupportedVersion(3))));
+        assert!(matches!(Expr::from_json("{"), Err(SerialError::Json(_))));
+    }
+
+    #[test]
+    fn malformed_trees_are_rejected() {
+        let inputs = [
+            r#"{"operator":"add","elements":null}"#,
+            r#"{"operator":"add","elements":[]}"#,
+            r#"{"operator":{"func":"sine"},"elements":[]}"#,
+            r#"{"operator":"pow","elements":[{"operator":{"name":"x"},"elements":null}]}"#,
+            r#"{"operator":{"name":"x"},"elements":[{"operator":{"name":"y"},"elements":null}]}"#,
+            r#"{"operator":"mul","elements":[{"operator":{"func":{"name":"f"}},"elements":null}]}"#
+        ];
+        for expr in inputs.iter() {
+            let json = format!(r#"{{"version":2,"expr":{}}}"#, expr);
+            assert!(matches!(Expr::from_json(&json), Err(SerialError::InvalidTree(_))), "{}", expr);
+        }
+        let json = r#"{"version":2,"expr":{"operator":{"frac":{"num":1,"denom":0}},"elements":null}}"#;
+        assert!(matches!(Expr::from_json(json), Err(SerialError::Json(_))));
+
+        // sin() and a fraction over zero, in version 1 binary
+        assert!(matches!(Expr::from_binary(&[1, 7, 1, 1, 0]), Err(SerialError::InvalidTree(_))));
+        assert!(matches!(Expr::from_binary(&[1, 0, 2, 0, 0]), Err(SerialError::InvalidTree(_))));
+        let bytes = Expr::new(Operator::Sub, Some(Vec::new())).to_binary().unwrap();
+        assert!(matches!(Expr::from_binary(&bytes), Err(SerialError::InvalidTree(_))));
+    }
+}
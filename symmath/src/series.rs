@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::expr::*;
+use crate::num::{Frac, Int, NumError};
+
+const ESCALATION_STEPS: u32 = 8;
+
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum SeriesError {
+    Num(NumError),
+    // Such as exp(1/x) or x^(1/2) at 0, which have no Laurent expansion there
+    Singular,
+    // No leading term turned up, as for an expression that cancels to zero
+    Indeterminate,
+    // An even root of a leading coefficient whose sign cannot be worked out, such as
+    // x^(1/2) about a symbolic point p, which is only a power series when p > 0
+    UnknownSign
+}
+
+impl fmt::Display for SeriesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SeriesError::Num(err) => write!(f, "{}", err),
+            SeriesError::Singular => write!(f, "no Laurent series exists at this point"),
+            SeriesError::Indeterminate => write!(f, "could not find a leading term"),
+            SeriesError::UnknownSign => write!(f, "could not tell the sign of the leading coefficient")
+        }
+    }
+}
+
+impl std::error::Error for SeriesError {}
+
+impl From<NumError> for SeriesError {
+    fn from(err: NumError) -> SeriesError {
+        SeriesError::Num(err)
+    }
+}
+
+enum Failure {
+    Series(SeriesError),
+    // Too few terms were carried to decide, such as the leading term of a divisor
+    Imprecise
+}
+
+impl From<SeriesError> for Failure {
+    fn from(err: SeriesError) -> Failure {
+        Failure::Series(err)
+    }
+}
+
+impl From<NumError> for Failure {
+    fn from(err: NumError) -> Failure {
+        Failure::Series(SeriesError::Num(err))
+    }
+}
+
+fn sum(mut terms: Vec<Expr>) -> Expr {
+    match terms.len() {
+        0 => Expr::int(0),
+        1 => terms.pop().unwrap(),
+        _ => Expr::new(Operator::Add, Some(terms))
+    }
+}
+
+fn known_positive(expr: &Expr) -> bool {
+    matches!(expr.eval_f64(&HashMap::new()), Ok(val) if val > 0.0)
+}
+
+fn factorial(n: i64) -> Int {
+    let mut out = Int::from(1);
+    for k in 2..=n {
+        out = &out * &Int::from(k);
+    }
+    return out;
+}
+
+// The series coeffs[k] h^(start + k) + O(h^order) in h = var - point, where coefficients
+// past the end of `coeffs` are zero. Leading zeros are dropped as they are found, so a
+// series with no coefficients is known to be zero up to its order.
+#[derive(Clone)]
+#[derive(Debug)]
+struct Terms {
+    start: i64,
+    coeffs: Vec<Expr>,
+    order: i64
+}
+
+impl Terms {
+    fn new(start: i64, coeffs: Vec<Expr>, order: i64) -> Result<Terms, NumError> {
+        let mut out = Terms { start, coeffs: Vec::new(), order };
+        for coeff in coeffs.into_iter().take((order - start).max(0) as usize) {
+            let coeff = coeff.simplified()?;
+            if out.coeffs.is_empty() && coeff == Expr::int(0) {
+                out.start += 1;
+                continue;
+            }
+            out.coeffs.push(coeff);
+        }
+        if out.coeffs.is_empty() {
+            out.start = order;
+        }
+        return Ok(out);
+    }
+
+    fn zero(order: i64) -> Terms {
+        Terms { start: order, coeffs: Vec::new(), order }
+    }
+
+    fn constant(value: Expr, order: i64) -> Result<Terms, NumError> {
+        Terms::new(0, vec![value], order)
+    }
+
+    fn coeff(&self, power: i64) -> Expr {
+        if power < self.start {
+            return Expr::int(0);
+        }
+        return self.coeffs.get((power - self.start) as usize).cloned().unwrap_or_else(|| Expr::int(0));
+    }
+
+    fn shift(mut self, by: i64) -> Terms {
+        self.start += by;
+        self.order += by;
+        return self;
+    }
+
+    fn truncate(mut self, order: i64) -> Terms {
+        self.coeffs.truncate((order - self.start).max(0) as usize);
+        self.order = order;
+        if self.coeffs.is_empty() {
+            self.start = order;
+        }
+        return self;
+    }
+
+    fn scale(&self, factor: &Expr) -> Result<Terms, NumError> {
+        Terms::new(self.start, self.coeffs.iter().map(|coeff| coeff.clone() * factor.clone()).collect(), self.order)
+    }
+
+    fn add(&self, other: &Terms) -> Result<Terms, NumError> {
+        let start = self.start.min(other.start);
+        let order = self.order.min(other.order);
+        Terms::new(start, (start..order).map(|n| self.coeff(n) + other.coeff(n)).collect(), order)
+    }
+
+    fn mul(&self, other: &Terms) -> Result<Terms, NumError> {
+        let start = self.start + other.start;
+        let order = (self.start + other.order).min(other.start + self.order);
+        let mut coeffs = Vec::new();
+        for n in start..order {
+            let mut terms = Vec::new();
+            for (idx, coeff) in self.coeffs.iter().enumerate() {
+                let power = n - self.start - idx as i64;
+                if power >= other.start && power < other.start + other.coeffs.len() as i64 {
+                    terms.push(coeff.clone() * other.coeff(power));
+                }
+            }
+            coeffs.push(sum(terms));
+        }
+        return Terms::new(start, coeffs, order);
+    }
+
+    // b_0 = 1 / a_0 and b_n = -(a_1 b_(n-1) + ... + a_n b_0) / a_0
+    fn recip(&self) -> Result<Terms, Failure> {
+        if self.coeffs.is_empty() {
+            return Err(Failure::Imprecise);
+        }
+        let len = self.order - self.start;
+        let inv = (Expr::int(1) / self.coeffs[0].clone()).simplified()?;
+        let mut coeffs = vec![inv.clone()];
+        for n in 1..len {
+            let terms = (1..=n).map(|k| self.coeff(self.start + k) * coeffs[(n - k) as usize].clone()).collect();
+            coeffs.push((sum(terms) * inv.clone() * Expr::int(-1)).simplified()?);
+        }
+        return Ok(Terms::new(-self.start, coeffs, len - self.start)?);
+    }
+
+    fn powi(&self, n: i64) -> Result<Terms, Failure> {
+        let mut base = if n < 0 { self.recip()? } else { self.clone() };
+        // Starting from the base rather than a truncated 1 keeps every order it has
+        let mut out: Option<Terms> = None;
+        let mut n = n.unsigned_abs();
+        while n > 0 {
+            if n % 2 == 1 {
+                out = Some(match out {
+                    Some(out) => out.mul(&base)?,
+                    None => base.clone()
+                });
+            }
+            n /= 2;
+            if n > 0 {
+                base = base.mul(&base)?;
+            }
+        }
+        return match out {
+            Some(out) => Ok(out),
+            None => Ok(Terms::constant(Expr::int(1), self.order.max(0))?)
+        };
+    }
+
+    // a_0^c h^(sc) (1 + u)^c for a series a_0 h^s (1 + u), expanding the last factor with
+    // the binomial series. There is a branch point unless sc is a whole number. Even roots
+    // also need a_0 h^s positive, and an even s makes h^s = |h|^s, so (x^2)^(1/2) is |x|
+    // rather than x and has no series at 0. A symbolic a_0 may be either sign, which is
+    // reported apart from a real branch point.
+    fn powf(&self, exponent: &Frac) -> Result<Terms, Failure> {
+        if self.coeffs.is_empty() {
+            return Err(Failure::Imprecise);
+        }
+        let shift = Frac::new(self.start, 1) * exponent.clone();
+        if !shift.is_int() {
+            return Err(SeriesError::Singular.into());
+        }
+        let even = |n: &Int| n % &Int::from(2) == 0;
+        let even_root = even(exponent.denom());
+        let principal = if self.start % 2 == 0 { even(shift.num()) } else { !even_root };
+        if !principal {
+            return Err(SeriesError::Singular.into());
+        }
+        if self.start % 2 == 0 && even_root && !known_positive(&self.coeffs[0]) {
+            if known_positive(&(self.coeffs[0].clone() * Expr::int(-1))) {
+                return Err(SeriesError::Singular.into());
+            }
+            return Err(SeriesError::UnknownSign.into());
+        }
+        let len = self.order - self.start;
+        let inv = (Expr::int(1) / self.coeffs[0].clone()).simplified()?;
+        let unit = Terms::new(0, self.coeffs.iter().map(|coeff| coeff.clone() * inv.clone()).collect(), len)?;
+        let u = unit.add(&Terms::constant(Expr::int(-1), len)?)?;
+
+        let mut out = Terms::zero(len);
+        let mut power = Terms::constant(Expr::int(1), len)?;
+        let mut binomial = Frac::new(1, 1);
+        for k in 0..len {
+            out = out.add(&power.scale(&Expr::frac_cpy(&binomial))?)?;
+            power = power.mul(&u)?;
+            binomial = binomial * (exponent.clone() - Frac::new(k, 1)) / Frac::new(k + 1, 1);
+        }
+        // A leading 1, as in (1 + x)^(1/2), needs no power taken
+        let lead = if self.coeffs[0] == Expr::int(1) { Expr::int(1) } else { Expr::pow(self.coeffs[0].clone(), Expr::frac_cpy(exponent)).simplified()? };
+        return Ok(out.scale(&lead)?.shift(shift.num().to_i64().ok_or(NumError::Overflow)?));
+    }
+
+    // Splits off the constant term c, leaving w with no constant or negative powers. A
+    // negative power is an essential singularity of exp, sin and cos.
+    fn split_constant(&self) -> Result<(Expr, Terms), Failure> {
+        if !self.coeffs.is_empty() && self.start < 0 {
+            return Err(SeriesError::Singular.into());
+        }
+        if self.order <= 0 {
+            return Err(Failure::Imprecise);
+        }
+        let constant = self.coeff(0);
+        let rest = self.add(&Terms::constant(constant.clone() * Expr::int(-1), self.order)?)?;
+        return Ok((constant, rest));
+    }
+
+    // e^(c + w) = e^c (1 + w + w^2/2 + ...)
+    fn exp(&self) -> Result<Terms, Failure> {
+        let (constant, rest) = self.split_constant()?;
+        let mut out = Terms::zero(self.order);
+        let mut power = Terms::constant(Expr::int(1), self.order)?;
+        for k in 0..self.order {
+            out = out.add(&power.scale(&Expr::frac(1, factorial(k)))?)?;
+            power = power.mul(&rest)?;
+        }
+        // e^0 is written 1 here, as it is in sin_cos
+        let scale = if constant == Expr::int(0) { Expr::int(1) } else { Expr::pow(Expr::e(), constant).simplified()? };
+        return Ok(out.scale(&scale)?);
+    }
+
+    // sin(c + w) = sin(c) cos(w) + cos(c) sin(w) and cos(c + w) = cos(c) cos(w) - sin(c) sin(w)
+    fn sin_cos(&self) -> Result<(Terms, Terms), Failure> {
+        let (constant, rest) = self.split_constant()?;
+        let mut sin = Terms::zero(self.order);
+        let mut cos = Terms::zero(self.order);
+        let mut power = Terms::constant(Expr::int(1), self.order)?;
+        for k in 0..self.order {
+            let sign = if k % 4 < 2 { 1 } else { -1 };
+            let term = power.scale(&Expr::frac(sign, factorial(k)))?;
+            if k % 2 == 0 {
+                cos = cos.add(&term)?;
+            } else {
+                sin = sin.add(&term)?;
+            }
+            power = power.mul(&rest)?;
+        }
+        // About 0 the constant term is usually 0, and cos(0) is not folded by simplify
+        let (sin_c, cos_c) = if constant == Expr::int(0) {
+            (Expr::int(0), Expr::int(1))
+        } else {
+            (Expr::func(FuncIdentifier::Sine, vec![constant.clone()]), Expr::func(FuncIdentifier::Cosine, vec![constant]))
+        };
+        let sin_out = cos.scale(&sin_c)?.add(&sin.scale(&cos_c)?)?;
+        let cos_out = cos.scale(&cos_c)?.add(&sin.scale(&(sin_c * Expr::int(-1)))?)?;
+        return Ok((sin_out, cos_out));
+    }
+}
+
+// A truncated Taylor or Laurent series in powers of var - point
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Series {
+    var: String,
+    point: Expr,
+    terms: Terms
+}
+
+impl Series {
+    // The lowest power with a nonzero coefficient, negative for a pole
+    pub fn start(&self) -> i64 {
+        self.terms.start
+    }
+
+    // The power in the order term, the first one not known
+    pub fn order(&self) -> i64 {
+        self.terms.order
+    }
+
+    pub fn coefficient(&self, power: i64) -> Expr {
+        assert!(power < self.terms.order, "x^{} is beyond the order of the series", power);
+        self.terms.coeff(power)
+    }
+
+    fn basis(&self, power: i64) -> Option<Expr> {
+        let offset = if self.point == Expr::int(0) { Expr::var(&self.var) } else { Expr::var(&self.var) + negate(self.point.clone()) };
+        match power {
+            0 => None,
+            1 => Some(offset),
+            _ => Some(Expr::pow(offset, Expr::int(power)))
+        }
+    }
+
+    fn polynomial_terms(&self) -> Vec<Expr> {
+        let mut terms = Vec::new();
+        for (idx, coeff) in self.terms.coeffs.iter().enumerate() {
+            if *coeff == Expr::int(0) {
+                continue;
+            }
+            terms.push(match self.basis(self.terms.start + idx as i64) {
+                Some(basis) if *coeff == Expr::int(1) => basis,
+                Some(basis) => basis * coeff.clone(),
+                None => coeff.clone()
+            });
+        }
+        return terms;
+    }
+
+    // The series without its order term, ready to evaluate or compile
+    pub fn polynomial(&self) -> Result<Expr, NumError> {
+        sum(self.polynomial_terms()).simplified()
+    }
+
+    // The series followed by O((var - point)^order)
+    pub fn to_expr(&self) -> Result<Expr, NumError> {
+        let mut terms = self.polynomial_terms();
+        let bound = self.basis(self.terms.order).unwrap_or_else(|| Expr::int(1));
+        terms.push(Expr::func(FuncIdentifier::Name(String::from("O")), vec![bound]));
+        sum(terms).simplified()
+    }
+}
+
+impl Expr {
+    // The series to O(h^order), where the rules below may lose a few orders. Composition
+    // covers arithmetic, powers, exp, sin, cos and tan; anything else is expanded from its
+    // derivatives at the point, which assumes it is analytic there.
+    fn terms(&self, var: &str, point: &Expr, order: i64) -> Result<Terms, Failure> {
+        if !self.depends_on(var) {
+            return Ok(Terms::constant(self.clone(), order)?);
+        }
+        match &self.operator {
+            Operator::Name(_) => Ok(Terms::new(0, vec![point.clone(), Expr::int(1)], order)?),
+            Operator::Add => {
+                let mut out = Terms::constant(Expr::int(0), order)?;
+                for child in self.children().iter() {
+                    out = out.add(&child.terms(var, point, order)?)?;
+                }
+                Ok(out)
+            },
+            Operator::Sub => {
+                let els = self.children();
+                let mut out = els[0].terms(var, point, order)?;
+                for child in els[1..].iter() {
+                    out = out.add(&child.terms(var, point, order)?.scale(&Expr::int(-1))?)?;
+                }
+                Ok(out)
+            },
+            Operator::Mul => {
+                let mut out = Terms::constant(Expr::int(1), order)?;
+                for child in self.children().iter() {
+                    out = out.mul(&child.terms(var, point, order)?)?;
+                }
+                Ok(out)
+            },
+            Operator::Div => {
+                let els = self.children();
+                let num = els[0].terms(var, point, order)?;
+                Ok(num.mul(&els[1].terms(var, point, order)?.recip()?)?)
+            },
+            Operator::Pow => {
+                let els = self.children();
+                let (u, v) = (&els[0], &els[1]);
+                if let Operator::Frac(f) = &v.operator {
+                    let base = u.terms(var, point, order)?;
+                    if f.is_int() {
+                        return base.powi(f.num().to_i64().ok_or(NumError::Overflow)?);
+                    }
+                    return base.powf(f);
+                }
+                if let Operator::Const(Constant::E) = u.operator {
+                    return v.terms(var, point, order)?.exp();
+                }
+                // c^v = e^(v ln(c))
+                if !u.depends_on(var) {
+                    let ln = Expr::func(FuncIdentifier::Name(String::from("ln")), vec![u.clone()]);
+                    return v.terms(var, point, order)?.scale(&ln)?.exp();
+                }
+                self.taylor(var, point, order)
+            },
+            Operator::Func(ident) => {
+                let args = self.children();
+                if args.len() != 1 {
+                    return self.taylor(var, point, order);
+                }
+                match ident {
+                    FuncIdentifier::Sine => Ok(args[0].terms(var, point, order)?.sin_cos()?.0),
+                    FuncIdentifier::Cosine => Ok(args[0].terms(var, point, order)?.sin_cos()?.1),
+                    FuncIdentifier::Tangent => {
+                        let (sin, cos) = args[0].terms(var, point, order)?.sin_cos()?;
+                        Ok(sin.mul(&cos.recip()?)?)
+                    },
+                    FuncIdentifier::Name(name) if name == "exp" => args[0].terms(var, point, order)?.exp(),
+                    // abs is only smooth where its argument keeps one sign
+                    FuncIdentifier::Abs => {
+                        let arg = args[0].terms(var, point, order)?;
+                        if arg.coeffs.is_empty() {
+                            return Err(Failure::Imprecise);
+                        }
+                        if arg.start != 0 {
+                            return Err(SeriesError::Singular.into());
+                        }
+                        if known_positive(&arg.coeffs[0]) {
+                            return Ok(arg);
+                        }
+                        if known_positive(&(arg.coeffs[0].clone() * Expr::int(-1))) {
+                            return Ok(arg.scale(&Expr::int(-1))?);
+                        }
+                        self.taylor(var, point, order)
+                    },
+                    _ => self.taylor(var, point, order)
+                }
+            },
+            // Leaves other than names never depend on a variable
+            Operator::Frac(_) | Operator::Const(_) => unreachable!()
+        }
+    }
+
+    // f(p) + f'(p) h + f''(p) h^2 / 2 + ...
+    fn taylor(&self, var: &str, point: &Expr, order: i64) -> Result<Terms, Failure> {
+        let mut derivative = self.clone();
+        let mut coeffs = Vec::new();
+        for k in 0..order.max(0) {
+            if k > 0 {
+                derivative = derivative.diff(var)?;
+            }
+            coeffs.push(derivative.substitute(var, point) * Expr::frac(1, factorial(k)));
+        }
+        return Ok(Terms::new(0, coeffs, order)?);
+    }
+
+    // Expands about `point` up to but not including (var - point)^order, carrying extra
+    // terms through the expansion wherever divisions by powers of var - point use them up
+    pub fn series_terms(&self, var: &str, point: &Expr, order: i64) -> Result<Series, SeriesError> {
+        let mut pad = 0;
+        for _ in 0..ESCALATION_STEPS {
+            match self.terms(var, point, order + pad) {
+                Ok(terms) if terms.order >= order => {
+                    return Ok(Series { var: String::from(var), point: point.clone(), terms: terms.truncate(order) });
+                },
+                Ok(terms) => pad += order - terms.order,
+                Err(Failure::Series(err)) => return Err(err),
+                Err(Failure::Imprecise) => pad = 2 * pad + 1
+            }
+        }
+        return Err(SeriesError::Indeterminate);
+    }
+
+    // The series as a single expression ending in an order term, such as
+    // x - x^3/6 + O(x^5) for sin(x) about 0 to order 5
+    pub fn series(&self, var: &str, point: &Expr, order: i64) -> Result<Expr, SeriesError> {
+        Ok(self.series_terms(var, point, order)?.to_expr()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::expr::*;
+    use crate::parse::parse;
+    use super::SeriesError;
+
+    fn coefficients(input: &str, point: Expr, order: i64) -> (i64, Vec<Expr>) {
+        let series = parse(input).unwrap().series_terms("x", &point, order).unwrap();
+        assert_eq!(series.order(), order);
+        (series.start(), (series.start()..order).map(|power| series.coefficient(power)).collect())
+    }
+
+    fn fracs(values: &[(i64, i64)]) -> Vec<Expr> {
+        values.iter().map(|(num, denom)| Expr::frac(*num, *denom)).collect()
+    }
+
+    #[test]
+    fn taylor() {
+        assert_eq!(coefficients("sin(x)", Expr::int(0), 6), (1, fracs(&[(1, 1), (0, 1), (-1, 6), (0, 1), (1, 120)])));
+        assert_eq!(coefficients("tan(x)", Expr::int(0), 6), (1, fracs(&[(1, 1), (0, 1), (1, 3), (0, 1), (2, 15)])));
+        assert_eq!(coefficients("1/(1 - x)", Expr::int(0), 4), (0, fracs(&[(1, 1), (1, 1), (1, 1), (1, 1)])));
+        assert_eq!(coefficients("(1 + x)^(1/2)", Expr::int(0), 4), (0, fracs(&[(1, 1), (1, 2), (-1, 8), (1, 16)])));
+        assert_eq!(coefficients("exp(x^2)*cos(x)", Expr::int(0), 5), (0, fracs(&[(1, 1), (0, 1), (1, 2), (0, 1), (1, 24)])));
+        assert_eq!(coefficients("x^3", Expr::int(2), 5), (0, fracs(&[(8, 1), (12, 1), (6, 1), (1, 1), (0, 1)])));
+
+        let f = |name: &str, arg: Expr| Expr::func(FuncIdentifier::Name(String::from(name)), vec![arg]);
+        assert_eq!(coefficients("f(x)", Expr::var("p"), 3).1, vec![f("f", Expr::var("p")), f("f'", Expr::var("p")), f("f''", Expr::var("p")) * Expr::frac(1, 2)]);
+    }
+
+    #[test]
+    fn laurent() {
+        assert_eq!(coefficients("1/sin(x)", Expr::int(0), 4), (-1, fracs(&[(1, 1), (0, 1), (1, 6), (0, 1), (7, 360)])));
+        assert_eq!(coefficients("cos(x)/x^2", Expr::int(0), 1), (-2, fracs(&[(1, 1), (0, 1), (-1, 2)])));
+        assert_eq!(coefficients("1/(x^2 - 1)", Expr::int(1), 1), (-1, fracs(&[(1, 2), (-1, 4)])));
+
+        assert_eq!(parse("e^(1/x)").unwrap().series("x", &Expr::int(0), 3), Err(SeriesError::Singular));
+        assert_eq!(parse("x^(1/2)").unwrap().series("x", &Expr::int(0), 3), Err(SeriesError::Singular));
+        for input in ["(x^2)^(1/2)", "(x^2)^(3/2)", "(x^3)^(1/2)", "(x - 1)^(1/2)", "abs(x)", "abs(sin(x))"].iter() {
+            assert_eq!(parse(input).unwrap().series("x", &Expr::int(0), 3), Err(SeriesError::Singular), "{}", input);
+        }
+        assert_eq!(coefficients("(x^4 + x^5)^(1/2)", Expr::int(0), 4), (2, fracs(&[(1, 1), (1, 2)])));
+        assert_eq!(coefficients("(x^3 + x^4)^(1/3)", Expr::int(0), 3), (1, fracs(&[(1, 1), (1, 3)])));
+        assert_eq!(coefficients("(x^3)^(2/3)", Expr::int(0), 3), (2, fracs(&[(1, 1)])));
+        assert_eq!(coefficients("abs(x - 2)", Expr::int(0), 3), (0, fracs(&[(2, 1), (-1, 1), (0, 1)])));
+
+        // Real about 4 and not about -4, but about p it depends on the sign of p
+        assert_eq!(coefficients("x^(1/2)", Expr::int(4), 2), (0, fracs(&[(2, 1), (1, 4)])));
+        assert_eq!(parse("x^(1/2)").unwrap().series("x", &Expr::int(-4), 3), Err(SeriesError::Singular));
+        assert_eq!(parse("x^(1/2)").unwrap().series("x", &Expr::var("p"), 3), Err(SeriesError::UnknownSign));
+        assert_eq!(parse("(x^4*p)^(1/2)").unwrap().series("x", &Expr::int(0), 3), Err(SeriesError::UnknownSign));
+    }
+
+    #[test]
+    fn display_and_accuracy() {
+        assert_eq!(parse("sin(x)").unwrap().series("x", &Expr::int(0), 6).unwrap().to_string(), "x + x^3*(-1/6) + x^5*(1/120) + O(x^6)");
+
+        let expr = parse("e^(sin(x)) / (1 + x^2)").unwrap();
+        let polynomial = expr.series_terms("x", &Expr::int(0), 12).unwrap().polynomial().unwrap();
+        for x in [-0.05, 0.02, 0.05].iter() {
+            let env: HashMap<String, f64> = [(String::from("x"), *x)].iter().cloned().collect();
+            let (exact, approx) = (expr.eval_f64(&env).unwrap(), polynomial.eval_f64(&env).unwrap());
+            assert!((exact - approx).abs() < 1e-12, "{}: {} vs {}", x, exact, approx);
+        }
+    }
+}
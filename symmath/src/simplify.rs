@@ -1,5 +1,7 @@
+use std::collections::HashMap;
 use crate::expr::*;
 use crate::num;
+use crate::num::NumError;
 
 pub struct SimplifcationOpts {
     pub expand: bool,
@@ -29,51 +31,421 @@ impl SimplifcationOpts {
     }
 }
 
-impl Expr {
-    pub fn simplify(&mut self, opts: &SimplifcationOpts) {
-        let mut a = true;
-        let mut b = false;
-        while a || b {
-            a = self.simplify_impl(opts);
-            b = self.flatten_impl(opts);
-            // println!("{}", self.to_ascii());
+// Whether the subtree is built only from fractions and i, so that it has an exact
+// Gaussian rational value
+fn is_gaussian(expr: &Expr) -> bool {
+    match &expr.operator {
+        Operator::Frac(_) | Operator::Const(Constant::I) => true,
+        Operator::Add | Operator::Mul | Operator::Sub | Operator::Div => expr.elements.as_ref().expect("Node has no children").iter().all(is_gaussian),
+        Operator::Pow => {
+            let els = expr.elements.as_ref().expect("Pow has no children");
+            let int_exponent = match &els[1].operator {
+                Operator::Frac(f) => f.is_int(),
+                _ => false
+            };
+            is_gaussian(&els[0]) && int_exponent
+        },
+        _ => false
+    }
+}
+
+fn count_i(expr: &Expr) -> usize {
+    match &expr.operator {
+        Operator::Const(Constant::I) => 1,
+        _ => expr.elements.as_ref().map_or(0, |els| els.iter().map(count_i).sum())
+    }
+}
+
+// A Gaussian subtree is already in the form gaussian_expr builds, give or take the
+// shapes the rest of simplify gives it, unless i occurs more than once or is raised to a
+// power or divided by
+fn needs_fold(expr: &Expr) -> bool {
+    if count_i(expr) >= 2 {
+        return true;
+    }
+    match &expr.operator {
+        Operator::Pow => count_i(&expr.elements.as_ref().unwrap()[0]) > 0,
+        Operator::Div => count_i(&expr.elements.as_ref().unwrap()[1]) > 0,
+        _ => expr.elements.as_ref().is_some_and(|els| els.iter().any(needs_fold))
+    }
+}
+
+// The exact value, or None when it holds a power too large to work out, as frac_pow
+// leaves such powers alone too
+fn gaussian_value(expr: &Expr) -> Result<Option<num::Gaussian>, NumError> {
+    let args = match &expr.elements {
+        Some(els) => match els.iter().map(gaussian_value).collect::<Result<Vec<_>, NumError>>()?.into_iter().collect::<Option<Vec<num::Gaussian>>>() {
+            Some(args) => args,
+            None => return Ok(None)
+        },
+        None => Vec::new()
+    };
+    let mut args = args.into_iter();
+    let val = match &expr.operator {
+        Operator::Frac(f) => num::Gaussian::real(f.clone()),
+        Operator::Const(Constant::I) => num::Gaussian::i(),
+        Operator::Add | Operator::Mul | Operator::Sub => {
+            let mut val = args.next().unwrap();
+            for next in args {
+                val = match expr.operator {
+                    Operator::Add => val.checked_add(next)?,
+                    Operator::Mul => val.checked_mul(next)?,
+                    _ => val.checked_sub(next)?
+                };
+            }
+            val
+        },
+        Operator::Div => {
+            let num = args.next().unwrap();
+            num.checked_div(args.next().unwrap())?
+        },
+        Operator::Pow => {
+            let base = args.next().unwrap();
+            let exponent = match &expr.children()[1].operator {
+                Operator::Frac(f) => f.num(),
+                _ => unreachable!()
+            };
+            match exponent.abs().to_i64() {
+                Some(power) if base.bits().saturating_mul(power as u64) <= MAX_POWER_BITS => base.checked_pow(exponent)?,
+                _ => return Ok(None)
+            }
+        },
+        _ => unreachable!()
+    };
+    return Ok(Some(val));
+}
+
+// Builds im*i + re, leaving out whichever parts are zero
+fn gaussian_expr(val: num::Gaussian) -> Expr {
+    if val.is_real() {
+        return Expr::new_empty(Operator::Frac(val.re));
+    }
+    let imaginary = if val.im.is_one() { Expr::i() } else { Expr::i() * Expr::new_empty(Operator::Frac(val.im)) };
+    if val.re.is_zero() {
+        return imaginary;
+    }
+    return imaginary + Expr::new_empty(Operator::Frac(val.re));
+}
+
+// Whether infinity appears anywhere in the tree. Such terms never cancel or combine, since
+// inf - inf and inf/inf have no value.
+fn mentions_infinity(expr: &Expr) -> bool {
+    matches!(expr.operator, Operator::Const(Constant::Infinity)) || expr.elements.as_ref().is_some_and(|els| els.iter().any(mentions_infinity))
+}
+
+// Splits a term into its numeric coefficient and the rest, so x*y*3 gives 3 and x*y.
+// Nested products, such as the (x*2)*-1 that a subtraction becomes, are split through.
+fn split_coefficient(term: Expr) -> (num::Frac, Expr) {
+    if let Operator::Mul = term.operator {
+        let mut coeff = num::Frac::new(1, 1);
+        let mut rest = Vec::new();
+        for factor in term.elements.expect("Mul has no children").into_iter() {
+            match factor.operator {
+                Operator::Frac(f) => coeff = coeff * f,
+                Operator::Mul => {
+                    let (inner, factor) = split_coefficient(factor);
+                    coeff = coeff * inner;
+                    match factor.operator {
+                        Operator::Mul => rest.extend(factor.elements.expect("Mul has no children")),
+                        _ => rest.push(factor)
+                    }
+                },
+                _ => rest.push(factor)
+            }
         }
+        let rest = if rest.len() == 1 { rest.pop().unwrap() } else { Expr::new(Operator::Mul, Some(rest)) };
+        return (coeff, rest);
     }
+    return (num::Frac::new(1, 1), term);
+}
 
-    fn mul_frac(mut self, frac: num::Frac, _opts: &SimplifcationOpts) -> (Self, bool) {
-        match self.operator {
-            Operator::Add => {
-                let mut new_elements = Vec::new();
+// The largest power worked out exactly, in bits of numerator and denominator, so that
+// something like 7^(10^9) stays as it is written
+pub(crate) const MAX_POWER_BITS: u64 = 1 << 16;
+
+// base^exponent when it is a fraction: integer powers, and roots of positive fractions
+// whose numerator and denominator are perfect powers, such as (4/9)^(3/2) = 8/27
+fn frac_pow(base: &num::Frac, exponent: &num::Frac) -> Result<Option<num::Frac>, NumError> {
+    let root = match exponent.denom().to_i64() {
+        Some(1) => base.clone(),
+        Some(n) if n <= u32::MAX as i64 && !base.is_negative() => {
+            let num = base.num().exact_root(n as u32);
+            let denom = base.denom().exact_root(n as u32);
+            match (num, denom) {
+                (Some(num), Some(denom)) => num::Frac::new(num, denom),
+                _ => return Ok(None)
+            }
+        },
+        _ => return Ok(None)
+    };
+    let power = match exponent.num().abs().to_i64() {
+        Some(power) if (root.num().bits() + root.denom().bits()).saturating_mul(power as u64) <= MAX_POWER_BITS => power as u32,
+        _ => return Ok(None)
+    };
+    let (num, denom) = (root.num().pow(power), root.denom().pow(power));
+    if exponent.is_negative() {
+        return Ok(Some(num::Frac::checked_new(denom, num)?));
+    }
+    return Ok(Some(num::Frac::new(num, denom)));
+}
+
+// Splits a factor into its base and exponent, so x gives x and 1
+fn split_exponent(factor: Expr) -> (Expr, Expr) {
+    if let Operator::Pow = factor.operator {
+        let mut els = factor.elements.expect("Pow has no children").into_iter();
+        let base = els.next().unwrap();
+        return (base, els.next().unwrap());
+    }
+    return (factor, Expr::int(1));
+}
+
+// Adds up the exponents of factors with the same base, so that x * y * x^2 becomes
+// x^3 * y. Like collect_like_terms, bases keep the place of their first occurrence and
+// plain numbers are left for the caller.
+fn collect_powers(factors: &mut Vec<Expr>) -> bool {
+    let mut groups: Vec<(Expr, Vec<Expr>)> = Vec::new();
+    let mut index = HashMap::new();
+    let mut slots: Vec<Result<usize, Expr>> = Vec::new();
+    let mut changed = false;
+    for factor in factors.drain(..) {
+        if matches!(factor.operator, Operator::Frac(_)) || mentions_infinity(&factor) {
+            slots.push(Err(factor));
+            continue;
+        }
+        let (base, exponent) = split_exponent(factor);
+        match index.get(&base) {
+            Some(&idx) => {
+                let group: &mut (Expr, Vec<Expr>) = &mut groups[idx];
+                group.1.push(exponent);
+                changed = true;
+            },
+            None => {
+                index.insert(base.clone(), groups.len());
+                slots.push(Ok(groups.len()));
+                groups.push((base, vec![exponent]));
+            }
+        }
+    }
+
+    let mut groups = groups.into_iter().map(Some).collect::<Vec<Option<(Expr, Vec<Expr>)>>>();
+    for slot in slots.into_iter() {
+        let (base, mut exponents) = match slot {
+            Ok(idx) => groups[idx].take().unwrap(),
+            Err(number) => {
+                factors.push(number);
+                continue;
+            }
+        };
+        let exponent = if exponents.len() == 1 { exponents.pop().unwrap() } else { Expr::new(Operator::Add, Some(exponents)) };
+        factors.push(if exponent == Expr::int(1) { base } else { Expr::pow(base, exponent) });
+    }
+    return changed;
+}
+
+// The most terms an expansion may produce. Products and powers that would multiply out to
+// more are left as they are, so that something like (x + 1)^(10^8) stays as it is written.
+pub(crate) const MAX_EXPANDED_TERMS: usize = 1 << 10;
+
+// The number of terms in (a + b + ...)^power with `terms` terms, C(power + terms - 1, terms - 1),
+// or None once it passes MAX_EXPANDED_TERMS
+pub(crate) fn multinomial_terms(terms: usize, power: u64) -> Option<usize> {
+    let mut count: u128 = 1;
+    for i in 1..terms as u128 {
+        count = count * (power as u128 + i) / i;
+        if count > MAX_EXPANDED_TERMS as u128 {
+            return None;
+        }
+    }
+    return Some(count as usize);
+}
 
-                for child in self.elements.expect("Add has no children").into_iter() {
-                    new_elements.push(child * Expr::frac_cpy(&frac));
+// Multiplies out a product of sums into a sum of products, one for each way of picking a
+// term from every sum
+fn distribute(factors: Vec<Expr>) -> Expr {
+    let mut products: Vec<Vec<Expr>> = vec![Vec::new()];
+    for factor in factors.into_iter() {
+        match factor.operator {
+            Operator::Add => {
+                let terms = factor.elements.expect("Add has no children");
+                products = products.into_iter().flat_map(|product| terms.iter().map(move |term| {
+                    let mut product = product.clone();
+                    product.push(term.clone());
+                    product
+                })).collect();
+            },
+            _ => {
+                for product in products.iter_mut() {
+                    product.push(factor.clone());
                 }
+            }
+        }
+    }
+    return Expr::new(Operator::Add, Some(products.into_iter().map(|product| Expr::new(Operator::Mul, Some(product))).collect()));
+}
 
-                self.elements = Some(new_elements);
-                (self, true)
+// Every way of writing `total` as an ordered sum of `parts` non-negative integers, in
+// descending order of the first part
+fn compositions(total: i64, parts: usize) -> Vec<Vec<i64>> {
+    if parts == 1 {
+        return vec![vec![total]];
+    }
+    let mut out = Vec::new();
+    for first in (0..=total).rev() {
+        for mut rest in compositions(total - first, parts - 1).into_iter() {
+            rest.insert(0, first);
+            out.push(rest);
+        }
+    }
+    return out;
+}
+
+// (a + b + ...)^n as the sum of n! / (i! j! ...) a^i b^j ...
+fn multinomial(terms: &[Expr], power: i64) -> Expr {
+    let mut products = Vec::new();
+    for exponents in compositions(power, terms.len()).into_iter() {
+        let mut coeff = num::Int::from(1);
+        let mut remaining = power;
+        let mut factors = Vec::new();
+        for (term, exponent) in terms.iter().zip(exponents) {
+            // C(remaining, exponent), built up so each division is exact
+            for k in 0..exponent {
+                coeff = &(&coeff * &num::Int::from(remaining - k)) / &num::Int::from(k + 1);
+            }
+            remaining -= exponent;
+            match exponent {
+                0 => {},
+                1 => factors.push(term.clone()),
+                _ => factors.push(Expr::pow(term.clone(), Expr::int(exponent)))
+            }
+        }
+        factors.push(Expr::int(coeff));
+        products.push(Expr::new(Operator::Mul, Some(factors)));
+    }
+    return Expr::new(Operator::Add, Some(products));
+}
+
+// Adds up the coefficients of terms that agree apart from them, so that x + pi + 2*x
+// becomes 3*x + pi. Terms keep the place of their first occurrence and coefficients go
+// last, as everywhere else. Plain numbers are left where they are for the caller to fold.
+fn collect_like_terms(terms: &mut Vec<Expr>) -> Result<bool, NumError> {
+    let mut groups: Vec<(Expr, num::Frac)> = Vec::new();
+    let mut index = HashMap::new();
+    // Each term becomes either a number or the first occurrence of a group
+    let mut slots: Vec<Result<usize, Expr>> = Vec::new();
+    let mut count = 0;
+    let mut changed = false;
+    for term in terms.drain(..) {
+        let (coeff, rest) = match term.operator {
+            Operator::Frac(_) => {
+                slots.push(Err(term));
+                continue;
             },
-            _ => (self * Expr::frac_cpy(&frac), false)
+            _ if mentions_infinity(&term) => {
+                slots.push(Err(term));
+                continue;
+            },
+            _ => split_coefficient(term)
+        };
+        if rest.elements.as_ref().is_some_and(|els| els.is_empty()) {
+            slots.push(Err(Expr::new_empty(Operator::Frac(coeff))));
+            changed = true;
+            continue;
+        }
+        count += 1;
+        // Products match whatever order their factors are in, so that x*y and y*x combine
+        let key = match rest.operator {
+            Operator::Mul => {
+                let mut factors = rest.elements.clone().unwrap();
+                factors.sort_by_cached_key(|factor| factor.to_ascii());
+                Expr::new(Operator::Mul, Some(factors))
+            },
+            _ => rest.clone()
+        };
+        match index.get(&key) {
+            Some(&idx) => {
+                let group: &mut (Expr, num::Frac) = &mut groups[idx];
+                group.1 = group.1.clone().checked_add(coeff)?;
+            },
+            None => {
+                index.insert(key, groups.len());
+                slots.push(Ok(groups.len()));
+                groups.push((rest, coeff));
+            }
         }
     }
 
-    fn expand_mul(self, other: Expr, opts: &SimplifcationOpts) -> (Self, bool) {
-        match other.operator {
-            Operator::Frac(f) => self.mul_frac(f, opts),
-            _ => (self * other, false)
+    changed = changed || groups.len() != count || groups.iter().any(|(_, coeff)| coeff.is_zero());
+    let mut groups = groups.into_iter().map(Some).collect::<Vec<Option<(Expr, num::Frac)>>>();
+    for slot in slots.into_iter() {
+        let (rest, coeff) = match slot {
+            Ok(idx) => groups[idx].take().unwrap(),
+            Err(number) => {
+                terms.push(number);
+                continue;
+            }
+        };
+        if coeff.is_zero() {
+            continue;
         }
+        terms.push(match rest.operator {
+            _ if coeff.is_one() => rest,
+            Operator::Mul => {
+                let mut factors = rest.elements.expect("Mul has no children");
+                factors.push(Expr::new_empty(Operator::Frac(coeff)));
+                Expr::new(Operator::Mul, Some(factors))
+            },
+            _ => rest * Expr::new_empty(Operator::Frac(coeff))
+        });
+    }
+    if terms.is_empty() {
+        terms.push(Expr::int(0));
     }
+    return Ok(changed);
+}
+
+impl Expr {
+    // Stops at the first numeric error, such as a division by zero, leaving the expression
+    // partly simplified
+    pub fn simplify(&mut self, opts: &SimplifcationOpts) -> Result<(), NumError> {
+        let mut a = true;
+        let mut b = false;
+        while a || b {
+            a = self.simplify_impl(opts)?;
+            b = self.flatten_impl(opts);
+            // println!("{}", self.to_ascii());
+        }
+        return Ok(());
+    }
+
+    // Simplifies with the default options, for building results out of intermediate trees
+    pub(crate) fn simplified(mut self) -> Result<Expr, NumError> {
+        self.simplify(&SimplifcationOpts::default())?;
+        return Ok(self);
+    }
+
+    fn simplify_impl(&mut self, opts: &SimplifcationOpts) -> Result<bool, NumError> {
+        // Arithmetic on i and fractions alone folds to an exact im*i + re
+        if self.elements.is_some() && is_gaussian(self) && needs_fold(self) {
+            if let Some(val) = gaussian_value(self)? {
+                *self = gaussian_expr(val);
+                return Ok(true);
+            }
+        }
 
-    fn simplify_impl(&mut self, opts: &SimplifcationOpts) -> bool {
         match &mut self.operator {
-            Operator::Frac(ref mut frac) => {
-                frac.simplify()
+            // Fractions are always stored reduced, so there is nothing to do but validate them
+            Operator::Frac(ref frac) => {
+                if frac.denom().is_zero() {
+                    return Err(NumError::DivisionByZero);
+                }
+                Ok(false)
             },
             Operator::Add => {
                 let mut changed = false;
                 let mut fraccount = 0;
                 let mut haszero = false;
                 for ref mut child in self.elements.as_mut().expect("Add has no children").iter_mut() {
-                    if child.simplify_impl(opts) { changed = true; }
+                    if child.simplify_impl(opts)? { changed = true; }
                     match &child.operator {
                         Operator::Frac(f) => {
                             fraccount += 1;
@@ -82,8 +454,9 @@ impl Expr {
                         _ => {}
                     }
                 }
+                if collect_like_terms(self.elements.as_mut().unwrap())? { changed = true; }
                 if fraccount <= 1 && !haszero {
-                    return changed;
+                    return Ok(changed);
                 }
 
                 let mut frac = num::Frac::new(0, 1);
@@ -93,7 +466,7 @@ impl Expr {
                 for child in self.elements.take().unwrap().into_iter() {
                     match child.operator {
                         Operator::Frac(f) => {
-                            frac = frac + f;
+                            frac = frac.checked_add(f)?;
                             changed = true;
                         },
                         _ => new_elements.push(child)
@@ -108,14 +481,14 @@ impl Expr {
 
                 self.elements = Some(new_elements);
                 
-                return changed;
+                return Ok(changed);
             },
             Operator::Mul => {
                 let mut changed = false;
                 let mut fraccount = 0;
                 let mut iszero = false;
                 for ref mut child in self.elements.as_mut().expect("Mul has no children").iter_mut() {
-                    if child.simplify_impl(opts) { changed = true; }
+                    if child.simplify_impl(opts)? { changed = true; }
 
                     match &child.operator {
                         Operator::Frac(f) => {
@@ -125,11 +498,32 @@ impl Expr {
                         _ => {}
                     }
                 }
+
+                // Multiplies out the factors involving i, so that i*x*i becomes -x
+                let elements = self.elements.as_mut().unwrap();
+                let values = elements.iter().map(|child| if count_i(child) > 0 && is_gaussian(child) { gaussian_value(child) } else { Ok(None) }).collect::<Result<Vec<_>, NumError>>()?;
+                if values.iter().filter(|val| val.is_some()).count() >= 2 {
+                    let mut product = num::Gaussian::real(num::Frac::new(1, 1));
+                    let mut new_elements = Vec::new();
+                    for (child, val) in elements.drain(..).zip(values) {
+                        match val {
+                            Some(val) => product = product.checked_mul(val)?,
+                            None => new_elements.push(child)
+                        }
+                    }
+                    new_elements.push(gaussian_expr(product));
+                    *elements = new_elements;
+                    changed = true;
+                }
                 
+                // 0 * inf has no value, so the product is left as it is
+                if iszero && self.children().iter().any(mentions_infinity) {
+                    return Ok(changed);
+                }
                 if iszero {
                     self.operator = Operator::Frac(num::Frac::new(0, 1));
                     self.elements = None;
-                    return true;
+                    return Ok(true);
                 }
 
                 if fraccount >= 1 {
@@ -140,22 +534,22 @@ impl Expr {
                     for child in self.elements.take().unwrap().into_iter() {
                         match child.operator {
                             Operator::Frac(f) => {
-                                frac = frac * f;
+                                frac = frac.checked_mul(f)?;
                                 if fraccount != 1 { changed = true; }
                             },
                             _ => new_elements.push(child)
                         }
                     }
 
-                    if opts.target_integers && frac.inv().is_int() && !frac.is_one() {
+                    if opts.target_integers && frac.inv().is_int() && !frac.is_one() && !new_elements.is_empty() {
                         self.operator = Operator::Div;
                         self.elements = Some(vec![
                             Expr::new(Operator::Mul, Some(new_elements)),
                             Expr::frac_cpy(&frac.inv())
                         ]);
-                        return true;
+                        return Ok(true);
                     } else {
-                        if !frac.is_one() {
+                        if !frac.is_one() || new_elements.is_empty() {
                             new_elements.push(Expr::new_empty(Operator::Frac(frac)));
                         }
 
@@ -163,77 +557,142 @@ impl Expr {
                     }
                 }
 
-                if opts.expand && self.elements.as_ref().unwrap().len() > 1 {
-                    let mut elements = self.elements.take().unwrap().into_iter();
-                    let mut new = elements.next().unwrap();
-
-                    while let Some(next) = elements.next() {
-                        let (new2, didchange) = new.expand_mul(next, opts);
-                        if didchange {
-                            changed = true;
-                        }
-
-                        new = new2;
+                if collect_powers(self.elements.as_mut().unwrap()) { changed = true; }
+
+                if opts.expand {
+                    let factors = self.elements.as_ref().unwrap();
+                    let count = factors.iter().try_fold(1usize, |count, factor| match &factor.operator {
+                        Operator::Add => Some(count * factor.children().len()).filter(|&count| count <= MAX_EXPANDED_TERMS),
+                        _ => Some(count)
+                    });
+                    if factors.len() >= 2 && count.is_some() && factors.iter().any(|factor| matches!(factor.operator, Operator::Add)) {
+                        *self = distribute(self.elements.take().unwrap());
+                        return Ok(true);
                     }
-
-                    self.operator = new.operator;
-                    self.elements = new.elements;
                 }
 
-                return changed;
+                return Ok(changed);
             },
             Operator::Div => {
                 let mut changed = false;
                 for ref mut child in self.elements.as_mut().expect("Div has no children").iter_mut() {
-                    if child.simplify_impl(opts) { changed = true; }
+                    if child.simplify_impl(opts)? { changed = true; }
                 }
                 
+                let divisor = match &self.elements.as_ref().unwrap().get(1).expect("Div does not have a second child").operator {
+                    Operator::Frac(f) => Some(f.checked_inv()?),
+                    _ => None
+                };
+
                 if !opts.target_integers {
-                    let mut replacement: Option<Expr> = None;
-                    match &self.elements.as_ref().unwrap().get(1).expect("Div does not have a second child").operator {
-                        Operator::Frac(f) => {
-                            replacement = Some(Expr::new_empty(Operator::Frac(f.inv())));
-                        },
-                        _ => {}
-                    }
-                    
-                    if let Some(e) = replacement {
+                    if let Some(f) = divisor {
                         self.operator = Operator::Mul;
-                        self.elements.as_mut().unwrap()[1] = e;
+                        self.elements.as_mut().unwrap()[1] = Expr::new_empty(Operator::Frac(f));
                         changed = true;
                     }
                 }
                 
-                return changed;
+                return Ok(changed);
             },
             Operator::Sub => {
                 let mut changed = false;
                 for ref mut child in self.elements.as_mut().expect("Sub has no children").iter_mut() {
-                    if child.simplify_impl(opts) { changed = true; }
+                    if child.simplify_impl(opts)? { changed = true; }
                 }
-                return changed;
+                return Ok(changed);
             },
             Operator::Pow => {
                 let mut changed = false;
                 for ref mut child in self.elements.as_mut().expect("Pow has no children").iter_mut() {
-                    if child.simplify_impl(opts) { changed = true; }
+                    if child.simplify_impl(opts)? { changed = true; }
                 }
-                return changed;
+
+                // x^1 = x, and x^0 = 1^x = 1 except for inf^0 and 1^inf
+                let els = self.elements.as_ref().unwrap();
+                let exponent = match (&els[0].operator, &els[1].operator) {
+                    (_, Operator::Frac(f)) if f.is_one() => Some(true),
+                    _ if mentions_infinity(&els[0]) || mentions_infinity(&els[1]) => None,
+                    (_, Operator::Frac(f)) if f.is_zero() => Some(false),
+                    (Operator::Frac(f), _) if f.is_one() => Some(false),
+                    _ => None
+                };
+                match exponent {
+                    Some(true) => {
+                        let base = self.elements.take().unwrap().into_iter().next().unwrap();
+                        *self = base;
+                        return Ok(true);
+                    },
+                    Some(false) => {
+                        *self = Expr::int(1);
+                        return Ok(true);
+                    },
+                    None => {}
+                }
+
+                let els = self.elements.as_mut().unwrap();
+                if let (Operator::Frac(base), Operator::Frac(exponent)) = (&els[0].operator, &els[1].operator) {
+                    if let Some(val) = frac_pow(base, exponent)? {
+                        *self = Expr::new_empty(Operator::Frac(val));
+                        return Ok(true);
+                    }
+                }
+
+                // (a^b)^c = a^(bc) holds for any integer c, and for any c when a is positive
+                let fold = match (&els[0].operator, &els[1].operator) {
+                    (Operator::Pow, Operator::Frac(c)) if c.is_int() => true,
+                    (Operator::Pow, _) => match &els[0].elements.as_ref().unwrap()[0].operator {
+                        Operator::Frac(a) => !a.is_negative() && !a.is_zero(),
+                        Operator::Const(Constant::E) | Operator::Const(Constant::Pi) => true,
+                        _ => false
+                    },
+                    _ => false
+                };
+                if fold {
+                    let outer = els.pop().unwrap();
+                    let (base, inner) = split_exponent(els.pop().unwrap());
+                    *els = vec![base, inner * outer];
+                    return Ok(true);
+                }
+
+                let power = match &els[1].operator {
+                    Operator::Frac(f) if f.is_int() && !f.is_negative() => f.num().to_i64(),
+                    _ => None
+                };
+                if let (true, Some(power)) = (opts.expand, power) {
+                    match els[0].operator {
+                        // (ab)^n = a^n b^n
+                        Operator::Mul => {
+                            let exponent = els.pop().unwrap();
+                            let factors = els.pop().unwrap().elements.expect("Mul has no children");
+                            *self = Expr::new(Operator::Mul, Some(factors.into_iter().map(|factor| Expr::pow(factor, exponent.clone())).collect()));
+                            return Ok(true);
+                        },
+                        Operator::Add if multinomial_terms(els[0].children().len(), power as u64).is_some() => {
+                            let terms = els[0].elements.as_ref().unwrap();
+                            *self = multinomial(terms, power);
+                            return Ok(true);
+                        },
+                        _ => {}
+                    }
+                }
+                return Ok(changed);
             },
-            Operator::Name(_) => { false },
+            Operator::Name(_) => { Ok(false) },
             Operator::Func(ref mut f) => {
                 let mut changed = false;
                 for ref mut child in self.elements.as_mut().expect("Func has no children").iter_mut() {
-                    if child.simplify_impl(opts) { changed = true; }
+                    if child.simplify_impl(opts)? { changed = true; }
                 }
                 
                 let val = f.simplify_value(self.elements.take().unwrap());
+                // Such as abs(-2*x) becoming 2*abs(x), whose factors can now be folded
+                if val.operator != self.operator { changed = true; }
                 self.operator = val.operator;
                 self.elements = val.elements;
 
-                return changed;
+                return Ok(changed);
             },
-            Operator::Const(_) => { false }
+            Operator::Const(_) => { Ok(false) }
         }
     }
 
@@ -306,9 +765,8 @@ impl Expr {
                 return changed;
             },
             Operator::Sub => {
-                let mut changed = false;
                 for ref mut child in self.elements.as_mut().expect("Sub has no children").iter_mut() {
-                    if child.flatten_impl(opts) { changed = true; }
+                    child.flatten_impl(opts);
                 }
                 self.operator = Operator::Add;
 
@@ -323,7 +781,8 @@ impl Expr {
                 }
 
                 self.elements = Some(new_elements);
-                return changed;
+                // The new sum still has to be simplified
+                return true;
             },
             Operator::Pow => {
                 let mut changed = false;
@@ -343,4 +802,125 @@ impl Expr {
             Operator::Const(_) => { false }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+    use crate::num::NumError;
+    use crate::parse::parse;
+    use super::SimplifcationOpts;
+
+    #[test]
+    fn division_by_zero_is_reported() {
+        let mut expr = parse("x/0 + 1").unwrap();
+        assert_eq!(expr.simplify(&SimplifcationOpts::default()), Err(NumError::DivisionByZero));
+        let mut expr = parse("x/(2 - 2)").unwrap();
+        assert_eq!(expr.simplify(&SimplifcationOpts::integers()), Err(NumError::DivisionByZero));
+        let mut expr = Expr::var("x") * Expr::frac(3, 0);
+        assert_eq!(expr.simplify(&SimplifcationOpts::expand()), Err(NumError::DivisionByZero));
+
+        let mut expr = parse("x/4 + 1/2 + 1/2").unwrap();
+        assert_eq!(expr.simplify(&SimplifcationOpts::default()), Ok(()));
+        assert_eq!(expr.to_string(), "x*(1/4) + 1");
+    }
+
+    #[test]
+    fn like_terms() {
+        let simplified = |input: &str| {
+            let mut expr = parse(input).unwrap();
+            expr.simplify(&SimplifcationOpts::default()).unwrap();
+            expr.to_string()
+        };
+        assert_eq!(simplified("x + x + 2*x"), "x*4");
+        assert_eq!(simplified("pi + 1 + 2*pi/3"), "pi*(5/3) + 1");
+        assert_eq!(simplified("sin(x) + y - 3*sin(x)"), "sin(x)*-2 + y");
+        assert_eq!(simplified("x*y*3 + 2 + x*y - 2"), "x*y*4");
+        assert_eq!(simplified("x^2 - x^2 + x - x"), "0");
+        assert_eq!(simplified("2*abs(x - 1) + abs(x - 1)"), "abs(x + -1)*3");
+        assert_eq!(simplified("inf - inf"), "inf + inf*-1");
+        assert_eq!(simplified("x*inf + x*inf"), "x*inf + x*inf");
+    }
+
+    #[test]
+    fn infinity_has_no_arithmetic() {
+        let simplified = |input: &str| {
+            let mut expr = parse(input).unwrap();
+            expr.simplify(&SimplifcationOpts::default()).unwrap();
+            expr.to_string()
+        };
+        assert_eq!(simplified("0*inf + 0*x"), "0*inf");
+        assert_eq!(simplified("inf/inf + inf^0 + 1^inf"), "inf/inf + inf^0 + 1^inf");
+        assert_eq!(simplified("inf^1*2"), "inf*2");
+    }
+
+    #[test]
+    fn powers() {
+        let simplified = |input: &str| {
+            let mut expr = parse(input).unwrap();
+            expr.simplify(&SimplifcationOpts::default()).unwrap();
+            expr.to_string()
+        };
+        assert_eq!(simplified("x * x * x^2"), "x^4");
+        assert_eq!(simplified("x^2 * y * x^-2 * 3"), "y*3");
+        assert_eq!(simplified("2^x * y * 2^(1 - x)"), "y*2");
+        assert_eq!(simplified("(x^2)^3 * (x^(1/2))^2"), "x^7");
+        assert_eq!(simplified("(x^2)^(1/2) + (e^x)^y"), "(x^2)^(1/2) + e^(x*y)");
+        assert_eq!(simplified("8^(2/3) + (4/9)^(-1/2) + 2^-2"), "23/4");
+        assert_eq!(simplified("2^(1/2) + (-8)^(1/3)"), "2^(1/2) + (-8)^(1/3)");
+        assert_eq!(simplified("2^(1/100000) + 1^(1/100000)"), "2^(1/100000) + 1");
+        assert_eq!(simplified("x^1 + y^0 + 1^y"), "x + 2");
+
+        let mut expr = parse("x + 0^-1").unwrap();
+        assert_eq!(expr.simplify(&SimplifcationOpts::default()), Err(NumError::DivisionByZero));
+        let mut expr = parse("7^1000000000").unwrap();
+        assert_eq!(expr.simplify(&SimplifcationOpts::default()), Ok(()));
+        assert_eq!(expr.to_string(), "7^1000000000");
+    }
+
+    #[test]
+    fn expansion() {
+        let expanded = |input: &str| {
+            let mut expr = parse(input).unwrap();
+            expr.simplify(&SimplifcationOpts::expand()).unwrap();
+            expr.to_string()
+        };
+        assert_eq!(expanded("(x + 1)*(x - 1)"), "x^2 + -1");
+        assert_eq!(expanded("(x + 1)^3"), "x^3 + x^2*3 + x*3 + 1");
+        assert_eq!(expanded("(x + y)*(x - y)"), "x^2 + y^2*-1");
+        assert_eq!(expanded("(2*x*y)^2 + (a + b + c)^2 - 2*a*b"), "x^2*y^2*4 + a^2 + a*c*2 + b^2 + b*c*2 + c^2");
+        assert_eq!(expanded("(x + 1)*(x^2 - x + 1) - x^3"), "1");
+        assert_eq!(expanded("(x + 1)/2 + (x + 1)^-2"), "x*(1/2) + 1/2 + (x + 1)^-2");
+        assert_eq!(expanded("(x + 1)^100000000"), "(x + 1)^100000000");
+        assert_eq!(expanded("(x + 1)^3000 - 1"), "(x + 1)^3000 + -1");
+        assert_eq!(expanded("(a + b + c + d)^20 * 0"), "0");
+    }
+
+    #[test]
+    fn powers_of_i() {
+        let simplified = |input: &str| {
+            let mut expr = parse(input).unwrap();
+            expr.simplify(&SimplifcationOpts::default()).unwrap();
+            expr
+        };
+        assert_eq!(simplified("i*i"), Expr::int(-1));
+        assert_eq!(simplified("i^7"), Expr::i() * Expr::int(-1));
+        assert_eq!(simplified("i^-2 + x"), Expr::int(-1) + Expr::var("x"));
+        assert_eq!(simplified("(1 + i)^2 / (1 - i)"), Expr::i() + Expr::int(-1));
+        assert_eq!(simplified("(3 + 4*i)*(3 - 4*i)"), Expr::int(25));
+        assert_eq!(simplified("abs(-2*i)"), Expr::int(2));
+        assert_eq!(simplified("abs(x + i)").to_string(), "abs(x + i)");
+        assert_eq!(simplified("(1 + i)^8 + i^-3"), Expr::i() + Expr::int(16));
+        assert_eq!(simplified("(1 + i)^100000000").to_string(), "(1 + i)^100000000");
+        assert_eq!(simplified("(1 + i)^1000000 * i * i").to_string(), "(1 + i)^1000000*-1");
+        assert_eq!(simplified("3^3 - (1/2)^2"), Expr::frac(107, 4));
+        assert_eq!(simplified("2 * (1/2) + x"), Expr::int(1) + Expr::var("x"));
+        assert_eq!(simplified("i*x*y*i"), Expr::new(Operator::Mul, Some(vec![Expr::var("x"), Expr::var("y"), Expr::int(-1)])));
+        assert_eq!(simplified("2*x*i"), Expr::new(Operator::Mul, Some(vec![Expr::var("x"), Expr::i(), Expr::int(2)])));
+
+        let mut expr = parse("1/(i - i)").unwrap();
+        assert_eq!(expr.simplify(&SimplifcationOpts::default()), Err(NumError::DivisionByZero));
+        let mut expr = parse("x*y*z*i").unwrap();
+        assert_eq!(expr.simplify(&SimplifcationOpts::expand()), Ok(()));
+    }
+}